@@ -0,0 +1,2 @@
+-- Storage class requested at initiation, applied to the object on completion
+ALTER TABLE multipart_upload ADD COLUMN IF NOT EXISTS storage_class VARCHAR(50);
@@ -0,0 +1,2 @@
+-- Reverse lookup of on-disk files to their tracked item
+CREATE INDEX IF NOT EXISTS idx_s3_item_detail_data_location ON s3_item_detail (data_location);
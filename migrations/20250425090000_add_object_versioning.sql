@@ -0,0 +1,11 @@
+-- Objects written while versioning is not enabled keep the literal "null" version id
+ALTER TABLE s3_item_detail ADD COLUMN IF NOT EXISTS version_id VARCHAR(64) NOT NULL DEFAULT 'null';
+ALTER TABLE s3_item_detail DROP CONSTRAINT IF EXISTS s3_item_detail_pkey;
+ALTER TABLE s3_item_detail ADD PRIMARY KEY (bucket, key, version_id);
+
+CREATE TABLE IF NOT EXISTS bucket_config (
+    bucket VARCHAR(50) NOT NULL,
+    -- Enabled or Suspended, NULL when versioning was never configured
+    versioning_status VARCHAR(20),
+    PRIMARY KEY (bucket)
+);
@@ -0,0 +1,2 @@
+-- Deleting from a versioned bucket leaves a delete marker instead of removing data
+ALTER TABLE s3_item_detail ADD COLUMN IF NOT EXISTS delete_marker BOOLEAN NOT NULL DEFAULT FALSE;
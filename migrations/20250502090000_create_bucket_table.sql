@@ -0,0 +1,15 @@
+-- Buckets are tracked here so empty buckets are listed too
+CREATE TABLE IF NOT EXISTS bucket (
+    name VARCHAR(50) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    -- Access key of the creator, NULL for buckets created before this table existed
+    owner VARCHAR(255),
+    PRIMARY KEY (name)
+);
+
+-- Buckets created earlier are only known through their objects
+INSERT INTO bucket (name, created_at)
+SELECT bucket, MIN(last_modified)
+FROM s3_item_detail
+GROUP BY bucket
+ON CONFLICT (name) DO NOTHING;
@@ -0,0 +1,2 @@
+-- BucketOwnerEnforced, BucketOwnerPreferred or ObjectWriter, NULL when ownership controls were never configured
+ALTER TABLE bucket_config ADD COLUMN IF NOT EXISTS object_ownership VARCHAR(32);
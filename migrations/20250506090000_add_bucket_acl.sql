@@ -0,0 +1,2 @@
+-- Canned ACL of the bucket (private, public-read, ...), NULL when none was set
+ALTER TABLE bucket_config ADD COLUMN IF NOT EXISTS acl VARCHAR(32);
@@ -0,0 +1,2 @@
+-- Content headers given when a multipart upload is initiated, applied on completion
+ALTER TABLE multipart_upload ADD COLUMN IF NOT EXISTS content_headers TEXT;
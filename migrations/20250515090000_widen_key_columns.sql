@@ -0,0 +1,10 @@
+-- Keys are up to 1024 bytes. The data location of a key grows with escaped segments,
+-- and the one of a part is an absolute path, neither has a useful bound.
+ALTER TABLE s3_item_detail ALTER COLUMN key TYPE VARCHAR(1024);
+ALTER TABLE multipart_upload ALTER COLUMN key TYPE VARCHAR(1024);
+
+-- Only looked up by equality, a hash index has no limit on the size of an entry
+DROP INDEX IF EXISTS idx_s3_item_detail_data_location;
+ALTER TABLE s3_item_detail ALTER COLUMN data_location TYPE TEXT;
+CREATE INDEX IF NOT EXISTS idx_s3_item_detail_data_location ON s3_item_detail USING HASH (data_location);
+ALTER TABLE multipart_upload_part ALTER COLUMN data_location TYPE TEXT;
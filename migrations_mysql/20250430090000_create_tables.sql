@@ -0,0 +1,46 @@
+-- MySQL / MariaDB schema, kept in step with the Postgres migrations in ./migrations
+CREATE TABLE IF NOT EXISTS s3_item_detail (
+    bucket VARCHAR(50) NOT NULL,
+    `key` VARCHAR(255) NOT NULL,
+    -- Objects written while versioning is not enabled keep the literal "null" version id
+    version_id VARCHAR(64) NOT NULL DEFAULT 'null',
+    metadata TEXT NOT NULL,
+    internal_info TEXT NOT NULL,
+    last_modified DATETIME(6) NOT NULL,
+    md5 VARCHAR(255) NOT NULL,
+    -- The length of this field is the size of bucket and key together
+    data_location VARCHAR(310) NOT NULL,
+    delete_marker BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (bucket, `key`, version_id),
+    INDEX idx_s3_item_detail_data_location (data_location)
+) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin;
+
+CREATE TABLE IF NOT EXISTS multipart_upload (
+    upload_id VARCHAR(255) NOT NULL,
+    bucket VARCHAR(50) NOT NULL,
+    `key` VARCHAR(255) NOT NULL,
+    metadata TEXT NOT NULL,
+    last_modified DATETIME(6) NOT NULL,
+    access_key VARCHAR(255) NOT NULL,
+    storage_class VARCHAR(50),
+    PRIMARY KEY (upload_id),
+    UNIQUE (upload_id, bucket, `key`)
+) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin;
+
+CREATE TABLE IF NOT EXISTS multipart_upload_part (
+    upload_id VARCHAR(255) NOT NULL,
+    part_number INTEGER NOT NULL,
+    last_modified DATETIME(6) NOT NULL,
+    md5 VARCHAR(255) NOT NULL,
+    -- The length of this field is the size of bucket and key together
+    data_location VARCHAR(310) NOT NULL,
+    PRIMARY KEY (upload_id, part_number),
+    FOREIGN KEY (upload_id) REFERENCES multipart_upload(upload_id) ON DELETE CASCADE
+) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin;
+
+CREATE TABLE IF NOT EXISTS bucket_config (
+    bucket VARCHAR(50) NOT NULL,
+    -- Enabled or Suspended, NULL when versioning was never configured
+    versioning_status VARCHAR(20),
+    PRIMARY KEY (bucket)
+) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin;
@@ -0,0 +1,14 @@
+-- Buckets are tracked here so empty buckets are listed too
+CREATE TABLE IF NOT EXISTS bucket (
+    name VARCHAR(50) NOT NULL,
+    created_at DATETIME(6) NOT NULL,
+    -- Access key of the creator, NULL for buckets created before this table existed
+    owner VARCHAR(255),
+    PRIMARY KEY (name)
+) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin;
+
+-- Buckets created earlier are only known through their objects
+INSERT IGNORE INTO bucket (name, created_at)
+SELECT bucket, MIN(last_modified)
+FROM s3_item_detail
+GROUP BY bucket;
@@ -0,0 +1,2 @@
+-- Content type given to objects uploaded without one, NULL for application/octet-stream
+ALTER TABLE bucket_config ADD COLUMN default_content_type VARCHAR(255);
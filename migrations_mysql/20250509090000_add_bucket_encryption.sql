@@ -0,0 +1,2 @@
+-- Default encryption configuration as JSON, only stored and returned
+ALTER TABLE bucket_config ADD COLUMN encryption TEXT;
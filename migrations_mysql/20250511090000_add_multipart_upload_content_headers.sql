@@ -0,0 +1,2 @@
+-- Content headers given when a multipart upload is initiated, applied on completion
+ALTER TABLE multipart_upload ADD COLUMN content_headers TEXT;
@@ -0,0 +1,2 @@
+-- Lifecycle expiration rules as JSON
+ALTER TABLE bucket_config ADD COLUMN lifecycle TEXT;
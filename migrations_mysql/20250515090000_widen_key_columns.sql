@@ -0,0 +1,21 @@
+-- Keys are up to 1024 bytes. The data location of a key grows with escaped segments,
+-- and the one of a part is an absolute path, neither has a useful bound.
+--
+-- An index entry is at most 3072 bytes, too little for a key of 1024 characters in
+-- utf8mb4. Objects are unique by the hash of their key, and listings use a prefix of it.
+ALTER TABLE s3_item_detail
+    DROP PRIMARY KEY,
+    DROP INDEX idx_s3_item_detail_data_location,
+    MODIFY `key` VARCHAR(1024) NOT NULL,
+    MODIFY data_location TEXT NOT NULL,
+    ADD COLUMN key_hash BINARY(16) AS (UNHEX(MD5(`key`))) STORED,
+    ADD UNIQUE INDEX idx_s3_item_detail_key_hash (bucket, key_hash, version_id),
+    ADD INDEX idx_s3_item_detail_key (bucket, `key`(700)),
+    ADD INDEX idx_s3_item_detail_data_location (data_location(768));
+
+-- The upload id alone is unique already
+ALTER TABLE multipart_upload
+    DROP INDEX upload_id,
+    MODIFY `key` VARCHAR(1024) NOT NULL;
+
+ALTER TABLE multipart_upload_part MODIFY data_location TEXT NOT NULL;
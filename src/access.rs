@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use s3s::{
+    S3Result,
+    access::{S3Access, S3AccessContext},
+    path::S3Path,
+    s3_error,
+};
+
+use crate::{DataStore, storage_backend::StorageBackend, utils};
+
+/// Lets unsigned requests read what a canned ACL makes public, anything else still has
+/// to be signed.
+///
+/// `GetObject` and `HeadObject` are allowed on objects whose ACL is `public-read` or
+/// `public-read-write`, and the listings and `HeadBucket` on buckets with such an ACL, as
+/// on S3 where the bucket ACL does not open its objects. Buckets with ACLs disabled by
+/// `BucketOwnerEnforced` are never public.
+#[derive(Debug)]
+pub struct PublicReadAccess<T: DataStore> {
+    fs: StorageBackend<T>,
+}
+
+impl<T: DataStore> PublicReadAccess<T> {
+    /// Checks the ACLs through `fs`, which should not cache object metadata so that an
+    /// ACL made private takes effect right away.
+    #[must_use]
+    pub fn new(fs: StorageBackend<T>) -> Self {
+        Self { fs }
+    }
+}
+
+/// The bucket and key an unsigned `op` on `path` reads, the key is `None` for listings.
+fn public_read_target<'a>(op: &str, path: &'a S3Path) -> Option<(&'a str, Option<&'a str>)> {
+    match (op, path) {
+        ("GetObject" | "HeadObject", S3Path::Object { bucket, key }) => Some((bucket, Some(key))),
+        ("ListObjects" | "ListObjectsV2" | "HeadBucket", S3Path::Bucket { bucket }) => {
+            Some((bucket, None))
+        }
+        _ => None,
+    }
+}
+
+/// The raw, still percent-encoded, value of the query parameter `name`.
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then_some(value)
+    })
+}
+
+#[async_trait]
+impl<T: DataStore> S3Access for PublicReadAccess<T> {
+    async fn check(&self, cx: &mut S3AccessContext<'_>) -> S3Result<()> {
+        if cx.credentials().is_some() {
+            return Ok(());
+        }
+        if let Some((bucket, key)) = public_read_target(cx.s3_op().name(), cx.s3_path()) {
+            let version_id = match query_param(cx.uri().query(), "versionId") {
+                Some(version_id) => Some(
+                    utils::url_decode(version_id)
+                        .ok_or_else(|| s3_error!(InvalidArgument, "Invalid version id"))?,
+                ),
+                None => None,
+            };
+            if self
+                .fs
+                .is_public_read(bucket, key, version_id.as_deref())
+                .await?
+            {
+                return Ok(());
+            }
+        }
+        Err(s3_error!(AccessDenied, "Signature is required"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_read_target() {
+        let object = S3Path::Object {
+            bucket: "bucket".into(),
+            key: "a/b.txt".into(),
+        };
+        let bucket = S3Path::Bucket {
+            bucket: "bucket".into(),
+        };
+        assert_eq!(
+            public_read_target("GetObject", &object),
+            Some(("bucket", Some("a/b.txt")))
+        );
+        assert_eq!(
+            public_read_target("HeadObject", &object),
+            Some(("bucket", Some("a/b.txt")))
+        );
+        assert_eq!(
+            public_read_target("ListObjectsV2", &bucket),
+            Some(("bucket", None))
+        );
+        assert_eq!(public_read_target("PutObject", &object), None);
+        assert_eq!(public_read_target("GetObjectAcl", &object), None);
+        assert_eq!(public_read_target("DeleteBucket", &bucket), None);
+        assert_eq!(public_read_target("ListBuckets", &S3Path::Root), None);
+    }
+
+    #[test]
+    fn test_query_param() {
+        assert_eq!(query_param(Some("versionId=v1"), "versionId"), Some("v1"));
+        assert_eq!(
+            query_param(Some("partNumber=1&versionId=v2"), "versionId"),
+            Some("v2")
+        );
+        assert_eq!(query_param(Some("versionIds=v1"), "versionId"), None);
+        assert_eq!(query_param(None, "versionId"), None);
+    }
+
+    #[test]
+    fn test_url_decode() {
+        assert_eq!(utils::url_decode("v1").as_deref(), Some("v1"));
+        assert_eq!(utils::url_decode("a%2Fb%3D").as_deref(), Some("a/b="));
+        assert_eq!(utils::url_decode("a+b").as_deref(), Some("a b"));
+        assert_eq!(utils::url_decode("100%").as_deref(), Some("100%"));
+        assert_eq!(utils::url_decode("%+5").as_deref(), Some("% 5"));
+        assert_eq!(utils::url_decode("%FF"), None);
+    }
+}
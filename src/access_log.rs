@@ -0,0 +1,212 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use hyper::{Method, Request, Response, header};
+use tower::{Layer, Service};
+use tracing::info;
+
+/// Emits one `access` log event per request with the S3 operation, the remote address,
+/// the response status and the elapsed time.
+///
+/// A layer is created per connection since the remote address is only known at accept time.
+#[derive(Debug, Clone, Copy)]
+pub struct AccessLogLayer {
+    remote_addr: SocketAddr,
+}
+
+impl AccessLogLayer {
+    #[must_use]
+    pub fn new(remote_addr: SocketAddr) -> Self {
+        Self { remote_addr }
+    }
+}
+
+impl<S> Layer<S> for AccessLogLayer {
+    type Service = AccessLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AccessLogService {
+            inner,
+            remote_addr: self.remote_addr,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessLogService<S> {
+    inner: S,
+    remote_addr: SocketAddr,
+}
+
+/// Best effort name of the S3 operation addressed by a path-style request.
+fn operation_name(method: &Method, path: &str, query: Option<&str>) -> &'static str {
+    let has = |param: &str| {
+        query.is_some_and(|q| {
+            q.split('&')
+                .any(|pair| pair.split('=').next() == Some(param))
+        })
+    };
+    let is_object = path
+        .trim_start_matches('/')
+        .split_once('/')
+        .is_some_and(|(_, key)| !key.is_empty());
+    let is_service = path.trim_matches('/').is_empty();
+
+    match *method {
+        Method::GET if is_service => "ListBuckets",
+        Method::GET if is_object && has("uploadId") => "ListParts",
+        Method::GET if is_object && has("acl") => "GetObjectAcl",
+        Method::GET if is_object => "GetObject",
+        Method::GET if has("versioning") => "GetBucketVersioning",
+        Method::GET if has("ownershipControls") => "GetBucketOwnershipControls",
+        Method::GET if has("acl") => "GetBucketAcl",
+        Method::GET if has("location") => "GetBucketLocation",
+        Method::GET if has("versions") => "ListObjectVersions",
+        Method::GET if has("uploads") => "ListMultipartUploads",
+        Method::GET if has("list-type") => "ListObjectsV2",
+        Method::GET => "ListObjects",
+        Method::HEAD if is_object => "HeadObject",
+        Method::HEAD => "HeadBucket",
+        Method::PUT if is_object && has("partNumber") => "UploadPart",
+        Method::PUT if is_object && has("acl") => "PutObjectAcl",
+        Method::PUT if is_object => "PutObject",
+        Method::PUT if has("versioning") => "PutBucketVersioning",
+        Method::PUT if has("ownershipControls") => "PutBucketOwnershipControls",
+        Method::PUT if has("acl") => "PutBucketAcl",
+        Method::PUT => "CreateBucket",
+        Method::POST if has("uploads") => "CreateMultipartUpload",
+        Method::POST if has("uploadId") => "CompleteMultipartUpload",
+        Method::POST if has("delete") => "DeleteObjects",
+        Method::POST if is_object && has("restore") => "RestoreObject",
+        Method::DELETE if is_object && has("uploadId") => "AbortMultipartUpload",
+        Method::DELETE if is_object => "DeleteObject",
+        Method::DELETE => "DeleteBucket",
+        _ => "Unknown",
+    }
+}
+
+impl<S, B, RB> Service<Request<B>> for AccessLogService<S>
+where
+    S: Service<Request<B>, Response = Response<RB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    RB: Send + 'static,
+{
+    type Response = Response<RB>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let start = Instant::now();
+        let remote_addr = self.remote_addr;
+        let method = req.method().clone();
+        let path = req.uri().path().to_owned();
+        let operation = operation_name(&method, &path, req.uri().query());
+
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await?;
+            let bytes = res
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok());
+            info!(
+                target: "access",
+                operation,
+                %remote_addr,
+                %method,
+                path,
+                status = res.status().as_u16(),
+                bytes,
+                elapsed_ms = start.elapsed().as_millis(),
+            );
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::StatusCode;
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+
+    #[test]
+    fn test_operation_name() {
+        let cases = [
+            (Method::GET, "/", None, "ListBuckets"),
+            (Method::GET, "/bucket", Some("list-type=2"), "ListObjectsV2"),
+            (
+                Method::GET,
+                "/bucket/",
+                Some("versioning"),
+                "GetBucketVersioning",
+            ),
+            (Method::GET, "/bucket/a/b", None, "GetObject"),
+            (Method::GET, "/bucket/key", Some("uploadId=1"), "ListParts"),
+            (Method::HEAD, "/bucket", None, "HeadBucket"),
+            (Method::PUT, "/bucket/key", None, "PutObject"),
+            (
+                Method::PUT,
+                "/bucket/key",
+                Some("partNumber=1&uploadId=1"),
+                "UploadPart",
+            ),
+            (Method::PUT, "/bucket/key", Some("acl"), "PutObjectAcl"),
+            (Method::PUT, "/bucket", Some("acl"), "PutBucketAcl"),
+            (Method::GET, "/bucket/key", Some("acl"), "GetObjectAcl"),
+            (Method::GET, "/bucket", Some("acl"), "GetBucketAcl"),
+            (Method::PUT, "/bucket", None, "CreateBucket"),
+            (
+                Method::POST,
+                "/bucket/key",
+                Some("uploads"),
+                "CreateMultipartUpload",
+            ),
+            (
+                Method::POST,
+                "/bucket/key",
+                Some("restore"),
+                "RestoreObject",
+            ),
+            (Method::DELETE, "/bucket/key", None, "DeleteObject"),
+            (Method::DELETE, "/bucket", None, "DeleteBucket"),
+            (Method::PATCH, "/bucket", None, "Unknown"),
+        ];
+        for (method, path, query, expected) in cases {
+            assert_eq!(
+                operation_name(&method, path, query),
+                expected,
+                "{method} {path}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_passes_through() {
+        let inner = service_fn(|_req: Request<()>| async {
+            let mut res = Response::new("s3".to_string());
+            *res.status_mut() = StatusCode::NOT_FOUND;
+            Ok::<_, Infallible>(res)
+        });
+        let svc = AccessLogLayer::new(([127, 0, 0, 1], 4321).into()).layer(inner);
+        let req = Request::builder().uri("/bucket/key").body(()).unwrap();
+
+        let res = svc.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.body(), "s3");
+    }
+}
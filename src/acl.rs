@@ -0,0 +1,172 @@
+use s3s::{
+    S3Result,
+    dto::{BucketCannedACL, Grant, Grantee, Grants, ObjectCannedACL, Owner, Permission, Type},
+    s3_error,
+};
+
+use crate::storage_backend::InternalInfo;
+
+/// Canned ACL of buckets and objects stored without one.
+pub const DEFAULT_ACL: &str = BucketCannedACL::PRIVATE;
+
+const ALL_USERS: &str = "http://acs.amazonaws.com/groups/global/AllUsers";
+const AUTHENTICATED_USERS: &str = "http://acs.amazonaws.com/groups/global/AuthenticatedUsers";
+
+pub fn is_bucket_canned_acl(acl: &str) -> bool {
+    [
+        BucketCannedACL::PRIVATE,
+        BucketCannedACL::PUBLIC_READ,
+        BucketCannedACL::PUBLIC_READ_WRITE,
+        BucketCannedACL::AUTHENTICATED_READ,
+    ]
+    .contains(&acl)
+}
+
+pub fn is_object_canned_acl(acl: &str) -> bool {
+    [
+        ObjectCannedACL::PRIVATE,
+        ObjectCannedACL::PUBLIC_READ,
+        ObjectCannedACL::PUBLIC_READ_WRITE,
+        ObjectCannedACL::AUTHENTICATED_READ,
+        ObjectCannedACL::AWS_EXEC_READ,
+        ObjectCannedACL::BUCKET_OWNER_READ,
+        ObjectCannedACL::BUCKET_OWNER_FULL_CONTROL,
+    ]
+    .contains(&acl)
+}
+
+/// The canned ACL of a `Put*Acl` request, only canned ACLs are supported.
+pub fn check_request(
+    acl: Option<&str>,
+    has_grants: bool,
+    is_canned: fn(&str) -> bool,
+) -> S3Result<String> {
+    if has_grants {
+        return Err(s3_error!(
+            NotImplemented,
+            "only canned ACLs are supported, grants are not"
+        ));
+    }
+    let Some(acl) = acl else {
+        return Err(s3_error!(MalformedACLError, "a canned ACL is required"));
+    };
+    if !is_canned(acl) {
+        return Err(s3_error!(InvalidArgument, "unknown canned ACL {acl}"));
+    }
+    Ok(acl.to_owned())
+}
+
+pub fn owner(access_key: &str) -> Owner {
+    Owner {
+        display_name: Some(access_key.to_owned()),
+        id: Some(access_key.to_owned()),
+    }
+}
+
+fn grant(grantee: Grantee, permission: &'static str) -> Grant {
+    Grant {
+        grantee: Some(grantee),
+        permission: Some(Permission::from_static(permission)),
+    }
+}
+
+fn group(uri: &str) -> Grantee {
+    Grantee {
+        display_name: None,
+        email_address: None,
+        id: None,
+        type_: Type::from_static(Type::GROUP),
+        uri: Some(uri.to_owned()),
+    }
+}
+
+/// Grants a canned ACL stands for.
+///
+/// Objects are owned by the bucket owner, so the `bucket-owner-*` ACLs come down to the
+/// owner's full control.
+pub fn grants(acl: &str, owner: &Owner) -> Grants {
+    let owner = Grantee {
+        display_name: owner.display_name.clone(),
+        email_address: None,
+        id: owner.id.clone(),
+        type_: Type::from_static(Type::CANONICAL_USER),
+        uri: None,
+    };
+    let mut grants = vec![grant(owner, Permission::FULL_CONTROL)];
+    match acl {
+        BucketCannedACL::PUBLIC_READ => grants.push(grant(group(ALL_USERS), Permission::READ)),
+        BucketCannedACL::PUBLIC_READ_WRITE => {
+            grants.push(grant(group(ALL_USERS), Permission::READ));
+            grants.push(grant(group(ALL_USERS), Permission::WRITE));
+        }
+        BucketCannedACL::AUTHENTICATED_READ => {
+            grants.push(grant(group(AUTHENTICATED_USERS), Permission::READ));
+        }
+        _ => {}
+    }
+    grants
+}
+
+/// Whether a canned ACL lets anyone read, without signing requests.
+pub fn is_public_read(acl: &str) -> bool {
+    [
+        BucketCannedACL::PUBLIC_READ,
+        BucketCannedACL::PUBLIC_READ_WRITE,
+    ]
+    .contains(&acl)
+}
+
+pub fn modify_internal_info(info: &mut InternalInfo, acl: &str) {
+    info.insert("acl".to_owned(), serde_json::Value::String(acl.to_owned()));
+}
+
+/// The canned ACL of an object, [`DEFAULT_ACL`] when none was set.
+pub fn from_internal_info(info: &InternalInfo) -> String {
+    info.get("acl")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_ACL)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uris(grants: &Grants) -> Vec<(Option<&str>, &str)> {
+        grants
+            .iter()
+            .map(|g| {
+                (
+                    g.grantee.as_ref().unwrap().uri.as_deref(),
+                    g.permission.as_ref().unwrap().as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grants() {
+        let owner = owner("owner");
+        assert_eq!(uris(&grants("private", &owner)), [(None, "FULL_CONTROL")]);
+        assert_eq!(
+            uris(&grants("public-read-write", &owner)),
+            [
+                (None, "FULL_CONTROL"),
+                (Some(ALL_USERS), "READ"),
+                (Some(ALL_USERS), "WRITE")
+            ]
+        );
+        assert_eq!(
+            uris(&grants("authenticated-read", &owner)),
+            [(None, "FULL_CONTROL"), (Some(AUTHENTICATED_USERS), "READ")]
+        );
+    }
+
+    #[test]
+    fn test_internal_info_round_trip() {
+        let mut info = InternalInfo::new();
+        assert_eq!(from_internal_info(&info), "private");
+        modify_internal_info(&mut info, "public-read");
+        assert_eq!(from_internal_info(&info), "public-read");
+    }
+}
@@ -0,0 +1,513 @@
+use std::{
+    convert::Infallible,
+    future::{Future, Ready, ready},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use hyper::{Method, Request, Response, StatusCode, header};
+use tower::{Layer, Service};
+
+use crate::{DataStore, DatabaseHealth, cache::Caches, metrics::Metrics};
+
+// `_admin` is not a valid bucket name, so the routes never shadow a bucket on the S3 port
+const METRICS_PATH: &str = "/_admin/metrics";
+const HEALTH_PATH: &str = "/_admin/health";
+const READYZ_PATH: &str = "/_admin/readyz";
+const FLUSH_CACHES_PATH: &str = "/_admin/flush-caches";
+
+/// What `GET /_admin/readyz` checks before reporting the server ready, every [`DataStore`] is one.
+#[async_trait]
+pub trait ReadinessCheck: Send + Sync + std::fmt::Debug + 'static {
+    async fn check(&self) -> DatabaseHealth;
+}
+
+#[async_trait]
+impl<T: DataStore> ReadinessCheck for T {
+    async fn check(&self) -> DatabaseHealth {
+        self.health().await
+    }
+}
+
+/// Serves the operational routes (`/_admin/metrics`, `/_admin/health`, `/_admin/readyz`
+/// and `/_admin/flush-caches`) in front of the S3 service.
+///
+/// Only bare requests without a query string are answered here, everything
+/// else is passed through to the wrapped service.
+#[derive(Debug, Clone)]
+pub struct AdminLayer {
+    metrics: Arc<Metrics>,
+    caches: Caches,
+    token: Option<Arc<str>>,
+    readiness: Option<Arc<dyn ReadinessCheck>>,
+}
+
+impl AdminLayer {
+    #[must_use]
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self {
+            metrics,
+            caches: Caches::default(),
+            token: None,
+            readiness: None,
+        }
+    }
+
+    /// Requires `Authorization: Bearer <token>` on the admin routes, without a token every
+    /// admin route answers `401 Unauthorized`.
+    #[must_use]
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.map(Arc::from);
+        self
+    }
+
+    /// Caches dropped by `POST /_admin/flush-caches`.
+    #[must_use]
+    pub fn with_caches(mut self, caches: Caches) -> Self {
+        self.caches = caches;
+        self
+    }
+
+    /// Serves `GET /_admin/readyz`, which answers `503 Service Unavailable` while `readiness`
+    /// fails. Without one the route is left to the S3 service.
+    #[must_use]
+    pub fn with_readiness(mut self, readiness: impl ReadinessCheck) -> Self {
+        self.readiness = Some(Arc::new(readiness));
+        self
+    }
+}
+
+impl<S> Layer<S> for AdminLayer {
+    type Service = AdminService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdminService {
+            inner,
+            metrics: Arc::clone(&self.metrics),
+            caches: self.caches.clone(),
+            token: self.token.clone(),
+            readiness: self.readiness.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminService<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+    caches: Caches,
+    token: Option<Arc<str>>,
+    readiness: Option<Arc<dyn ReadinessCheck>>,
+}
+
+fn response<RB>(status: StatusCode, content_type: &'static str, body: String) -> Response<RB>
+where
+    RB: From<String>,
+{
+    let mut res = Response::new(RB::from(body));
+    *res.status_mut() = status;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static(content_type),
+    );
+    res
+}
+
+/// Answers `GET /_admin/readyz`, a slow database is still ready, only a failed check is not.
+async fn readiness<RB>(readiness: Arc<dyn ReadinessCheck>) -> Response<RB>
+where
+    RB: From<String>,
+{
+    let health = readiness.check().await;
+    let (status, text) = if health.ok {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    };
+    let body = serde_json::json!({
+        "status": text,
+        "database": {
+            "ok": health.ok,
+            "latency_ms": health.latency_ms,
+            "pool_size": health.pool_size,
+            "idle": health.idle,
+        },
+    });
+    response(status, "application/json", body.to_string())
+}
+
+/// Compares without returning early on the first differing byte, so the time taken
+/// does not tell how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl<S> AdminService<S> {
+    fn is_admin_route<B>(&self, req: &Request<B>) -> bool {
+        if req.uri().query().is_some() {
+            return false;
+        }
+        match req.uri().path() {
+            FLUSH_CACHES_PATH => req.method() == Method::POST,
+            METRICS_PATH | HEALTH_PATH => req.method() == Method::GET,
+            READYZ_PATH => req.method() == Method::GET && self.readiness.is_some(),
+            _ => false,
+        }
+    }
+
+    fn is_authorized<B>(&self, req: &Request<B>) -> bool {
+        let Some(token) = &self.token else {
+            return false;
+        };
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|v| constant_time_eq(v.as_bytes(), token.as_bytes()))
+    }
+
+    fn route<B, RB>(&self, req: &Request<B>) -> Option<Response<RB>>
+    where
+        RB: From<String>,
+    {
+        if !self.is_admin_route(req) {
+            return None;
+        }
+
+        if !self.is_authorized(req) {
+            let mut res = Response::new(RB::from(String::new()));
+            *res.status_mut() = StatusCode::UNAUTHORIZED;
+            res.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+            return Some(res);
+        }
+
+        if req.uri().path() == FLUSH_CACHES_PATH {
+            self.caches.flush();
+            let mut res = Response::new(RB::from(String::new()));
+            *res.status_mut() = StatusCode::NO_CONTENT;
+            return Some(res);
+        }
+
+        let (content_type, body) = match req.uri().path() {
+            METRICS_PATH => ("text/plain; version=0.0.4", self.metrics.render()),
+            HEALTH_PATH => {
+                let disk = self.metrics.disk_usage();
+                let health = serde_json::json!({
+                    "status": "ok",
+                    "active_connections": self.metrics.active_connections(),
+                    "disk": {
+                        "total_bytes": disk.total_bytes,
+                        "free_bytes": disk.free_bytes,
+                        "used_bytes": disk.used_bytes,
+                    },
+                });
+                ("application/json", health.to_string())
+            }
+            // answered once the check is done
+            _ => return None,
+        };
+        Some(response(StatusCode::OK, content_type, body))
+    }
+}
+
+impl<S, B, RB> Service<Request<B>> for AdminService<S>
+where
+    S: Service<Request<B>, Response = Response<RB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    RB: From<String> + Send + 'static,
+{
+    type Response = Response<RB>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if let Some(res) = self.route(&req) {
+            return Box::pin(async move { Ok(res) });
+        }
+        if req.uri().path() == READYZ_PATH
+            && self.is_admin_route(&req)
+            && let Some(check) = self.readiness.clone()
+        {
+            return Box::pin(async move { Ok(readiness(check).await) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+/// Answers every request with `404 Not Found`, the service behind [`AdminLayer`]
+/// when the admin routes are served on a listener of their own.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NotFound;
+
+impl<B> Service<Request<B>> for NotFound {
+    type Response = Response<s3s::Body>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: Request<B>) -> Self::Future {
+        let mut res = Response::new(s3s::Body::empty());
+        *res.status_mut() = StatusCode::NOT_FOUND;
+        ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, num::NonZeroUsize, time::Duration};
+
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+    use crate::{S3ItemDetail, cache::ItemCache};
+
+    async fn call(metrics: Arc<Metrics>, method: Method, uri: &str) -> Response<String> {
+        call_with_caches(metrics, Caches::default(), method, uri).await
+    }
+
+    async fn call_with_caches(
+        metrics: Arc<Metrics>,
+        caches: Caches,
+        method: Method,
+        uri: &str,
+    ) -> Response<String> {
+        let inner = service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new("s3".to_string()))
+        });
+        let svc = AdminLayer::new(metrics)
+            .with_caches(caches)
+            .with_token(Some("secret".to_string()))
+            .layer(inner);
+        svc.oneshot(authorized(Request::builder().method(method).uri(uri)))
+            .await
+            .unwrap()
+    }
+
+    fn authorized(req: hyper::http::request::Builder) -> Request<()> {
+        req.header(header::AUTHORIZATION, "Bearer secret")
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route() {
+        let metrics = Metrics::new();
+        let _guard = metrics.connection_opened();
+
+        let res = call(metrics, Method::GET, METRICS_PATH).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.body().contains("beggar_active_connections 1"));
+    }
+
+    #[tokio::test]
+    async fn test_health_route() {
+        let metrics = Metrics::new();
+
+        let res = call(metrics, Method::GET, HEALTH_PATH).await;
+        let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["active_connections"], 0);
+        assert_eq!(body["disk"]["total_bytes"], 0);
+    }
+
+    #[derive(Debug)]
+    struct Database(bool);
+
+    #[async_trait]
+    impl ReadinessCheck for Database {
+        async fn check(&self) -> DatabaseHealth {
+            DatabaseHealth {
+                ok: self.0,
+                latency_ms: 3,
+                pool_size: 4,
+                idle: 2,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readyz_route() {
+        let readyz = |admin: AdminLayer| {
+            let inner = service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new("s3".to_string()))
+            });
+            let req = authorized(Request::builder().uri(READYZ_PATH));
+            admin
+                .with_token(Some("secret".to_string()))
+                .layer(inner)
+                .oneshot(req)
+        };
+
+        let res = readyz(AdminLayer::new(Metrics::new()).with_readiness(Database(true)))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["status"], "ready");
+        assert_eq!(body["database"]["latency_ms"], 3);
+        assert_eq!(body["database"]["pool_size"], 4);
+        assert_eq!(body["database"]["idle"], 2);
+
+        let res = readyz(AdminLayer::new(Metrics::new()).with_readiness(Database(false)))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = serde_json::from_str(res.body()).unwrap();
+        assert_eq!(body["database"]["ok"], false);
+
+        let res = readyz(AdminLayer::new(Metrics::new())).await.unwrap();
+        assert_eq!(res.body(), "s3");
+    }
+
+    #[tokio::test]
+    async fn test_flush_caches_route() {
+        let items = Arc::new(ItemCache::new(
+            NonZeroUsize::new(8).unwrap(),
+            Duration::from_secs(30),
+            Duration::ZERO,
+        ));
+        let item = S3ItemDetail::builder()
+            .bucket("bucket".to_string())
+            .key("key".to_string())
+            .e_tag("etag".to_string())
+            .data_location("bucket/key".to_string())
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .build();
+        items.insert("bucket", "key", Some(&item));
+        let caches = Caches {
+            items: Some(Arc::clone(&items)),
+        };
+
+        let res = call_with_caches(Metrics::new(), caches, Method::POST, FLUSH_CACHES_PATH).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        assert!(items.get("bucket", "key").is_none());
+
+        let res = call(Metrics::new(), Method::GET, FLUSH_CACHES_PATH).await;
+        assert_eq!(res.body(), "s3");
+    }
+
+    #[tokio::test]
+    async fn test_admin_token() {
+        let inner = service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new("s3".to_string()))
+        });
+        let svc = AdminLayer::new(Metrics::new())
+            .with_token(Some("secret".to_string()))
+            .layer(inner);
+        let get = |authorization: Option<&str>| {
+            let mut req = Request::builder().uri(HEALTH_PATH);
+            if let Some(authorization) = authorization {
+                req = req.header(header::AUTHORIZATION, authorization);
+            }
+            svc.clone().oneshot(req.body(()).unwrap())
+        };
+
+        let res = get(None).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = get(Some("Bearer other")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = get(Some("Bearer secret")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // S3 requests keep their own authentication
+        let req = Request::builder().uri("/bucket/key").body(()).unwrap();
+        assert_eq!(svc.oneshot(req).await.unwrap().body(), "s3");
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_without_token() {
+        let inner = service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new("s3".to_string()))
+        });
+        let svc = AdminLayer::new(Metrics::new()).layer(inner);
+
+        for (method, path) in [
+            (Method::GET, METRICS_PATH),
+            (Method::GET, HEALTH_PATH),
+            (Method::POST, FLUSH_CACHES_PATH),
+        ] {
+            let req = Request::builder()
+                .method(method)
+                .uri(path)
+                .body(())
+                .unwrap();
+            let res = svc.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        }
+        // no bearer token can match a token that was never set
+        let req = authorized(Request::builder().uri(HEALTH_PATH));
+        let res = svc.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_on_their_own_listener() {
+        let metrics = Metrics::new();
+
+        // the admin listener answers nothing but the admin routes
+        let admin = AdminLayer::new(Arc::clone(&metrics))
+            .with_token(Some("secret".to_string()))
+            .layer(NotFound);
+        let req = authorized(Request::builder().uri(METRICS_PATH));
+        let res = admin.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let req = Request::builder().uri("/bucket/key").body(()).unwrap();
+        let res = admin.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // and the S3 port leaves them to the S3 service
+        let s3 = tower::ServiceBuilder::new()
+            .option_layer(None::<AdminLayer>)
+            .service(service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new("s3".to_string()))
+            }));
+        let req = Request::builder().uri(METRICS_PATH).body(()).unwrap();
+        let res = s3.oneshot(req).await.unwrap();
+        assert_eq!(res.body(), "s3");
+    }
+
+    #[tokio::test]
+    async fn test_other_requests_pass_through() {
+        let metrics = Metrics::new();
+
+        let res = call(Arc::clone(&metrics), Method::GET, "/bucket/key").await;
+        assert_eq!(res.body(), "s3");
+
+        // buckets named like a route are left to the S3 service
+        for uri in ["/metrics", "/health", "/readyz", "/admin/flush-caches"] {
+            let res = call(Arc::clone(&metrics), Method::GET, uri).await;
+            assert_eq!(res.body(), "s3");
+        }
+
+        let res = call(Arc::clone(&metrics), Method::GET, "/_admin/metrics?x=1").await;
+        assert_eq!(res.body(), "s3");
+
+        let res = call(metrics, Method::PUT, METRICS_PATH).await;
+        assert_eq!(res.body(), "s3");
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+}
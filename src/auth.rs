@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use s3s::{
+    S3Result,
+    auth::{S3Auth, SecretKey},
+    s3_error,
+};
+
+/// Authenticates requests signed with any of several access keys, one per tenant.
+///
+/// Unlike `SimpleAuth`, a request signed with an unknown access key fails with
+/// `AccessDenied`.
+#[derive(Debug, Default)]
+pub struct MultiKeyAuth {
+    secret_keys: HashMap<String, SecretKey>,
+}
+
+impl MultiKeyAuth {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts requests signed with `access_key`, returns `false` when the access key was
+    /// already registered, its secret key is then left unchanged.
+    pub fn register(&mut self, access_key: String, secret_key: SecretKey) -> bool {
+        if self.secret_keys.contains_key(&access_key) {
+            return false;
+        }
+        self.secret_keys.insert(access_key, secret_key);
+        true
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.secret_keys.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.secret_keys.is_empty()
+    }
+}
+
+#[async_trait]
+impl S3Auth for MultiKeyAuth {
+    async fn get_secret_key(&self, access_key: &str) -> S3Result<SecretKey> {
+        self.secret_keys
+            .get(access_key)
+            .cloned()
+            .ok_or_else(|| s3_error!(AccessDenied, "The access key does not exist"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use s3s::S3ErrorCode;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_secret_key() {
+        let mut auth = MultiKeyAuth::new();
+        assert!(auth.register("tenant-a".to_string(), SecretKey::from("secret-a")));
+        assert!(auth.register("tenant-b".to_string(), SecretKey::from("secret-b")));
+        assert!(!auth.register("tenant-a".to_string(), SecretKey::from("other")));
+        assert_eq!(auth.len(), 2);
+
+        let secret_key = auth.get_secret_key("tenant-a").await.unwrap();
+        assert_eq!(secret_key.expose(), "secret-a");
+        let secret_key = auth.get_secret_key("tenant-b").await.unwrap();
+        assert_eq!(secret_key.expose(), "secret-b");
+
+        let err = auth.get_secret_key("unknown").await.unwrap_err();
+        assert_eq!(*err.code(), S3ErrorCode::AccessDenied);
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use tracing::info;
+
+use crate::S3ItemDetail;
+
+type CacheKey = (String, String);
+
+/// Bounded, in-process cache of the latest [`S3ItemDetail`] of hot objects, keyed by
+/// `(bucket, key)`.
+///
+/// Lookups that found no object are remembered for `negative_ttl`, a zero duration
+/// disables negative caching.
+/// Entries expire so changes made by other instances become visible eventually.
+/// Writes going through this instance invalidate the entry right away.
+#[derive(Debug)]
+pub(crate) struct ItemCache {
+    entries: Mutex<LruCache<CacheKey, (Instant, Option<S3ItemDetail>)>>,
+    ttl: Duration,
+    negative_ttl: Duration,
+}
+
+impl ItemCache {
+    pub(crate) fn new(capacity: NonZeroUsize, ttl: Duration, negative_ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+            negative_ttl,
+        }
+    }
+
+    /// Returns `None` on a cache miss, `Some(None)` when the object is known to be missing.
+    #[allow(clippy::option_option)]
+    pub(crate) fn get(&self, bucket: &str, key: &str) -> Option<Option<S3ItemDetail>> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let cache_key = (bucket.to_string(), key.to_string());
+        let (inserted, item) = entries.get(&cache_key)?;
+        let ttl = if item.is_some() {
+            self.ttl
+        } else {
+            self.negative_ttl
+        };
+        if inserted.elapsed() < ttl {
+            return Some(item.clone());
+        }
+        entries.pop(&cache_key);
+        None
+    }
+
+    pub(crate) fn insert(&self, bucket: &str, key: &str, item: Option<&S3ItemDetail>) {
+        if item.is_none() && self.negative_ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.put(
+            (bucket.to_string(), key.to_string()),
+            (Instant::now(), item.cloned()),
+        );
+    }
+
+    pub(crate) fn invalidate(&self, bucket: &str, key: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.pop(&(bucket.to_string(), key.to_string()));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+/// Handle to the in-memory caches of a [`StorageBackend`](crate::StorageBackend).
+///
+/// Cloning is cheap, the handle can be moved into other services such as the admin routes.
+#[derive(Debug, Clone, Default)]
+pub struct Caches {
+    pub(crate) items: Option<Arc<ItemCache>>,
+}
+
+impl Caches {
+    /// Drops every cached entry so the next lookups go to the datastore.
+    pub fn flush(&self) {
+        if let Some(items) = &self.items {
+            items.clear();
+        }
+        info!("in-memory caches flushed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(bucket: &str, key: &str, e_tag: &str) -> S3ItemDetail {
+        S3ItemDetail::builder()
+            .bucket(bucket.to_string())
+            .key(key.to_string())
+            .e_tag(e_tag.to_string())
+            .data_location(format!("{bucket}/{key}"))
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .build()
+    }
+
+    fn new_cache(negative_ttl: Duration) -> ItemCache {
+        ItemCache::new(
+            NonZeroUsize::new(2).unwrap(),
+            Duration::from_secs(30),
+            negative_ttl,
+        )
+    }
+
+    #[test]
+    fn test_get_insert_invalidate() {
+        let cache = new_cache(Duration::ZERO);
+        assert!(cache.get("bucket", "key").is_none());
+
+        cache.insert("bucket", "key", Some(&item("bucket", "key", "etag1")));
+        assert_eq!(cache.get("bucket", "key").unwrap().unwrap().e_tag, "etag1");
+        assert!(cache.get("other", "key").is_none());
+
+        cache.invalidate("bucket", "key");
+        assert!(cache.get("bucket", "key").is_none());
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = new_cache(Duration::ZERO);
+        cache.insert("bucket", "a", Some(&item("bucket", "a", "a")));
+        cache.insert("bucket", "b", Some(&item("bucket", "b", "b")));
+        assert!(cache.get("bucket", "a").is_some());
+
+        cache.insert("bucket", "c", Some(&item("bucket", "c", "c")));
+        assert!(cache.get("bucket", "a").is_some());
+        assert!(cache.get("bucket", "b").is_none());
+        assert!(cache.get("bucket", "c").is_some());
+    }
+
+    #[test]
+    fn test_expired_entries_are_dropped() {
+        let cache = ItemCache::new(
+            NonZeroUsize::new(2).unwrap(),
+            Duration::ZERO,
+            Duration::ZERO,
+        );
+        cache.insert("bucket", "key", Some(&item("bucket", "key", "etag1")));
+        assert!(cache.get("bucket", "key").is_none());
+    }
+
+    #[test]
+    fn test_negative_entries() {
+        let cache = new_cache(Duration::ZERO);
+        cache.insert("bucket", "missing", None);
+        assert!(cache.get("bucket", "missing").is_none());
+
+        let cache = new_cache(Duration::from_secs(5));
+        cache.insert("bucket", "missing", None);
+        assert_eq!(cache.get("bucket", "missing"), Some(None));
+    }
+
+    #[test]
+    fn test_flush() {
+        let items = Arc::new(new_cache(Duration::from_secs(5)));
+        items.insert("bucket", "a", Some(&item("bucket", "a", "a")));
+        items.insert("bucket", "missing", None);
+
+        let caches = Caches {
+            items: Some(Arc::clone(&items)),
+        };
+        caches.flush();
+        assert!(items.get("bucket", "a").is_none());
+        assert!(items.get("bucket", "missing").is_none());
+
+        // flushing without any cache configured is a no-op
+        Caches::default().flush();
+    }
+}
@@ -59,6 +59,11 @@ pub fn from_internal_info(info: &InternalInfo) -> s3s::dto::Checksum {
     ans
 }
 
+/// Checksums are only returned to clients asking for them with `x-amz-checksum-mode: ENABLED`.
+pub fn is_requested(mode: Option<&s3s::dto::ChecksumMode>) -> bool {
+    mode.is_some_and(|mode| mode.as_str() == s3s::dto::ChecksumMode::ENABLED)
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -0,0 +1,126 @@
+use hyper::{Response, body::Body, header};
+use tower_http::compression::{CompressionLayer, DefaultPredicate, Predicate};
+
+/// Largest response body compressed, bigger objects are sent as stored.
+pub const MAX_COMPRESSED_SIZE: u64 = 1024 * 1024;
+
+/// Only compresses bodies of a known size up to the limit.
+#[derive(Debug, Clone, Copy)]
+struct SizeAtMost(u64);
+
+impl Predicate for SizeAtMost {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: Body,
+    {
+        let size = response.body().size_hint().exact().or_else(|| {
+            response
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.parse().ok())
+        });
+        size.is_some_and(|size| size <= self.0)
+    }
+}
+
+/// Compresses listings and small objects with gzip or deflate when the client accepts it.
+///
+/// Responses that already have a `Content-Encoding`, like objects stored gzipped, and
+/// ranges are sent as they are, as are images and bodies under 32 bytes or over
+/// [`MAX_COMPRESSED_SIZE`]. A disabled layer never compresses, it only keeps the response
+/// type of the service the same.
+#[must_use]
+pub fn compression_layer(enabled: bool) -> CompressionLayer<impl Predicate> {
+    let layer = CompressionLayer::new().no_br().no_zstd();
+    let layer = if enabled {
+        layer
+    } else {
+        layer.no_gzip().no_deflate()
+    };
+    layer.compress_when(DefaultPredicate::new().and(SizeAtMost(MAX_COMPRESSED_SIZE)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Request;
+    use tower::{Layer, ServiceExt, service_fn};
+
+    use super::*;
+
+    async fn call(
+        enabled: bool,
+        req: Request<()>,
+        res: fn() -> Response<String>,
+    ) -> Response<impl Body> {
+        let inner = service_fn(move |_req: Request<()>| async move { Ok::<_, Infallible>(res()) });
+        compression_layer(enabled)
+            .layer(inner)
+            .oneshot(req)
+            .await
+            .unwrap()
+    }
+
+    fn get() -> Request<()> {
+        Request::builder()
+            .uri("/bucket")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(())
+            .unwrap()
+    }
+
+    fn listing() -> Response<String> {
+        let body = "<ListBucketResult>".to_owned() + &"<Contents/>".repeat(100);
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/xml")
+            .body(body)
+            .unwrap()
+    }
+
+    fn content_encoding<B>(res: &Response<B>) -> Option<&str> {
+        res.headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|h| h.to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_listing_is_compressed() {
+        let res = call(true, get(), listing).await;
+        assert_eq!(content_encoding(&res), Some("gzip"));
+
+        let req = Request::builder().uri("/bucket").body(()).unwrap();
+        assert_eq!(content_encoding(&call(true, req, listing).await), None);
+
+        assert_eq!(content_encoding(&call(false, get(), listing).await), None);
+    }
+
+    #[tokio::test]
+    async fn test_encoded_ranged_and_large_responses_are_not_compressed() {
+        let res = call(true, get(), || {
+            let mut res = listing();
+            res.headers_mut()
+                .insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+            res
+        })
+        .await;
+        assert_eq!(content_encoding(&res), Some("gzip"));
+        assert!(!res.headers().contains_key(header::VARY));
+
+        let res = call(true, get(), || {
+            let mut res = listing();
+            res.headers_mut()
+                .insert(header::CONTENT_RANGE, "bytes 0-9/1200".parse().unwrap());
+            res
+        })
+        .await;
+        assert_eq!(content_encoding(&res), None);
+
+        let res = call(true, get(), || {
+            Response::new("x".repeat(usize::try_from(MAX_COMPRESSED_SIZE).unwrap() + 1))
+        })
+        .await;
+        assert_eq!(content_encoding(&res), None);
+    }
+}
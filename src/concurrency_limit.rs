@@ -0,0 +1,286 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response, StatusCode, header};
+use tower::{Layer, Service};
+
+const AMZ_CREDENTIAL_PARAM: &str = "X-Amz-Credential=";
+const AWS_ACCESS_KEY_ID_PARAM: &str = "AWSAccessKeyId=";
+
+/// Rejects requests beyond the allowed number in flight with `503 SlowDown` instead of
+/// queueing them, both in total and per access key, so a single client can neither
+/// overload the server and its database pool nor starve the others.
+///
+/// A limit of 0 lifts it. A request counts until its response starts, the body of a
+/// download is not accounted for. Requests that are not signed only count in total.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimitLayer {
+    in_flight: Arc<InFlight>,
+}
+
+impl ConcurrencyLimitLayer {
+    #[must_use]
+    pub fn new(max_requests: usize, max_requests_per_key: usize) -> Self {
+        Self {
+            in_flight: Arc::new(InFlight {
+                max_requests,
+                max_requests_per_key,
+                counts: Mutex::default(),
+            }),
+        }
+    }
+}
+
+impl<S> Layer<S> for ConcurrencyLimitLayer {
+    type Service = ConcurrencyLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConcurrencyLimitService {
+            inner,
+            in_flight: Arc::clone(&self.in_flight),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimitService<S> {
+    inner: S,
+    in_flight: Arc<InFlight>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    /// Only keys with requests in flight are kept.
+    per_key: HashMap<String, usize>,
+}
+
+#[derive(Debug)]
+struct InFlight {
+    max_requests: usize,
+    max_requests_per_key: usize,
+    counts: Mutex<Counts>,
+}
+
+impl InFlight {
+    fn try_acquire(self: &Arc<Self>, access_key: Option<String>) -> Option<Permit> {
+        let mut counts = self.counts.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.max_requests > 0 && counts.total >= self.max_requests {
+            return None;
+        }
+        let access_key = access_key.filter(|_| self.max_requests_per_key > 0);
+        if let Some(access_key) = &access_key {
+            let count = counts.per_key.entry(access_key.clone()).or_default();
+            if *count >= self.max_requests_per_key {
+                return None;
+            }
+            *count += 1;
+        }
+        counts.total += 1;
+        Some(Permit {
+            in_flight: Arc::clone(self),
+            access_key,
+        })
+    }
+}
+
+/// A request in flight, released on drop.
+struct Permit {
+    in_flight: Arc<InFlight>,
+    access_key: Option<String>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut counts = self
+            .in_flight
+            .counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        counts.total -= 1;
+        if let Some(access_key) = &self.access_key
+            && let Some(count) = counts.per_key.get_mut(access_key)
+        {
+            *count -= 1;
+            if *count == 0 {
+                counts.per_key.remove(access_key);
+            }
+        }
+    }
+}
+
+/// Access key a request claims to be signed with, from the `Authorization` header or the
+/// query string of a presigned URL. The signature is checked later by s3s.
+fn access_key<B>(req: &Request<B>) -> Option<String> {
+    if let Some(authorization) = req.headers().get(header::AUTHORIZATION) {
+        let authorization = authorization.to_str().ok()?;
+        // AWS4-HMAC-SHA256 Credential=<key>/<scope>, ... or AWS <key>:<signature>
+        let key = match authorization.split_once("Credential=") {
+            Some((_, credential)) => credential.split('/').next()?,
+            None => authorization.strip_prefix("AWS ")?.split(':').next()?,
+        };
+        return Some(key.to_owned());
+    }
+    req.uri().query()?.split('&').find_map(|pair| {
+        if let Some(credential) = pair.strip_prefix(AMZ_CREDENTIAL_PARAM) {
+            // the scope separators are usually percent-encoded
+            let key = credential.split(['/', '%']).next()?;
+            return Some(key.to_owned());
+        }
+        pair.strip_prefix(AWS_ACCESS_KEY_ID_PARAM)
+            .map(str::to_owned)
+    })
+}
+
+fn reject<RB>() -> Response<RB>
+where
+    RB: From<String>,
+{
+    let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                <Error><Code>SlowDown</Code>\
+                <Message>Please reduce your request rate.</Message></Error>"
+        .to_owned();
+
+    let mut res = Response::new(RB::from(body));
+    *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/xml"),
+    );
+    res
+}
+
+impl<S, B, RB> Service<Request<B>> for ConcurrencyLimitService<S>
+where
+    S: Service<Request<B>, Response = Response<RB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    RB: From<String> + Send + 'static,
+{
+    type Response = Response<RB>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let Some(permit) = self.in_flight.try_acquire(access_key(&req)) else {
+            let res = reject();
+            return Box::pin(async move { Ok(res) });
+        };
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let res = fut.await;
+            drop(permit);
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tokio::sync::Semaphore;
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+
+    fn request(access_key: &str) -> Request<()> {
+        Request::builder()
+            .uri("/bucket/key")
+            .header(
+                header::AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/20250101/us-east-1/s3/aws4_request, \
+                     SignedHeaders=host, Signature=0"
+                ),
+            )
+            .body(())
+            .unwrap()
+    }
+
+    /// Calls a service that holds every request until the gate lets it through.
+    fn gated(
+        layer: &ConcurrencyLimitLayer,
+        gate: &Arc<Semaphore>,
+    ) -> impl Fn(Request<()>) -> tokio::task::JoinHandle<Response<String>> + use<> {
+        let gate = Arc::clone(gate);
+        let svc = layer.layer(service_fn(move |_req: Request<()>| {
+            let gate = Arc::clone(&gate);
+            async move {
+                gate.acquire().await.unwrap().forget();
+                Ok::<_, Infallible>(Response::new("s3".to_string()))
+            }
+        }));
+        move |req| {
+            let svc = svc.clone();
+            tokio::spawn(async move { svc.oneshot(req).await.unwrap() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_total_limit() {
+        let gate = Arc::new(Semaphore::new(0));
+        let call = gated(&ConcurrencyLimitLayer::new(1, 0), &gate);
+
+        let held = call(request("a"));
+        tokio::task::yield_now().await;
+        let res = call(request("b")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(res.body().contains("<Code>SlowDown</Code>"));
+
+        gate.add_permits(2);
+        assert_eq!(held.await.unwrap().body(), "s3");
+        assert_eq!(call(request("b")).await.unwrap().body(), "s3");
+    }
+
+    #[tokio::test]
+    async fn test_per_key_limit() {
+        let gate = Arc::new(Semaphore::new(0));
+        let layer = ConcurrencyLimitLayer::new(0, 1);
+        let call = gated(&layer, &gate);
+
+        let held = call(request("noisy"));
+        tokio::task::yield_now().await;
+        let res = call(request("noisy")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let other = call(request("quiet"));
+        gate.add_permits(2);
+        assert_eq!(other.await.unwrap().body(), "s3");
+        assert_eq!(held.await.unwrap().body(), "s3");
+
+        // keys are forgotten once their requests are done
+        let counts = layer.in_flight.counts.lock().unwrap();
+        assert_eq!(counts.total, 0);
+        assert!(counts.per_key.is_empty());
+    }
+
+    #[test]
+    fn test_access_key() {
+        assert_eq!(access_key(&request("AKID")).as_deref(), Some("AKID"));
+
+        let req = Request::builder()
+            .header(header::AUTHORIZATION, "AWS AKID:c2lnbmF0dXJl")
+            .body(())
+            .unwrap();
+        assert_eq!(access_key(&req).as_deref(), Some("AKID"));
+
+        let req = Request::builder()
+            .uri("/bucket/key?X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential=AKID%2F20250101%2Fus-east-1%2Fs3%2Faws4_request")
+            .body(())
+            .unwrap();
+        assert_eq!(access_key(&req).as_deref(), Some("AKID"));
+
+        let req = Request::builder().uri("/bucket/key").body(()).unwrap();
+        assert_eq!(access_key(&req), None);
+    }
+}
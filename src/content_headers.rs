@@ -0,0 +1,131 @@
+use s3s::dto::{Timestamp, TimestampFormat};
+
+use crate::storage_backend::InternalInfo;
+
+/// `CreateBucket` header setting the content type of objects later uploaded without one.
+pub const DEFAULT_CONTENT_TYPE_HEADER: &str = "x-beggar-default-content-type";
+
+/// Representation headers given on upload and returned as-is on `GET` and `HEAD`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ContentHeaders {
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+    pub content_encoding: Option<String>,
+    pub content_language: Option<String>,
+    pub content_type: Option<String>,
+    /// `Expires` in RFC 3339, kept without loss of precision.
+    pub expires: Option<String>,
+}
+
+impl ContentHeaders {
+    fn fields(&self) -> [(&'static str, &Option<String>); 6] {
+        [
+            ("cache_control", &self.cache_control),
+            ("content_disposition", &self.content_disposition),
+            ("content_encoding", &self.content_encoding),
+            ("content_language", &self.content_language),
+            ("content_type", &self.content_type),
+            ("expires", &self.expires),
+        ]
+    }
+
+    /// The stored `Expires` date, only a caching hint for clients, the object is not
+    /// removed when it passes.
+    pub fn expires(&self) -> Option<Timestamp> {
+        let expires = self.expires.as_deref()?;
+        Timestamp::parse(TimestampFormat::DateTime, expires).ok()
+    }
+
+    /// The stored media type, `application/octet-stream` when none was given on upload.
+    pub fn media_type(&self) -> mime::Mime {
+        self.content_type
+            .as_deref()
+            .and_then(|content_type| content_type.parse().ok())
+            .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+    }
+}
+
+/// Removes the `aws-chunked` transport coding from a `Content-Encoding` value.
+///
+/// The chunk framing is already decoded by s3s, it is not a property of the stored object.
+/// Returns `None` when no other coding is left.
+pub fn strip_aws_chunked(content_encoding: Option<String>) -> Option<String> {
+    let content_encoding = content_encoding?;
+    let codings = content_encoding
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty() && !c.eq_ignore_ascii_case("aws-chunked"))
+        .collect::<Vec<_>>();
+    (!codings.is_empty()).then(|| codings.join(","))
+}
+
+pub fn modify_internal_info(info: &mut InternalInfo, headers: &ContentHeaders) {
+    for (name, value) in headers.fields() {
+        if let Some(value) = value {
+            info.insert(name.to_owned(), serde_json::Value::String(value.clone()));
+        }
+    }
+}
+
+pub fn from_internal_info(info: &InternalInfo) -> ContentHeaders {
+    let get = |name: &str| info.get(name).and_then(|v| v.as_str()).map(str::to_owned);
+    ContentHeaders {
+        cache_control: get("cache_control"),
+        content_disposition: get("content_disposition"),
+        content_encoding: get("content_encoding"),
+        content_language: get("content_language"),
+        content_type: get("content_type"),
+        expires: get("expires"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_internal_info_round_trip() {
+        let headers = ContentHeaders {
+            cache_control: Some("max-age=3600".to_string()),
+            content_disposition: Some("attachment".to_string()),
+            content_encoding: Some("gzip".to_string()),
+            content_language: None,
+            content_type: Some("application/json".to_string()),
+            expires: None,
+        };
+
+        let mut info = InternalInfo::new();
+        modify_internal_info(&mut info, &headers);
+
+        assert_eq!(info.get("cache_control"), Some(&json!("max-age=3600")));
+        assert_eq!(info.get("content_language"), None);
+        assert_eq!(from_internal_info(&info), headers);
+        assert_eq!(headers.media_type(), mime::APPLICATION_JSON);
+        assert_eq!(
+            ContentHeaders::default().media_type(),
+            mime::APPLICATION_OCTET_STREAM
+        );
+    }
+
+    #[test]
+    fn test_strip_aws_chunked() {
+        assert_eq!(strip_aws_chunked(None), None);
+        assert_eq!(strip_aws_chunked(Some("aws-chunked".to_string())), None);
+        assert_eq!(
+            strip_aws_chunked(Some("aws-chunked, gzip".to_string())),
+            Some("gzip".to_string())
+        );
+        assert_eq!(
+            strip_aws_chunked(Some("gzip,br".to_string())),
+            Some("gzip,br".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_internal_info_missing_fields() {
+        let info: InternalInfo = serde_json::from_str(r#"{"checksum_crc32": "crc32"}"#).unwrap();
+        assert_eq!(from_internal_info(&info), ContentHeaders::default());
+    }
+}
@@ -1,30 +1,133 @@
 use core::fmt;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::pool::PoolConnection;
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
-use sqlx::{Pool, Postgres};
-use tracing::{debug, error, info, instrument};
+use sqlx::{Database, Pool, Postgres, Transaction};
+use tracing::{debug, error, info, instrument, warn};
 
 use crate::error::Result;
-use crate::{MultipartUpload, MultipartUploadPart, S3ItemDetail, Settings};
+use crate::{Ds, MultipartUpload, MultipartUploadPart, S3Bucket, S3ItemDetail, Settings};
 
 // Constants for security and performance
-const MAX_QUERY_SIZE: i32 = 1000; // Limit query results
-const CONNECTION_TIMEOUT: u64 = 30; // Connection timeout in seconds
-const STATEMENT_TIMEOUT: &str = "30000"; // SQL statement timeout in milliseconds
+pub(crate) const MAX_QUERY_SIZE: i32 = 1000; // Limit query results
+pub(crate) const CONNECTION_TIMEOUT: u64 = 30; // Connection timeout in seconds
+pub(crate) const CONNECT_ATTEMPTS: u32 = 5; // Connection attempts at startup
+pub(crate) const CONNECT_RETRY_BASE_DELAY_MS: u64 = 500; // First delay between connection attempts
+const CONNECT_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+pub(crate) const STATEMENT_TIMEOUT_MS: u64 = 30_000; // SQL statement timeout
+pub(crate) const ADMIN_STATEMENT_TIMEOUT_MS: u64 = 300_000; // SQL statement timeout of maintenance queries
 
 #[async_trait]
 pub trait DataStore: Send + Sync + 'static + std::fmt::Debug {
     async fn save_s3_item_detail(&self, item: &S3ItemDetail) -> Result<()>;
     async fn get_s3_item_detail(&self, bucket: &str, key: &str) -> Result<Option<S3ItemDetail>>;
+    async fn get_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<Option<S3ItemDetail>>;
     async fn get_s3_item_detail_with_filter(
         &self,
         bucket: &str,
         filter: &str,
     ) -> Result<Vec<S3ItemDetail>>;
 
-    async fn get_all_buckets(&self) -> Result<Vec<String>>;
+    async fn get_s3_item_versions_with_filter(
+        &self,
+        bucket: &str,
+        filter: &str,
+    ) -> Result<Vec<S3ItemDetail>>;
+
+    /// Lists the latest version of the keys starting with `prefix`, delete markers excluded.
+    ///
+    /// Keys are returned in ascending order, or descending when `descending` is set.
+    /// `start_after` skips every key up to and including it in that order,
+    /// an empty `start_after` starts at the first key.
+    async fn list_latest_items_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: &str,
+        descending: bool,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>>;
+
+    /// Lists the versions of the keys starting with `prefix`, delete markers included,
+    /// ordered by key and newest first within each key.
+    ///
+    /// An empty `key_marker` starts at the first key. Otherwise the listing resumes after
+    /// the version `version_id_marker` of `key_marker`, or at the key following
+    /// `key_marker` when there is no such version.
+    async fn list_versions_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>>;
+
+    /// Lists the stored versions of every bucket, delete markers included, ordered by
+    /// bucket, key and version id.
+    ///
+    /// Only the versions after the given bucket, key and version id are returned,
+    /// empty strings start at the first version.
+    async fn list_item_versions_page(
+        &self,
+        after_bucket: &str,
+        after_key: &str,
+        after_version_id: &str,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>>;
+
+    async fn get_item_by_data_location(&self, data_location: &str) -> Result<Option<S3ItemDetail>>;
+
+    async fn delete_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<()>;
+
+    /// Moves a stored version to another bucket and key with its data at `data_location`,
+    /// keeping everything else. Returns `false` when the version no longer exists.
+    async fn move_s3_item_detail(
+        &self,
+        item: &S3ItemDetail,
+        bucket: &str,
+        key: &str,
+        data_location: &str,
+    ) -> Result<bool>;
+
+    /// Records a new bucket, returns `false` when a bucket with that name already exists.
+    async fn create_bucket(&self, bucket: &S3Bucket) -> Result<bool>;
+    /// Forgets a bucket together with its configuration.
+    async fn delete_bucket(&self, bucket: &str) -> Result<()>;
+    async fn list_buckets(&self) -> Result<Vec<S3Bucket>>;
+    async fn count_buckets(&self) -> Result<i64>;
+    async fn bucket_exists(&self, bucket: &str) -> Result<bool>;
+    async fn get_bucket(&self, bucket: &str) -> Result<Option<S3Bucket>>;
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<Option<String>>;
+    async fn save_bucket_versioning(&self, bucket: &str, status: &str) -> Result<()>;
+    async fn get_bucket_ownership(&self, bucket: &str) -> Result<Option<String>>;
+    async fn save_bucket_ownership(&self, bucket: &str, ownership: &str) -> Result<()>;
+    async fn get_bucket_acl(&self, bucket: &str) -> Result<Option<String>>;
+    async fn save_bucket_acl(&self, bucket: &str, acl: &str) -> Result<()>;
+    async fn get_bucket_default_content_type(&self, bucket: &str) -> Result<Option<String>>;
+    async fn save_bucket_default_content_type(
+        &self,
+        bucket: &str,
+        content_type: &str,
+    ) -> Result<()>;
+    async fn get_bucket_encryption(&self, bucket: &str) -> Result<Option<String>>;
+    async fn save_bucket_encryption(&self, bucket: &str, encryption: &str) -> Result<()>;
+    async fn get_bucket_lifecycle(&self, bucket: &str) -> Result<Option<String>>;
+    async fn save_bucket_lifecycle(&self, bucket: &str, lifecycle: &str) -> Result<()>;
+    async fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<()>;
     async fn save_multipart_upload(&self, upload: &MultipartUpload) -> Result<()>;
     async fn save_multipart_upload_part(&self, part: &MultipartUploadPart) -> Result<()>;
     async fn get_access_key_by_upload_id(&self, upload_id: &str) -> Result<Option<String>>;
@@ -34,10 +137,159 @@ pub trait DataStore: Send + Sync + 'static + std::fmt::Debug {
         upload_id: &str,
     ) -> Result<Option<MultipartUpload>>;
     async fn delete_multipart_upload_by_upload_id(&self, upload_id: &str) -> Result<()>;
+    /// Multipart uploads initiated before `older_than`, oldest first.
+    async fn get_stale_multipart_uploads(
+        &self,
+        older_than: NaiveDateTime,
+    ) -> Result<Vec<MultipartUpload>>;
+
+    /// Runs a trivial query and reports how long it took and the state of the pool.
+    async fn health(&self) -> DatabaseHealth;
+
+    /// Closes the connections to the database, waiting for the ones in use to be returned.
+    ///
+    /// Called on shutdown, later calls fail.
+    async fn close(&self) {}
+}
+
+/// Outcome of a database health check, served by `GET /_admin/readyz`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatabaseHealth {
+    /// Whether the query succeeded
+    pub ok: bool,
+    /// Round trip of the query, including the wait for a connection
+    pub latency_ms: u64,
+    /// Connections open in the pool, idle or in use
+    pub pool_size: u32,
+    pub idle: usize,
 }
 
+impl DatabaseHealth {
+    pub(crate) fn new(ok: bool, latency: Duration, pool_size: u32, idle: usize) -> Self {
+        Self {
+            ok,
+            latency_ms: u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+            pool_size,
+            idle,
+        }
+    }
+}
+
+/// Sanitizes database inputs for logging
+pub(crate) fn sanitize_for_logging(input: &str) -> String {
+    // Simple sanitization for logging purposes
+    let shortened = if input.len() > 50 {
+        format!("{}...", &input[..47])
+    } else {
+        input.to_string()
+    };
+
+    // Remove potentially dangerous characters for logging
+    shortened
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '_' || *c == '-' || *c == '/')
+        .collect()
+}
+
+/// `LIKE` pattern matching the keys that start with `prefix`.
+///
+/// `%` and `_` are valid in keys, so they are escaped with `\`, which is the default
+/// escape character of both Postgres and `MySQL`, along with `\` itself.
+pub(crate) fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Runs `connect` until it succeeds or `max_attempts` were made, returning the last error.
+///
+/// The delay between attempts starts at `base_delay` and doubles every retry,
+/// so startup survives a database that is still coming up.
+pub(crate) async fn connect_with_retry<T, F, Fut>(
+    max_attempts: u32,
+    base_delay: Duration,
+    mut connect: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = base_delay;
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < max_attempts => {
+                warn!(
+                    target: "database",
+                    error = %e,
+                    attempt,
+                    max_attempts,
+                    ?delay,
+                    "Database connection attempt failed, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(CONNECT_RETRY_MAX_DELAY);
+                attempt += 1;
+            }
+            Err(e) => {
+                error!(target: "database", error = %e, attempt, "Giving up connecting to the database");
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Acquires a pooled connection for an object metadata lookup.
+///
+/// Reads give up after `timeout`, which may be shorter than the pool wide acquire timeout
+/// writes wait for, so a `HEAD` fails fast instead of queueing behind bulk work.
+pub(crate) async fn acquire_for_read<DB: Database>(
+    pool: &Pool<DB>,
+    timeout: Duration,
+) -> Result<PoolConnection<DB>> {
+    match tokio::time::timeout(timeout, pool.acquire()).await {
+        Ok(Ok(conn)) => Ok(conn),
+        Ok(Err(e)) => {
+            error!(error = %e, "Failed to acquire a connection for a read");
+            Err(e.into())
+        }
+        Err(_) => {
+            warn!(?timeout, "Timed out acquiring a connection for a read");
+            Err(sqlx::Error::PoolTimedOut.into())
+        }
+    }
+}
+
+/// Connection options of the Postgres pool, with the statement timeout of `ds`.
+pub(crate) fn pg_connect_options(ds: &Ds) -> PgConnectOptions {
+    let statement_timeout = ds.statement_timeout().as_millis().to_string();
+    PgConnectOptions::new()
+        .host(&ds.host)
+        .port(ds.port)
+        .username(&ds.user)
+        .password(&ds.password)
+        .database(&ds.db)
+        .options([
+            ("application_name", "beggar_s3_server"),
+            ("search_path", ds.schema.as_str()),
+            ("statement_timeout", statement_timeout.as_str()), // Prevent long-running queries
+            ("log_statement", "none"), // Don't log statements with credentials
+            ("tcp_keepalives_idle", "60"), // Keep connections alive
+        ])
+}
+
+#[derive(Clone)]
 pub struct PostgresDatastore {
     pool: Pool<Postgres>,
+    read_acquire_timeout: Duration,
+    admin_statement_timeout: Duration,
 }
 
 impl PostgresDatastore {
@@ -49,7 +301,6 @@ impl PostgresDatastore {
         let port = settings.datasource.port;
         let db = &settings.datasource.db;
         let user = &settings.datasource.user;
-        let password = &settings.datasource.password;
         let schema = &settings.datasource.schema;
 
         let max_connections = settings.datasource.max_connections;
@@ -64,302 +315,1292 @@ impl PostgresDatastore {
         info!(target: "database", connection = %sanitized_connection, "Initializing database connection");
 
         // Create connection options with security settings
-        let connect_options = PgConnectOptions::new()
-            .host(host)
-            .port(port)
-            .username(user)
-            .password(password)
-            .database(db)
-            .options([
-                ("application_name", "beggar_s3_server"),
-                ("search_path", schema),
-                ("statement_timeout", STATEMENT_TIMEOUT), // Prevent long-running queries
-                ("log_statement", "none"),                // Don't log statements with credentials
-                ("tcp_keepalives_idle", "60"),            // Keep connections alive
-            ]);
+        let connect_options = pg_connect_options(&settings.datasource);
 
         let pool_options = PgPoolOptions::new()
             .max_connections(max_connections)
             .min_connections(min_connections)
-            .acquire_timeout(Duration::from_secs(CONNECTION_TIMEOUT))
+            .acquire_timeout(settings.datasource.acquire_timeout())
             .acquire_slow_threshold(acquire_slow_threshold)
             .test_before_acquire(test_before_acquire);
 
         // Establish connection with immediate validation
-        debug!(target: "database", "Creating connection pool");
-
-        let pool = match pool_options.connect_with(connect_options).await {
-            Ok(pool) => {
-                // Verify we can actually execute a query
-                debug!(target: "database", "Validating database connection");
-                match sqlx::query("SELECT 1").execute(&pool).await {
-                    Ok(_) => {
-                        info!(target: "database", "Database connection established and validated");
-                        pool
-                    }
+        let pool = connect_with_retry(
+            settings.datasource.connect_max_attempts(),
+            settings.datasource.connect_retry_base_delay(),
+            || async {
+                debug!(target: "database", "Creating connection pool");
+                let pool = match pool_options
+                    .clone()
+                    .connect_with(connect_options.clone())
+                    .await
+                {
+                    Ok(pool) => pool,
                     Err(e) => {
-                        error!(
+                        warn!(
                             target: "database",
                             error = %e,
-                            "Database connection validation failed"
+                            host = %host,
+                            port = %port,
+                            db = %db,
+                            "Failed to establish database connection"
                         );
                         return Err(e.into());
                     }
+                };
+                // Verify we can actually execute a query
+                debug!(target: "database", "Validating database connection");
+                if let Err(e) = sqlx::query("SELECT 1").execute(&pool).await {
+                    warn!(
+                        target: "database",
+                        error = %e,
+                        "Database connection validation failed"
+                    );
+                    return Err(e.into());
+                }
+                Ok(pool)
+            },
+        )
+        .await?;
+        info!(target: "database", "Database connection established and validated");
+
+        Ok(Self {
+            pool,
+            read_acquire_timeout: settings.datasource.read_acquire_timeout(),
+            admin_statement_timeout: settings.datasource.admin_statement_timeout(),
+        })
+    }
+
+    /// Only for tests - creates a datastore with an existing pool
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_pool(pool: Pool<Postgres>) -> Self {
+        let read_acquire_timeout = pool.options().get_acquire_timeout();
+        Self {
+            pool,
+            read_acquire_timeout,
+            admin_statement_timeout: Duration::from_millis(ADMIN_STATEMENT_TIMEOUT_MS),
+        }
+    }
+
+    /// Starts a transaction for maintenance queries, which may run up to the admin
+    /// statement timeout instead of the one of the connection.
+    async fn begin_admin(&self) -> Result<Transaction<'_, Postgres>> {
+        let mut tx = self.pool.begin().await?;
+        // SET does not take bind parameters, the timeout is a plain number
+        let set_timeout = format!(
+            "SET LOCAL statement_timeout = {}",
+            self.admin_statement_timeout.as_millis()
+        );
+        sqlx::query(&set_timeout).execute(&mut *tx).await?;
+        Ok(tx)
+    }
+
+    /// Only for tests - overrides the acquire timeout of metadata reads
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_read_acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.read_acquire_timeout = timeout;
+        self
+    }
+
+    #[instrument(level = "info", name = "db_migration", skip(self))]
+    pub async fn migrate(&self) -> Result<()> {
+        info!(target: "database", "Running database migrations");
+        match sqlx::migrate!("./migrations").run(&self.pool).await {
+            Ok(()) => {
+                info!(target: "database", "Database migrations completed successfully");
+                Ok(())
+            }
+            Err(e) => {
+                error!(error = %e, "Database migration failed");
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Times a `SELECT 1`, a failed query is reported rather than returned as an error.
+    #[instrument(level = "debug", name = "db_health_check", skip(self))]
+    pub async fn check_connection_health(&self) -> DatabaseHealth {
+        debug!(target: "database", "Checking database connection health");
+        let start = Instant::now();
+        let ok = match sqlx::query("SELECT 1").execute(&self.pool).await {
+            Ok(_) => {
+                debug!(target: "database", "Database connection is healthy");
+                true
+            }
+            Err(e) => {
+                error!(
+                    target: "database",
+                    error = %e,
+                    "Database connection health check failed"
+                );
+                false
+            }
+        };
+        DatabaseHealth::new(ok, start.elapsed(), self.pool.size(), self.pool.num_idle())
+    }
+}
+
+#[async_trait]
+impl DataStore for PostgresDatastore {
+    #[instrument(level = "debug", name = "save_item", skip(self, item), fields(bucket = %item.bucket, key = %item.key))]
+    async fn save_s3_item_detail(&self, item: &S3ItemDetail) -> Result<()> {
+        debug!(
+            target: "storage",
+            "Saving S3 item detail"
+        );
+
+        match sqlx::query!(
+            r#"
+            INSERT INTO s3_item_detail (bucket, key, version_id, metadata, internal_info, last_modified, md5, data_location, delete_marker)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (bucket, key, version_id) DO UPDATE
+            SET metadata = $4,
+            internal_info = $5,
+            last_modified = $6,
+            md5 = $7,
+            data_location = $8,
+            delete_marker = $9
+            "#,
+            item.bucket,
+            item.key,
+            item.version_id,
+            item.metadata,
+            item.internal_info,
+            item.last_modified,
+            item.e_tag,
+            item.data_location,
+            item.delete_marker
+        )
+        .execute(&self.pool)
+        .await {
+            Ok(_) => {
+                info!(
+                    target: "storage",
+                    bucket = %item.bucket,
+                    key = %item.key,
+                    "S3 item detail saved successfully"
+                );
+                Ok(())
+            },
+            Err(e) => {
+                error!(
+                    target: "storage",
+                    error = %e,
+                    bucket = %item.bucket,
+                    key = %item.key,
+                    "Failed to save S3 item detail"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item", skip(self), fields(bucket = %bucket, key = %key))]
+    async fn get_s3_item_detail(&self, bucket: &str, key: &str) -> Result<Option<S3ItemDetail>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            key = %sanitize_for_logging(key),
+            "Retrieving S3 item detail"
+        );
+
+        let mut conn = acquire_for_read(&self.pool, self.read_acquire_timeout).await?;
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = $1 AND key = $2
+            ORDER BY last_modified DESC
+            LIMIT 1
+            "#,
+            bucket,
+            key
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        {
+            Ok(result) => {
+                // Only log at info level if found - reduces noise for common "not found" cases
+                if result.is_some() {
+                    info!(
+                        target: "storage",
+                        bucket = %bucket,
+                        key = %key,
+                        "S3 item detail retrieved successfully"
+                    );
+                } else {
+                    debug!(
+                        target: "storage",
+                        bucket = %bucket,
+                        key = %key,
+                        "S3 item detail not found"
+                    );
                 }
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    "Failed to retrieve S3 item detail"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item_version", skip(self), fields(bucket = %bucket, key = %key, version_id = %version_id))]
+    async fn get_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<Option<S3ItemDetail>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            key = %sanitize_for_logging(key),
+            version_id = %sanitize_for_logging(version_id),
+            "Retrieving S3 item detail version"
+        );
+
+        let mut conn = acquire_for_read(&self.pool, self.read_acquire_timeout).await?;
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = $1 AND key = $2 AND version_id = $3
+            "#,
+            bucket,
+            key,
+            version_id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        {
+            Ok(result) => {
+                debug!(
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    version_id = %sanitize_for_logging(version_id),
+                    found = result.is_some(),
+                    "S3 item detail version lookup completed"
+                );
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    version_id = %sanitize_for_logging(version_id),
+                    "Failed to retrieve S3 item detail version"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_items_with_filter", skip(self), fields(bucket = %bucket, filter = %filter))]
+    async fn get_s3_item_detail_with_filter(
+        &self,
+        bucket: &str,
+        filter: &str,
+    ) -> Result<Vec<S3ItemDetail>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            filter = %sanitize_for_logging(filter),
+            "Retrieving S3 items with filter"
+        );
+
+        // Add LIMIT to prevent too many results (DoS protection)
+        // Only the latest version of each key is listed
+        let filter_with_wildcard = like_prefix(filter);
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT DISTINCT ON (key) bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = $1 AND key LIKE $2 ESCAPE '\'
+            ORDER by key asc, last_modified desc
+            LIMIT $3
+            "#,
+            bucket,
+            filter_with_wildcard,
+            MAX_QUERY_SIZE as i32
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(
+                    bucket = %sanitize_for_logging(bucket),
+                    filter = %sanitize_for_logging(filter),
+                    count = result.len(),
+                    "Retrieved S3 items with filter"
+                );
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    filter = %sanitize_for_logging(filter),
+                    "Failed to retrieve S3 items with filter"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item_versions_with_filter", skip(self), fields(bucket = %bucket, filter = %filter))]
+    async fn get_s3_item_versions_with_filter(
+        &self,
+        bucket: &str,
+        filter: &str,
+    ) -> Result<Vec<S3ItemDetail>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            filter = %sanitize_for_logging(filter),
+            "Retrieving S3 item versions with filter"
+        );
+
+        // Every version is returned, newest first within each key
+        let filter_with_wildcard = like_prefix(filter);
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = $1 AND key LIKE $2 ESCAPE '\'
+            ORDER by key asc, last_modified desc, version_id asc
+            LIMIT $3
+            "#,
+            bucket,
+            filter_with_wildcard,
+            MAX_QUERY_SIZE as i32
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(
+                    bucket = %sanitize_for_logging(bucket),
+                    filter = %sanitize_for_logging(filter),
+                    count = result.len(),
+                    "Retrieved S3 item versions with filter"
+                );
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    filter = %sanitize_for_logging(filter),
+                    "Failed to retrieve S3 item versions with filter"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_latest_items_page", skip(self), fields(bucket = %bucket, prefix = %prefix))]
+    async fn list_latest_items_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: &str,
+        descending: bool,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            prefix = %sanitize_for_logging(prefix),
+            descending,
+            "Retrieving page of S3 items"
+        );
+
+        let prefix_with_wildcard = like_prefix(prefix);
+        let limit = limit.min(MAX_QUERY_SIZE);
+        let result = if descending {
+            sqlx::query_as!(
+                S3ItemDetail,
+                r#"
+                SELECT bucket, key, version_id, metadata, internal_info, last_modified, e_tag, data_location, delete_marker
+                FROM (
+                    SELECT DISTINCT ON (key) bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+                    FROM s3_item_detail
+                    WHERE bucket = $1 AND key LIKE $2 ESCAPE '\' AND ($3 = '' OR key < $3)
+                    ORDER BY key desc, last_modified desc
+                ) latest
+                WHERE NOT delete_marker
+                ORDER BY key desc
+                LIMIT $4
+                "#,
+                bucket,
+                prefix_with_wildcard,
+                start_after,
+                i64::from(limit)
+            )
+            .fetch_all(&self.pool)
+            .await
+        } else {
+            sqlx::query_as!(
+                S3ItemDetail,
+                r#"
+                SELECT bucket, key, version_id, metadata, internal_info, last_modified, e_tag, data_location, delete_marker
+                FROM (
+                    SELECT DISTINCT ON (key) bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+                    FROM s3_item_detail
+                    WHERE bucket = $1 AND key LIKE $2 ESCAPE '\' AND ($3 = '' OR key > $3)
+                    ORDER BY key asc, last_modified desc
+                ) latest
+                WHERE NOT delete_marker
+                ORDER BY key asc
+                LIMIT $4
+                "#,
+                bucket,
+                prefix_with_wildcard,
+                start_after,
+                i64::from(limit)
+            )
+            .fetch_all(&self.pool)
+            .await
+        };
+
+        match result {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved page of S3 items");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    prefix = %sanitize_for_logging(prefix),
+                    "Failed to retrieve page of S3 items"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_versions_page", skip(self), fields(bucket = %bucket, prefix = %prefix))]
+    async fn list_versions_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            prefix = %sanitize_for_logging(prefix),
+            "Retrieving page of S3 item versions"
+        );
+
+        // versions of the marked key sort after the marker by last_modified desc, version_id asc
+        let prefix_with_wildcard = like_prefix(prefix);
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail item
+            WHERE bucket = $1 AND key LIKE $2 ESCAPE '\'
+            AND ($3 = '' OR key > $3 OR (key = $3 AND EXISTS (
+                SELECT 1 FROM s3_item_detail marker
+                WHERE marker.bucket = $1 AND marker.key = $3 AND marker.version_id = $4
+                AND (item.last_modified < marker.last_modified
+                    OR (item.last_modified = marker.last_modified AND item.version_id > marker.version_id))
+            )))
+            ORDER BY key asc, last_modified desc, version_id asc
+            LIMIT $5
+            "#,
+            bucket,
+            prefix_with_wildcard,
+            key_marker,
+            version_id_marker,
+            i64::from(limit.min(MAX_QUERY_SIZE))
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved page of S3 item versions");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    prefix = %sanitize_for_logging(prefix),
+                    "Failed to retrieve page of S3 item versions"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_item_versions_page", skip(self))]
+    async fn list_item_versions_page(
+        &self,
+        after_bucket: &str,
+        after_key: &str,
+        after_version_id: &str,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE (bucket, key, version_id) > ($1, $2, $3)
+            ORDER BY bucket, key, version_id
+            LIMIT $4
+            "#,
+            after_bucket,
+            after_key,
+            after_version_id,
+            i64::from(limit.min(MAX_QUERY_SIZE))
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved page of S3 item versions");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(after_bucket),
+                    key = %sanitize_for_logging(after_key),
+                    "Failed to retrieve page of S3 item versions"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item_by_data_location", skip(self), fields(data_location = %data_location))]
+    async fn get_item_by_data_location(&self, data_location: &str) -> Result<Option<S3ItemDetail>> {
+        debug!(
+            data_location = %sanitize_for_logging(data_location),
+            "Retrieving S3 item detail by data location"
+        );
+
+        match sqlx::query_as!(
+            S3ItemDetail,
+            r#"
+            SELECT bucket, key, version_id, metadata, internal_info, last_modified, md5 as e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE data_location = $1
+            "#,
+            data_location
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(
+                    data_location = %sanitize_for_logging(data_location),
+                    found = result.is_some(),
+                    "S3 item detail lookup by data location completed"
+                );
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    data_location = %sanitize_for_logging(data_location),
+                    "Failed to retrieve S3 item detail by data location"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "info", name = "delete_item_version", skip(self), fields(bucket = %bucket, key = %key, version_id = %version_id))]
+    async fn delete_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<()> {
+        debug!(target: "storage", "Deleting S3 item detail version");
+
+        match sqlx::query!(
+            r#"
+            DELETE FROM s3_item_detail
+            WHERE bucket = $1 AND key = $2 AND version_id = $3
+            "#,
+            bucket,
+            key,
+            version_id
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                let rows_affected = result.rows_affected();
+                info!(
+                    target: "storage",
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    version_id = %sanitize_for_logging(version_id),
+                    rows_affected = %rows_affected,
+                    "S3 item detail version deleted"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    version_id = %sanitize_for_logging(version_id),
+                    "Failed to delete S3 item detail version"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "info", name = "move_item", skip(self, item), fields(bucket = %item.bucket, key = %item.key, to_bucket = %bucket, to_key = %key))]
+    async fn move_s3_item_detail(
+        &self,
+        item: &S3ItemDetail,
+        bucket: &str,
+        key: &str,
+        data_location: &str,
+    ) -> Result<bool> {
+        match sqlx::query!(
+            r#"
+            UPDATE s3_item_detail
+            SET bucket = $4, key = $5, data_location = $6
+            WHERE bucket = $1 AND key = $2 AND version_id = $3
+            "#,
+            item.bucket,
+            item.key,
+            item.version_id,
+            bucket,
+            key,
+            data_location
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                info!(
+                    target: "storage",
+                    rows_affected = %result.rows_affected(),
+                    "S3 item detail moved"
+                );
+                Ok(result.rows_affected() > 0)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(&item.bucket),
+                    key = %sanitize_for_logging(&item.key),
+                    to_bucket = %sanitize_for_logging(bucket),
+                    to_key = %sanitize_for_logging(key),
+                    "Failed to move S3 item detail"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "create_bucket", skip(self), fields(bucket = %bucket.name))]
+    async fn create_bucket(&self, bucket: &S3Bucket) -> Result<bool> {
+        debug!(
+            bucket = %sanitize_for_logging(&bucket.name),
+            "Creating bucket"
+        );
+
+        match sqlx::query!(
+            r#"
+            INSERT INTO bucket (name, created_at, owner)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO NOTHING
+            "#,
+            bucket.name,
+            bucket.created_at,
+            bucket.owner
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                let created = result.rows_affected() > 0;
+                info!(
+                    bucket = %sanitize_for_logging(&bucket.name),
+                    created = %created,
+                    "Bucket created"
+                );
+                Ok(created)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(&bucket.name),
+                    "Failed to create bucket"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "delete_bucket", skip(self), fields(bucket = %bucket))]
+    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            "Deleting bucket"
+        );
+
+        let mut tx = self.pool.begin().await?;
+        let result = async {
+            sqlx::query!(
+                r#"
+                DELETE FROM bucket_config
+                WHERE bucket = $1
+                "#,
+                bucket
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM bucket
+                WHERE name = $1
+                "#,
+                bucket
+            )
+            .execute(&mut *tx)
+            .await
+        }
+        .await;
+
+        match result {
+            Ok(_) => {
+                tx.commit().await?;
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    "Bucket deleted"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to delete bucket"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_buckets", skip(self))]
+    async fn list_buckets(&self) -> Result<Vec<S3Bucket>> {
+        debug!("Retrieving all buckets");
+
+        // Add LIMIT to prevent potential DoS with too many buckets
+        match sqlx::query_as!(
+            S3Bucket,
+            r#"
+            SELECT name, created_at, owner
+            FROM bucket
+            ORDER BY name
+            LIMIT $1
+            "#,
+            i64::from(MAX_QUERY_SIZE)
+        )
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved all buckets");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to retrieve all buckets");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "count_buckets", skip(self))]
+    async fn count_buckets(&self) -> Result<i64> {
+        match sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM bucket
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(count) => Ok(count),
+            Err(e) => {
+                error!(error = %e, "Failed to count buckets");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "bucket_exists", skip(self), fields(bucket = %bucket))]
+    async fn bucket_exists(&self, bucket: &str) -> Result<bool> {
+        match sqlx::query!(
+            r#"
+            SELECT name
+            FROM bucket
+            WHERE name = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.is_some()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to check bucket existence"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_bucket", skip(self), fields(bucket = %bucket))]
+    async fn get_bucket(&self, bucket: &str) -> Result<Option<S3Bucket>> {
+        match sqlx::query_as!(
+            S3Bucket,
+            r#"
+            SELECT name, created_at, owner
+            FROM bucket
+            WHERE name = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_bucket_versioning", skip(self), fields(bucket = %bucket))]
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<Option<String>> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            "Retrieving bucket versioning status"
+        );
+
+        match sqlx::query!(
+            r#"
+            SELECT versioning_status
+            FROM bucket_config
+            WHERE bucket = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                let status = result.and_then(|row| row.versioning_status);
+                debug!(
+                    bucket = %sanitize_for_logging(bucket),
+                    status = ?status,
+                    "Bucket versioning status retrieved"
+                );
+                Ok(status)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket versioning status"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_versioning", skip(self), fields(bucket = %bucket, status = %status))]
+    async fn save_bucket_versioning(&self, bucket: &str, status: &str) -> Result<()> {
+        debug!(
+            bucket = %sanitize_for_logging(bucket),
+            "Saving bucket versioning status"
+        );
+
+        match sqlx::query!(
+            r#"
+            INSERT INTO bucket_config (bucket, versioning_status)
+            VALUES ($1, $2)
+            ON CONFLICT (bucket) DO UPDATE
+            SET versioning_status = $2
+            "#,
+            bucket,
+            status
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    status = %status,
+                    "Bucket versioning status saved"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket versioning status"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_bucket_ownership", skip(self), fields(bucket = %bucket))]
+    async fn get_bucket_ownership(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query!(
+            r#"
+            SELECT object_ownership
+            FROM bucket_config
+            WHERE bucket = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.and_then(|row| row.object_ownership)),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket object ownership"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_ownership", skip(self), fields(bucket = %bucket, ownership = %ownership))]
+    async fn save_bucket_ownership(&self, bucket: &str, ownership: &str) -> Result<()> {
+        match sqlx::query!(
+            r#"
+            INSERT INTO bucket_config (bucket, object_ownership)
+            VALUES ($1, $2)
+            ON CONFLICT (bucket) DO UPDATE
+            SET object_ownership = $2
+            "#,
+            bucket,
+            ownership
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    ownership = %ownership,
+                    "Bucket object ownership saved"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket object ownership"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_bucket_acl(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query!(
+            r#"
+            SELECT acl
+            FROM bucket_config
+            WHERE bucket = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.and_then(|row| row.acl)),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket ACL"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_acl", skip(self), fields(bucket = %bucket, acl = %acl))]
+    async fn save_bucket_acl(&self, bucket: &str, acl: &str) -> Result<()> {
+        match sqlx::query!(
+            r#"
+            INSERT INTO bucket_config (bucket, acl)
+            VALUES ($1, $2)
+            ON CONFLICT (bucket) DO UPDATE
+            SET acl = $2
+            "#,
+            bucket,
+            acl
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    acl = %acl,
+                    "Bucket ACL saved"
+                );
+                Ok(())
             }
             Err(e) => {
                 error!(
-                    target: "database",
                     error = %e,
-                    host = %host,
-                    port = %port,
-                    db = %db,
-                    "Failed to establish database connection"
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket ACL"
                 );
-                return Err(e.into());
+                Err(e.into())
             }
-        };
-
-        Ok(Self { pool })
-    }
-
-    /// Only for tests - creates a datastore with an existing pool
-    #[cfg(test)]
-    #[must_use]
-    pub fn with_pool(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+        }
     }
 
-    #[instrument(level = "info", name = "db_migration", skip(self))]
-    pub async fn migrate(&self) -> Result<()> {
-        info!(target: "database", "Running database migrations");
-        match sqlx::migrate!("./migrations").run(&self.pool).await {
-            Ok(()) => {
-                info!(target: "database", "Database migrations completed successfully");
-                Ok(())
-            }
+    async fn get_bucket_default_content_type(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query!(
+            r#"
+            SELECT default_content_type
+            FROM bucket_config
+            WHERE bucket = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.and_then(|row| row.default_content_type)),
             Err(e) => {
-                error!(error = %e, "Database migration failed");
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket default content type"
+                );
                 Err(e.into())
             }
         }
     }
 
-    // New method to sanitize database inputs for logging
-    fn sanitize_for_logging(input: &str) -> String {
-        // Simple sanitization for logging purposes
-        let shortened = if input.len() > 50 {
-            format!("{}...", &input[..47])
-        } else {
-            input.to_string()
-        };
-
-        // Remove potentially dangerous characters for logging
-        shortened
-            .chars()
-            .filter(|c| {
-                c.is_alphanumeric() || c.is_whitespace() || *c == '_' || *c == '-' || *c == '/'
-            })
-            .collect()
-    }
-
-    // New health check method
-    #[instrument(level = "info", name = "db_health_check", skip(self))]
-    pub async fn check_connection_health(&self) -> Result<()> {
-        debug!(target: "database", "Checking database connection health");
-        match sqlx::query("SELECT 1").execute(&self.pool).await {
+    #[instrument(level = "debug", name = "save_bucket_default_content_type", skip(self), fields(bucket = %bucket, content_type = %content_type))]
+    async fn save_bucket_default_content_type(
+        &self,
+        bucket: &str,
+        content_type: &str,
+    ) -> Result<()> {
+        match sqlx::query!(
+            r#"
+            INSERT INTO bucket_config (bucket, default_content_type)
+            VALUES ($1, $2)
+            ON CONFLICT (bucket) DO UPDATE
+            SET default_content_type = $2
+            "#,
+            bucket,
+            content_type
+        )
+        .execute(&self.pool)
+        .await
+        {
             Ok(_) => {
-                info!(target: "database", "Database connection is healthy");
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    content_type = %content_type,
+                    "Bucket default content type saved"
+                );
                 Ok(())
             }
             Err(e) => {
                 error!(
-                    target: "database",
                     error = %e,
-                    "Database connection health check failed"
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket default content type"
                 );
                 Err(e.into())
             }
         }
     }
-}
 
-#[async_trait]
-impl DataStore for PostgresDatastore {
-    #[instrument(level = "debug", name = "save_item", skip(self, item), fields(bucket = %item.bucket, key = %item.key))]
-    async fn save_s3_item_detail(&self, item: &S3ItemDetail) -> Result<()> {
-        debug!(
-            target: "storage",
-            "Saving S3 item detail"
-        );
+    async fn get_bucket_encryption(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query!(
+            r#"
+            SELECT encryption
+            FROM bucket_config
+            WHERE bucket = $1
+            "#,
+            bucket
+        )
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.and_then(|row| row.encryption)),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket encryption"
+                );
+                Err(e.into())
+            }
+        }
+    }
 
+    #[instrument(level = "debug", name = "save_bucket_encryption", skip(self), fields(bucket = %bucket))]
+    async fn save_bucket_encryption(&self, bucket: &str, encryption: &str) -> Result<()> {
         match sqlx::query!(
             r#"
-            INSERT INTO s3_item_detail (bucket, key, metadata, internal_info, last_modified, md5, data_location)
-            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP, $5, $6)
-            ON CONFLICT (bucket, key) DO UPDATE
-            SET metadata = $3,
-            internal_info = $4,
-            md5 = $5,
-            data_location = $6
+            INSERT INTO bucket_config (bucket, encryption)
+            VALUES ($1, $2)
+            ON CONFLICT (bucket) DO UPDATE
+            SET encryption = $2
             "#,
-            item.bucket,
-            item.key,
-            item.metadata,
-            item.internal_info,
-            item.e_tag,
-            item.data_location
+            bucket,
+            encryption
         )
         .execute(&self.pool)
-        .await {
+        .await
+        {
             Ok(_) => {
                 info!(
-                    target: "storage",
-                    bucket = %item.bucket,
-                    key = %item.key,
-                    "S3 item detail saved successfully"
+                    bucket = %sanitize_for_logging(bucket),
+                    "Bucket encryption saved"
                 );
                 Ok(())
-            },
+            }
             Err(e) => {
                 error!(
-                    target: "storage",
                     error = %e,
-                    bucket = %item.bucket,
-                    key = %item.key,
-                    "Failed to save S3 item detail"
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket encryption"
                 );
                 Err(e.into())
             }
         }
     }
 
-    #[instrument(level = "debug", name = "get_item", skip(self), fields(bucket = %bucket, key = %key))]
-    async fn get_s3_item_detail(&self, bucket: &str, key: &str) -> Result<Option<S3ItemDetail>> {
-        debug!(
-            bucket = %Self::sanitize_for_logging(bucket),
-            key = %Self::sanitize_for_logging(key),
-            "Retrieving S3 item detail"
-        );
-
-        match sqlx::query_as!(
-            S3ItemDetail,
+    async fn get_bucket_lifecycle(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query!(
             r#"
-            SELECT bucket, key, metadata, internal_info, last_modified, md5 as e_tag, data_location
-            FROM s3_item_detail
-            WHERE bucket = $1 AND key = $2
+            SELECT lifecycle
+            FROM bucket_config
+            WHERE bucket = $1
             "#,
-            bucket,
-            key
+            bucket
         )
         .fetch_optional(&self.pool)
         .await
         {
-            Ok(result) => {
-                // Only log at info level if found - reduces noise for common "not found" cases
-                if result.is_some() {
-                    info!(
-                        target: "storage",
-                        bucket = %bucket,
-                        key = %key,
-                        "S3 item detail retrieved successfully"
-                    );
-                } else {
-                    debug!(
-                        target: "storage",
-                        bucket = %bucket,
-                        key = %key,
-                        "S3 item detail not found"
-                    );
-                }
-                Ok(result)
-            }
+            Ok(result) => Ok(result.and_then(|row| row.lifecycle)),
             Err(e) => {
                 error!(
                     error = %e,
-                    bucket = %Self::sanitize_for_logging(bucket),
-                    key = %Self::sanitize_for_logging(key),
-                    "Failed to retrieve S3 item detail"
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket lifecycle"
                 );
                 Err(e.into())
             }
         }
     }
 
-    #[instrument(level = "debug", name = "get_items_with_filter", skip(self), fields(bucket = %bucket, filter = %filter))]
-    async fn get_s3_item_detail_with_filter(
-        &self,
-        bucket: &str,
-        filter: &str,
-    ) -> Result<Vec<S3ItemDetail>> {
-        debug!(
-            bucket = %Self::sanitize_for_logging(bucket),
-            filter = %Self::sanitize_for_logging(filter),
-            "Retrieving S3 items with filter"
-        );
-
-        // Add LIMIT to prevent too many results (DoS protection)
-        let filter_with_wildcard = format!("{filter}%");
-        match sqlx::query_as!(
-            S3ItemDetail,
+    #[instrument(level = "debug", name = "save_bucket_lifecycle", skip(self), fields(bucket = %bucket))]
+    async fn save_bucket_lifecycle(&self, bucket: &str, lifecycle: &str) -> Result<()> {
+        match sqlx::query!(
             r#"
-            SELECT bucket, key, metadata, internal_info, last_modified, md5 as e_tag, data_location
-            FROM s3_item_detail
-            WHERE bucket = $1 AND key LIKE $2
-            ORDER by key asc
-            LIMIT $3
+            INSERT INTO bucket_config (bucket, lifecycle)
+            VALUES ($1, $2)
+            ON CONFLICT (bucket) DO UPDATE
+            SET lifecycle = $2
             "#,
             bucket,
-            filter_with_wildcard,
-            MAX_QUERY_SIZE as i32
+            lifecycle
         )
-        .fetch_all(&self.pool)
+        .execute(&self.pool)
         .await
         {
-            Ok(result) => {
-                debug!(
-                    bucket = %Self::sanitize_for_logging(bucket),
-                    filter = %Self::sanitize_for_logging(filter),
-                    count = result.len(),
-                    "Retrieved S3 items with filter"
+            Ok(_) => {
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    "Bucket lifecycle saved"
                 );
-                Ok(result)
+                Ok(())
             }
             Err(e) => {
                 error!(
                     error = %e,
-                    bucket = %Self::sanitize_for_logging(bucket),
-                    filter = %Self::sanitize_for_logging(filter),
-                    "Failed to retrieve S3 items with filter"
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket lifecycle"
                 );
                 Err(e.into())
             }
         }
     }
 
-    #[instrument(level = "debug", name = "get_all_buckets", skip(self))]
-    async fn get_all_buckets(&self) -> Result<Vec<String>> {
-        debug!("Retrieving all buckets");
-
-        // Add LIMIT to prevent potential DoS with too many buckets
+    #[instrument(level = "debug", name = "delete_bucket_lifecycle", skip(self), fields(bucket = %bucket))]
+    async fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<()> {
         match sqlx::query!(
             r#"
-            SELECT DISTINCT bucket
-            FROM s3_item_detail
-            LIMIT $1
+            UPDATE bucket_config
+            SET lifecycle = NULL
+            WHERE bucket = $1
             "#,
-            MAX_QUERY_SIZE as i32
+            bucket
         )
-        .fetch_all(&self.pool)
+        .execute(&self.pool)
         .await
         {
-            Ok(result_set) => {
-                let result: Vec<String> = result_set.iter().map(|row| row.bucket.clone()).collect();
-                debug!(count = result.len(), "Retrieved all buckets");
-                Ok(result)
+            Ok(_) => {
+                info!(
+                    bucket = %sanitize_for_logging(bucket),
+                    "Bucket lifecycle deleted"
+                );
+                Ok(())
             }
             Err(e) => {
-                error!(error = %e, "Failed to retrieve all buckets");
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to delete bucket lifecycle"
+                );
                 Err(e.into())
             }
         }
@@ -367,32 +1608,36 @@ impl DataStore for PostgresDatastore {
 
     async fn save_multipart_upload(&self, upload: &MultipartUpload) -> Result<()> {
         debug!(
-            bucket = %Self::sanitize_for_logging(&upload.bucket),
-            key = %Self::sanitize_for_logging(&upload.key),
+            bucket = %sanitize_for_logging(&upload.bucket),
+            key = %sanitize_for_logging(&upload.key),
             upload_id = %upload.upload_id,
             "Saving multipart upload"
         );
 
         match sqlx::query!(
             r#"
-            INSERT INTO multipart_upload (upload_id, bucket, key, last_modified, metadata, access_key)
-            VALUES ($1, $2, $3, CURRENT_TIMESTAMP, $4, $5)
+            INSERT INTO multipart_upload (upload_id, bucket, key, last_modified, metadata, access_key, storage_class, content_headers)
+            VALUES ($1, $2, $3, CURRENT_TIMESTAMP, $4, $5, $6, $7)
             ON CONFLICT (upload_id, bucket, key) DO UPDATE
             SET metadata = $4,
-            access_key = $5
+            access_key = $5,
+            storage_class = $6,
+            content_headers = $7
             "#,
             upload.upload_id,
             upload.bucket,
             upload.key,
             upload.metadata,
             upload.access_key,
+            upload.storage_class,
+            upload.content_headers,
         )
         .execute(&self.pool)
         .await {
             Ok(_) => {
                 info!(
-                    bucket = %Self::sanitize_for_logging(&upload.bucket),
-                    key = %Self::sanitize_for_logging(&upload.key),
+                    bucket = %sanitize_for_logging(&upload.bucket),
+                    key = %sanitize_for_logging(&upload.key),
                     upload_id = %upload.upload_id,
                     "Multipart upload saved successfully"
                 );
@@ -401,8 +1646,8 @@ impl DataStore for PostgresDatastore {
             Err(e) => {
                 error!(
                     error = %e,
-                    bucket = %Self::sanitize_for_logging(&upload.bucket),
-                    key = %Self::sanitize_for_logging(&upload.key),
+                    bucket = %sanitize_for_logging(&upload.bucket),
+                    key = %sanitize_for_logging(&upload.key),
                     upload_id = %upload.upload_id,
                     "Failed to save multipart upload"
                 );
@@ -533,7 +1778,7 @@ impl DataStore for PostgresDatastore {
         match sqlx::query_as!(
             MultipartUpload,
             r#"
-            SELECT upload_id, bucket, key, last_modified, metadata, access_key
+            SELECT upload_id, bucket, key, last_modified, metadata, access_key, storage_class, content_headers
             FROM multipart_upload
             WHERE upload_id = $1
             "#,
@@ -595,6 +1840,53 @@ impl DataStore for PostgresDatastore {
             }
         }
     }
+
+    async fn get_stale_multipart_uploads(
+        &self,
+        older_than: NaiveDateTime,
+    ) -> Result<Vec<MultipartUpload>> {
+        debug!(older_than = %older_than, "Retrieving stale multipart uploads");
+
+        let mut tx = self.begin_admin().await?;
+        match sqlx::query_as!(
+            MultipartUpload,
+            r#"
+            SELECT upload_id, bucket, key, last_modified, metadata, access_key, storage_class, content_headers
+            FROM multipart_upload
+            WHERE last_modified < $1
+            ORDER BY last_modified ASC
+            LIMIT $2
+            "#,
+            older_than,
+            i64::from(MAX_QUERY_SIZE)
+        )
+        .fetch_all(&mut *tx)
+        .await
+        {
+            Ok(result) => {
+                tx.commit().await?;
+                debug!(count = result.len(), "Retrieved stale multipart uploads");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    older_than = %older_than,
+                    "Failed to retrieve stale multipart uploads"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn health(&self) -> DatabaseHealth {
+        self.check_connection_health().await
+    }
+
+    async fn close(&self) {
+        info!(target: "database", "Closing connection pool");
+        self.pool.close().await;
+    }
 }
 
 impl fmt::Debug for PostgresDatastore {
@@ -602,3 +1894,296 @@ impl fmt::Debug for PostgresDatastore {
         f.debug_struct("PostgresDatastore").finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect_with_retry() {
+        let mut calls = 0;
+        let result = connect_with_retry(3, Duration::from_millis(1), || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 3 {
+                    Err(crate::Error::from("not up yet"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result = connect_with_retry(2, Duration::from_millis(1), || {
+            calls += 1;
+            let attempt = calls;
+            async move { Err::<(), _>(crate::Error::from_string(format!("attempt {attempt}"))) }
+        })
+        .await;
+        assert_eq!(calls, 2);
+        assert!(result.unwrap_err().to_string().contains("attempt 2"));
+    }
+
+    #[test]
+    fn test_connect_options_statement_timeout() {
+        let ds = |statement_timeout_ms: Option<u64>| -> Ds {
+            serde_json::from_value(serde_json::json!({
+                "host": "localhost",
+                "port": 5432,
+                "db": "beggar",
+                "user": "beggar",
+                "password": "secret",
+                "schema": "public",
+                "max_connections": 5,
+                "min_connections": 1,
+                "test_before_acquire": true,
+                "acquire_slow_threshold": 1000,
+                "statement_timeout_ms": statement_timeout_ms,
+            }))
+            .unwrap()
+        };
+
+        let options = pg_connect_options(&ds(Some(1234)));
+        assert!(
+            options
+                .get_options()
+                .unwrap()
+                .contains("-c statement_timeout=1234"),
+        );
+        let options = pg_connect_options(&ds(None));
+        assert!(
+            options
+                .get_options()
+                .unwrap()
+                .contains("-c statement_timeout=30000"),
+        );
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_admin_statement_timeout(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool);
+        let mut tx = ds.begin_admin().await.unwrap();
+        let timeout: String = sqlx::query_scalar("SHOW statement_timeout")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(timeout, "5min");
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_close(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool.clone());
+        ds.close().await;
+        assert!(pool.is_closed());
+        assert!(ds.bucket_exists("bucket").await.is_err());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn test_read_acquire_times_out_before_write(
+        pool_options: PgPoolOptions,
+        connect_options: PgConnectOptions,
+    ) {
+        let pool = pool_options
+            .max_connections(1)
+            .acquire_timeout(Duration::from_secs(2))
+            .connect_with(connect_options)
+            .await
+            .unwrap();
+        let ds = PostgresDatastore::with_pool(pool.clone())
+            .with_read_acquire_timeout(Duration::from_millis(100));
+
+        // saturate the pool
+        let _held = pool.acquire().await.unwrap();
+
+        let start = Instant::now();
+        assert!(ds.get_s3_item_detail("bucket", "key").await.is_err());
+        let read_elapsed = start.elapsed();
+
+        let item = S3ItemDetail::builder()
+            .bucket("bucket".to_string())
+            .key("key".to_string())
+            .e_tag("etag".to_string())
+            .data_location("bucket/key".to_string())
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .build();
+        let start = Instant::now();
+        assert!(ds.save_s3_item_detail(&item).await.is_err());
+        let write_elapsed = start.elapsed();
+
+        assert!(read_elapsed < Duration::from_secs(1), "{read_elapsed:?}");
+        assert!(write_elapsed >= Duration::from_secs(2), "{write_elapsed:?}");
+    }
+
+    #[sqlx::test]
+    async fn test_save_s3_item_detail_keeps_last_modified(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool);
+        let last_modified = chrono::NaiveDate::from_ymd_opt(2024, 2, 29)
+            .unwrap()
+            .and_hms_micro_opt(13, 14, 15, 123_456)
+            .unwrap();
+        let item = S3ItemDetail::builder()
+            .bucket("bucket".to_string())
+            .key("key".to_string())
+            .e_tag("etag".to_string())
+            .last_modified(last_modified)
+            .data_location("bucket/key".to_string())
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .build();
+
+        ds.save_s3_item_detail(&item).await.unwrap();
+
+        let stored = ds
+            .get_s3_item_detail("bucket", "key")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.last_modified, last_modified);
+    }
+
+    #[sqlx::test]
+    async fn test_save_s3_item_detail_long_key(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool);
+        let key = format!("photos/{}/{}", "a".repeat(300), "b".repeat(600));
+        let item = S3ItemDetail::builder()
+            .bucket("bucket".to_string())
+            .key(key.clone())
+            .e_tag("etag".to_string())
+            .data_location(format!("bucket/{key}"))
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .build();
+        ds.save_s3_item_detail(&item).await.unwrap();
+
+        let stored = ds
+            .get_s3_item_detail("bucket", &key)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.key, key);
+        assert_eq!(stored.data_location, format!("bucket/{key}"));
+        let found = ds
+            .get_item_by_data_location(&format!("bucket/{key}"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.key, key);
+    }
+
+    #[test]
+    fn test_like_prefix() {
+        assert_eq!(like_prefix("photos/"), "photos/%");
+        assert_eq!(like_prefix(r"50%off_\"), r"50\%off\_\\%");
+    }
+
+    #[sqlx::test]
+    async fn test_prefix_wildcards_match_literally(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool);
+        for key in ["50%off/a", "50-off/b", "500off/c", "50_off/d"] {
+            let item = S3ItemDetail::builder()
+                .bucket("bucket".to_string())
+                .key(key.to_string())
+                .e_tag("etag".to_string())
+                .data_location(format!("bucket/{key}"))
+                .metadata(Some("{}".to_string()))
+                .internal_info(Some("{}".to_string()))
+                .build();
+            ds.save_s3_item_detail(&item).await.unwrap();
+        }
+
+        let keys = |items: Vec<S3ItemDetail>| items.into_iter().map(|i| i.key).collect::<Vec<_>>();
+        let items = ds
+            .get_s3_item_detail_with_filter("bucket", "50%off/")
+            .await
+            .unwrap();
+        assert_eq!(keys(items), ["50%off/a"]);
+        let items = ds
+            .list_latest_items_page("bucket", "50_", "", false, 10)
+            .await
+            .unwrap();
+        assert_eq!(keys(items), ["50_off/d"]);
+    }
+
+    #[sqlx::test]
+    async fn test_list_versions_page(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool);
+        let day = |d| {
+            chrono::NaiveDate::from_ymd_opt(2024, 3, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        for (key, version_id, last_modified) in [
+            ("a", "v1", day(1)),
+            ("a", "v2", day(2)),
+            ("a", "v0", day(2)),
+            ("b", "v3", day(1)),
+            ("c", "v4", day(1)),
+        ] {
+            let item = S3ItemDetail::builder()
+                .bucket("bucket".to_string())
+                .key(key.to_string())
+                .version_id(Some(version_id.to_string()))
+                .e_tag("etag".to_string())
+                .last_modified(last_modified)
+                .data_location(format!("bucket/{version_id}"))
+                .metadata(Some("{}".to_string()))
+                .internal_info(Some("{}".to_string()))
+                .build();
+            ds.save_s3_item_detail(&item).await.unwrap();
+        }
+
+        let page = async |key_marker, version_id_marker, limit| {
+            ds.list_versions_page("bucket", "", key_marker, version_id_marker, limit)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|i| i.version_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(page("", "", 3).await, ["v0", "v2", "v1"]);
+        assert_eq!(page("a", "v0", 2).await, ["v2", "v1"]);
+        assert_eq!(page("a", "v2", 10).await, ["v1", "v3", "v4"]);
+        // a missing version resumes at the next key
+        assert_eq!(page("a", "gone", 10).await, ["v3", "v4"]);
+        assert_eq!(page("b", "", 10).await, ["v4"]);
+    }
+
+    #[sqlx::test]
+    async fn test_get_stale_multipart_uploads(pool: Pool<Postgres>) {
+        let ds = PostgresDatastore::with_pool(pool.clone());
+        for upload_id in ["old", "new"] {
+            let upload = MultipartUpload::builder()
+                .upload_id(upload_id.to_string())
+                .bucket("bucket".to_string())
+                .key(upload_id.to_string())
+                .metadata("{}".to_string())
+                .access_key("access".to_string())
+                .build();
+            ds.save_multipart_upload(&upload).await.unwrap();
+        }
+        sqlx::query(
+            "UPDATE multipart_upload SET last_modified = last_modified - INTERVAL '2 days' \
+             WHERE upload_id = 'old'",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let older_than = chrono::Utc::now().naive_utc() - chrono::TimeDelta::days(1);
+        let stale = ds.get_stale_multipart_uploads(older_than).await.unwrap();
+        assert_eq!(
+            stale
+                .iter()
+                .map(|u| u.upload_id.as_str())
+                .collect::<Vec<_>>(),
+            ["old"]
+        );
+    }
+}
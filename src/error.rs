@@ -7,8 +7,22 @@ use std::panic::Location;
 
 use tracing::error;
 
+/// What went wrong, deciding the S3 error code and status a request fails with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A row or file the operation needs does not exist, `404`.
+    NotFound,
+    /// The input is rejected as given, `400`.
+    InvalidInput,
+    /// The operation clashes with the current state, `409`.
+    Conflict,
+    /// Anything else, `500`.
+    Internal,
+}
+
 #[derive(Debug)]
 pub struct Error {
+    kind: ErrorKind,
     source: StdError,
 }
 
@@ -18,8 +32,14 @@ impl Error {
     #[must_use]
     #[track_caller]
     pub fn new(source: StdError) -> Self {
+        Self::with_kind(ErrorKind::Internal, source)
+    }
+
+    #[must_use]
+    #[track_caller]
+    pub fn with_kind(kind: ErrorKind, source: StdError) -> Self {
         log(&*source);
-        Self { source }
+        Self { kind, source }
     }
 
     #[must_use]
@@ -28,6 +48,18 @@ impl Error {
         Self::new(s.into().into())
     }
 
+    /// An error of the given kind with a message, e.g. for a failed check.
+    #[must_use]
+    #[track_caller]
+    pub fn with_message(kind: ErrorKind, s: impl Into<String>) -> Self {
+        Self::with_kind(kind, s.into().into())
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
     /// Access the inner error source
     #[must_use]
     pub fn source(&self) -> &StdError {
@@ -60,11 +92,24 @@ impl From<std::io::Error> for Error {
     }
 }
 
-// Convert sqlx errors to our Error type
+// Convert sqlx errors to our Error type, telling missing rows and constraint violations
+// caused by the input apart from database failures
 impl From<sqlx::Error> for Error {
     #[track_caller]
     fn from(err: sqlx::Error) -> Self {
-        Self::new(Box::new(err))
+        let kind = match &err {
+            sqlx::Error::RowNotFound => ErrorKind::NotFound,
+            sqlx::Error::Database(e) => match e.kind() {
+                // the referenced bucket or upload is gone
+                sqlx::error::ErrorKind::ForeignKeyViolation => ErrorKind::NotFound,
+                sqlx::error::ErrorKind::UniqueViolation => ErrorKind::Conflict,
+                sqlx::error::ErrorKind::NotNullViolation
+                | sqlx::error::ErrorKind::CheckViolation => ErrorKind::InvalidInput,
+                _ => ErrorKind::Internal,
+            },
+            _ => ErrorKind::Internal,
+        };
+        Self::with_kind(kind, Box::new(err))
     }
 }
 
@@ -119,7 +164,13 @@ where
 
 impl From<Error> for S3Error {
     fn from(e: Error) -> Self {
-        S3Error::with_source(S3ErrorCode::InternalError, e.source)
+        let code = match e.kind {
+            ErrorKind::NotFound => S3ErrorCode::NoSuchKey,
+            ErrorKind::InvalidInput => S3ErrorCode::InvalidArgument,
+            ErrorKind::Conflict => S3ErrorCode::OperationAborted,
+            ErrorKind::Internal => S3ErrorCode::InternalError,
+        };
+        S3Error::with_source(code, e.source)
     }
 }
 
@@ -172,3 +223,20 @@ macro_rules! map_err {
         $result.map_err($crate::error::map_err)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_s3_error_code() {
+        let err = S3Error::from(Error::from(sqlx::Error::RowNotFound));
+        assert_eq!(*err.code(), S3ErrorCode::NoSuchKey);
+
+        let err = S3Error::from(Error::with_message(ErrorKind::Conflict, "taken"));
+        assert_eq!(*err.code(), S3ErrorCode::OperationAborted);
+
+        let err = S3Error::from(Error::from_string("broken"));
+        assert_eq!(*err.code(), S3ErrorCode::InternalError);
+    }
+}
@@ -16,20 +16,66 @@
 #[macro_use]
 mod error;
 
+mod access;
+mod access_log;
+mod acl;
+mod admin;
+mod auth;
+mod cache;
 mod checksum;
+mod clock_skew;
+mod compression;
+mod concurrency_limit;
+mod content_headers;
 mod datastore;
+mod lifecycle;
+mod maintenance;
+mod metrics;
+mod multipart_assembly;
 mod multipart_upload;
 mod multipart_upload_part;
+#[cfg(feature = "mysql")]
+mod mysql_datastore;
+mod object_lock;
+mod object_parts;
+mod restore;
 mod s3;
+mod s3_bucket;
 mod s3_item_detail;
+mod scrub;
+mod select;
+mod server_side_encryption;
 mod settings;
+mod sig_v2;
+mod sse_c;
 mod storage_backend;
+mod storage_class;
+mod transform;
 mod utils;
 
+pub use self::access::PublicReadAccess;
+pub use self::access_log::{AccessLogLayer, AccessLogService};
+pub use self::admin::{AdminLayer, AdminService, NotFound, ReadinessCheck};
+pub use self::auth::MultiKeyAuth;
+pub use self::cache::Caches;
+pub use self::clock_skew::{ClockSkewLayer, ClockSkewService, DEFAULT_MAX_CLOCK_SKEW};
+pub use self::compression::{MAX_COMPRESSED_SIZE, compression_layer};
+pub use self::concurrency_limit::{ConcurrencyLimitLayer, ConcurrencyLimitService};
 pub use self::datastore::*;
 pub use self::error::*;
+pub use self::maintenance::{MaintenanceLayer, MaintenanceService};
+pub use self::metrics::{ConnectionGuard, DiskUsage, Metrics};
 pub use self::multipart_upload::*;
 pub use self::multipart_upload_part::*;
+#[cfg(feature = "mysql")]
+pub use self::mysql_datastore::MySqlDatastore;
+pub use self::s3_bucket::S3Bucket;
 pub use self::s3_item_detail::*;
+pub use self::scrub::{ScrubOptions, ScrubReport, relocate, scrub};
 pub use self::settings::*;
-pub use self::storage_backend::StorageBackend;
+pub use self::sig_v2::{DenySigV2Layer, DenySigV2Service};
+pub use self::storage_backend::{
+    DEFAULT_MAX_BUCKETS, DEFAULT_MAX_COPY_SIZE, ListOrder, ObjectPage, StorageBackend,
+    clean_stale_multipart_uploads,
+};
+pub use self::transform::{ObjectInfo, ObjectTransform};
@@ -0,0 +1,331 @@
+use chrono::{NaiveDateTime, TimeDelta};
+use s3s::{
+    S3Result,
+    dto::{
+        BucketLifecycleConfiguration, ExpirationStatus, LifecycleExpiration, LifecycleRule,
+        LifecycleRuleFilter, LifecycleRules,
+    },
+    s3_error,
+};
+
+/// Rules a lifecycle configuration may hold, as on AWS.
+const MAX_RULES: usize = 1000;
+
+/// Longest rule id accepted, as on AWS.
+const MAX_ID_LEN: usize = 255;
+
+/// A lifecycle rule expiring the objects under `prefix` `days` after their last modification.
+///
+/// Expiration of current versions is the only supported action, a versioned bucket gets a
+/// delete marker like a `DeleteObject` without a version id would leave.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpirationRule {
+    pub id: Option<String>,
+    pub prefix: String,
+    pub days: u32,
+    pub enabled: bool,
+}
+
+impl ExpirationRule {
+    /// When an object last modified at `last_modified` expires: at midnight UTC after the
+    /// last day, so a rule of 0 days expires objects at the next midnight.
+    pub fn expiry_date(&self, last_modified: NaiveDateTime) -> NaiveDateTime {
+        (last_modified.date() + TimeDelta::days(i64::from(self.days) + 1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or(last_modified)
+    }
+
+    fn applies_to(&self, key: &str) -> bool {
+        self.enabled && key.starts_with(&self.prefix)
+    }
+}
+
+/// Checks a lifecycle configuration and returns its rules.
+///
+/// Only rules expiring current versions after a number of days, filtered by prefix, are
+/// supported, the others fail with `NotImplemented` rather than being stored and ignored.
+pub fn check_bucket_config(config: &BucketLifecycleConfiguration) -> S3Result<Vec<ExpirationRule>> {
+    if config.rules.is_empty() {
+        return Err(s3_error!(MalformedXML, "at least one rule is required"));
+    }
+    if config.rules.len() > MAX_RULES {
+        return Err(s3_error!(
+            MalformedXML,
+            "at most {MAX_RULES} rules are allowed"
+        ));
+    }
+    let mut rules = Vec::with_capacity(config.rules.len());
+    for rule in &config.rules {
+        let rule = check_rule(rule)?;
+        if rule.id.is_some() && rules.iter().any(|r: &ExpirationRule| r.id == rule.id) {
+            return Err(s3_error!(InvalidArgument, "rule ids must be unique"));
+        }
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+fn check_rule(rule: &LifecycleRule) -> S3Result<ExpirationRule> {
+    if rule.id.as_ref().is_some_and(|id| id.len() > MAX_ID_LEN) {
+        return Err(s3_error!(
+            InvalidArgument,
+            "the rule id must be at most {MAX_ID_LEN} characters long"
+        ));
+    }
+    if rule.transitions.as_ref().is_some_and(|t| !t.is_empty())
+        || rule
+            .noncurrent_version_transitions
+            .as_ref()
+            .is_some_and(|t| !t.is_empty())
+    {
+        return Err(s3_error!(NotImplemented, "transitions are not supported"));
+    }
+    if rule.noncurrent_version_expiration.is_some() {
+        return Err(s3_error!(
+            NotImplemented,
+            "expiration of noncurrent versions is not supported"
+        ));
+    }
+    if rule.abort_incomplete_multipart_upload.is_some() {
+        return Err(s3_error!(
+            NotImplemented,
+            "incomplete multipart uploads are removed after --multipart-upload-ttl-secs"
+        ));
+    }
+    let prefix = match (&rule.filter, &rule.prefix) {
+        (Some(_), Some(_)) => {
+            return Err(s3_error!(
+                MalformedXML,
+                "a rule cannot have both a filter and a prefix"
+            ));
+        }
+        (Some(filter), None) => filter_prefix(filter)?,
+        (None, prefix) => prefix.clone().unwrap_or_default(),
+    };
+    let Some(expiration) = &rule.expiration else {
+        return Err(s3_error!(MalformedXML, "a rule must have an expiration"));
+    };
+    if expiration.date.is_some() || expiration.expired_object_delete_marker.is_some() {
+        return Err(s3_error!(
+            NotImplemented,
+            "only expiration after a number of days is supported"
+        ));
+    }
+    let Some(days) = expiration.days else {
+        return Err(s3_error!(MalformedXML, "the expiration must have days"));
+    };
+    let Ok(days) = u32::try_from(days) else {
+        return Err(s3_error!(
+            InvalidArgument,
+            "the days of an expiration must not be negative"
+        ));
+    };
+    Ok(ExpirationRule {
+        id: rule.id.clone(),
+        prefix,
+        days,
+        enabled: rule.status.as_str() == ExpirationStatus::ENABLED,
+    })
+}
+
+fn filter_prefix(filter: &LifecycleRuleFilter) -> S3Result<String> {
+    let and_is_prefix_only = filter.and.as_ref().is_none_or(|and| {
+        and.tags.as_ref().is_none_or(Vec::is_empty)
+            && and.object_size_greater_than.is_none()
+            && and.object_size_less_than.is_none()
+    });
+    if filter.tag.is_some()
+        || filter.object_size_greater_than.is_some()
+        || filter.object_size_less_than.is_some()
+        || !and_is_prefix_only
+    {
+        return Err(s3_error!(
+            NotImplemented,
+            "only filtering by prefix is supported"
+        ));
+    }
+    let and_prefix = filter.and.as_ref().and_then(|and| and.prefix.clone());
+    Ok(filter.prefix.clone().or(and_prefix).unwrap_or_default())
+}
+
+/// The rules as returned by `GetBucketLifecycleConfiguration`, filters are always used
+/// instead of the deprecated rule prefix.
+pub fn to_config_rules(rules: &[ExpirationRule]) -> LifecycleRules {
+    rules
+        .iter()
+        .map(|rule| LifecycleRule {
+            abort_incomplete_multipart_upload: None,
+            expiration: Some(LifecycleExpiration {
+                date: None,
+                days: i32::try_from(rule.days).ok(),
+                expired_object_delete_marker: None,
+            }),
+            filter: Some(LifecycleRuleFilter {
+                and: None,
+                object_size_greater_than: None,
+                object_size_less_than: None,
+                prefix: Some(rule.prefix.clone()),
+                tag: None,
+            }),
+            id: rule.id.clone(),
+            noncurrent_version_expiration: None,
+            noncurrent_version_transitions: None,
+            prefix: None,
+            status: ExpirationStatus::from_static(if rule.enabled {
+                ExpirationStatus::ENABLED
+            } else {
+                ExpirationStatus::DISABLED
+            }),
+            transitions: None,
+        })
+        .collect()
+}
+
+pub fn rules_to_json(rules: &[ExpirationRule]) -> String {
+    let rules = rules
+        .iter()
+        .map(|rule| {
+            serde_json::json!({
+                "id": rule.id,
+                "prefix": rule.prefix,
+                "days": rule.days,
+                "enabled": rule.enabled,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({ "rules": rules }).to_string()
+}
+
+pub fn rules_from_json(json: &str) -> Option<Vec<ExpirationRule>> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    value
+        .get("rules")?
+        .as_array()?
+        .iter()
+        .map(|rule| {
+            Some(ExpirationRule {
+                id: rule.get("id").and_then(|v| v.as_str()).map(str::to_owned),
+                prefix: rule.get("prefix")?.as_str()?.to_owned(),
+                days: u32::try_from(rule.get("days")?.as_u64()?).ok()?,
+                enabled: rule.get("enabled")?.as_bool()?,
+            })
+        })
+        .collect()
+}
+
+/// The earliest expiration of the object `key` last modified at `last_modified` among the
+/// enabled rules, with the rule that sets it.
+pub fn expiration<'a>(
+    rules: &'a [ExpirationRule],
+    key: &str,
+    last_modified: NaiveDateTime,
+) -> Option<(NaiveDateTime, &'a ExpirationRule)> {
+    rules
+        .iter()
+        .filter(|rule| rule.applies_to(key))
+        .map(|rule| (rule.expiry_date(last_modified), rule))
+        .min_by_key(|(expiry_date, _)| *expiry_date)
+}
+
+/// Value of the `x-amz-expiration` header.
+pub fn header_value(expiry_date: NaiveDateTime, rule: &ExpirationRule) -> String {
+    format!(
+        "expiry-date=\"{}\", rule-id=\"{}\"",
+        expiry_date.format("%a, %d %b %Y %H:%M:%S GMT"),
+        rule.id.as_deref().unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(prefix: &str, days: i32) -> LifecycleRule {
+        LifecycleRule {
+            abort_incomplete_multipart_upload: None,
+            expiration: Some(LifecycleExpiration {
+                date: None,
+                days: Some(days),
+                expired_object_delete_marker: None,
+            }),
+            filter: Some(LifecycleRuleFilter {
+                and: None,
+                object_size_greater_than: None,
+                object_size_less_than: None,
+                prefix: Some(prefix.to_owned()),
+                tag: None,
+            }),
+            id: Some(format!("{prefix}rule")),
+            noncurrent_version_expiration: None,
+            noncurrent_version_transitions: None,
+            prefix: None,
+            status: ExpirationStatus::from_static(ExpirationStatus::ENABLED),
+            transitions: None,
+        }
+    }
+
+    fn date(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_expiration() {
+        let config = BucketLifecycleConfiguration {
+            rules: vec![rule("logs/", 30), rule("tmp/", 0), rule("", 365)],
+        };
+        let rules = check_bucket_config(&config).unwrap();
+
+        // a rule of 0 days expires objects at the next midnight
+        let now = date("2025-05-13 10:00:00");
+        let (expiry_date, rule) = expiration(&rules, "tmp/a", date("2025-05-12 23:59:00")).unwrap();
+        assert_eq!(expiry_date, date("2025-05-13 00:00:00"));
+        assert!(expiry_date <= now);
+        assert_eq!(
+            header_value(expiry_date, rule),
+            "expiry-date=\"Tue, 13 May 2025 00:00:00 GMT\", rule-id=\"tmp/rule\""
+        );
+
+        let (expiry_date, rule) =
+            expiration(&rules, "logs/a", date("2025-05-12 10:00:00")).unwrap();
+        assert_eq!(expiry_date, date("2025-06-12 00:00:00"));
+        assert_eq!(rule.id.as_deref(), Some("logs/rule"));
+
+        let mut rules = rules;
+        rules[2].enabled = false;
+        assert!(expiration(&rules, "data/a", date("2025-05-12 10:00:00")).is_none());
+    }
+
+    #[test]
+    fn test_bucket_config_round_trip() {
+        let config = BucketLifecycleConfiguration {
+            rules: vec![rule("logs/", 30)],
+        };
+        let rules = check_bucket_config(&config).unwrap();
+        let json = rules_to_json(&rules);
+        assert_eq!(rules_from_json(&json), Some(rules.clone()));
+        let returned = BucketLifecycleConfiguration {
+            rules: to_config_rules(&rules),
+        };
+        assert_eq!(check_bucket_config(&returned).unwrap(), rules);
+
+        let mut unsupported = rule("logs/", 30);
+        unsupported.expiration.as_mut().unwrap().days = None;
+        unsupported
+            .expiration
+            .as_mut()
+            .unwrap()
+            .expired_object_delete_marker = Some(true);
+        let config = BucketLifecycleConfiguration {
+            rules: vec![unsupported],
+        };
+        assert!(check_bucket_config(&config).is_err());
+
+        let config = BucketLifecycleConfiguration {
+            rules: vec![rule("logs/", -1)],
+        };
+        assert!(check_bucket_config(&config).is_err());
+
+        let config = BucketLifecycleConfiguration { rules: vec![] };
+        assert!(check_bucket_config(&config).is_err());
+    }
+}
@@ -2,22 +2,41 @@
 #![deny(clippy::all, clippy::pedantic)]
 #![allow(clippy::needless_return)]
 
-use std::{io::IsTerminal, path::PathBuf};
+use std::{
+    io::IsTerminal,
+    net::SocketAddr,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use beggar::{PostgresDatastore, Result, StorageBackend};
+use beggar::{
+    AccessLogLayer, AdminLayer, Caches, ClockSkewLayer, ConcurrencyLimitLayer, Credential,
+    DEFAULT_MAX_BUCKETS, DEFAULT_MAX_CLOCK_SKEW, DEFAULT_MAX_COPY_SIZE, DataStore, DenySigV2Layer,
+    Driver, Layout, MaintenanceLayer, Metrics, MultiKeyAuth, NotFound, PostgresDatastore,
+    PublicReadAccess, Result, ScrubOptions, Settings, StorageBackend,
+    clean_stale_multipart_uploads, compression_layer, relocate, scrub,
+};
 use clap::{CommandFactory, Parser};
 use hyper_util::{
     rt::{TokioExecutor, TokioIo},
     server::conn::auto::Builder as ConnBuilder,
     service::TowerToHyperService,
 };
-use s3s::{auth::SimpleAuth, service::S3ServiceBuilder};
+use s3s::service::S3ServiceBuilder;
 use tokio::net::TcpListener;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
+
+/// How often the disk gauges of the storage root are refreshed.
+const DISK_USAGE_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Parser)]
-#[command(version)]
+#[command(version, subcommand_negates_reqs = true)]
+#[allow(clippy::struct_excessive_bools)] // independent command line flags
 struct Opt {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Host name to listen on.
     #[arg(long, default_value = "localhost")]
     host: String,
@@ -34,12 +53,196 @@ struct Opt {
     #[arg(long)]
     secret_key: Option<String>,
 
+    /// Additional access key and secret key accepted, as `<access-key>:<secret-key>`.
+    /// Repeat it for more tenants, more can be listed under `credentials` in the settings.
+    #[arg(long, value_name = "ACCESS_KEY:SECRET_KEY", value_parser = parse_credential)]
+    credential: Vec<(String, String)>,
+
+    /// Serves the admin routes (metrics, health, cache flushing) on this address
+    /// instead of the S3 port.
+    #[arg(long)]
+    admin_addr: Option<SocketAddr>,
+
+    /// Bearer token required by the admin routes, they are disabled without one.
+    #[arg(long)]
+    admin_token: Option<String>,
+
+    /// Region reported as the location of the buckets, e.g. `eu-west-1`.
+    /// Creating a bucket in another region is then rejected.
+    #[arg(long)]
+    region: Option<String>,
+
     /// Domain names used for virtual-hosted-style requests.
     #[arg(long)]
     domain: Vec<String>,
 
+    /// Rejects writes with 503 and this message while keeping reads available.
+    #[arg(long)]
+    maintenance_message: Option<String>,
+
+    /// Rejects every mutating request with `AccessDenied`, for serving immutable datasets.
+    #[arg(long)]
+    read_only: bool,
+
+    /// Creates the bucket on the first upload to a missing bucket instead of answering
+    /// `NoSuchBucket`.
+    #[arg(long)]
+    auto_create_buckets: bool,
+
+    /// Writes multipart parts arriving in order straight into the final file,
+    /// completing the upload without copying them.
+    #[arg(long)]
+    direct_multipart_assembly: bool,
+
+    /// Buckets that may exist at once, creating more fails with `TooManyBuckets`.
+    /// 0 lifts the limit.
+    #[arg(long, default_value_t = DEFAULT_MAX_BUCKETS)]
+    max_buckets: u64,
+
+    /// Bytes a single copy may produce, larger copies fail with `EntityTooLarge`.
+    #[arg(long, default_value_t = DEFAULT_MAX_COPY_SIZE)]
+    max_copy_size: u64,
+
+    /// Seconds after which an unfinished multipart upload is removed with its parts.
+    #[arg(long, default_value = "604800")]
+    multipart_upload_ttl_secs: u64,
+
+    /// Seconds between sweeps for stale multipart uploads, 0 disables them.
+    #[arg(long, default_value = "3600")]
+    multipart_cleanup_interval_secs: u64,
+
+    /// Seconds between sweeps for objects expired by bucket lifecycle rules, 0 disables them.
+    #[arg(long, default_value = "3600")]
+    lifecycle_interval_secs: u64,
+
+    /// Seconds the date of a signed request may differ from the server clock before it is
+    /// rejected with `RequestTimeTooSkewed`, 0 disables the check.
+    #[arg(long, default_value_t = DEFAULT_MAX_CLOCK_SKEW.as_secs())]
+    max_clock_skew_secs: u64,
+
+    /// Requests served at once, more are rejected with `503 SlowDown`. 0 lifts the limit.
+    #[arg(long, default_value = "0")]
+    max_concurrent_requests: usize,
+
+    /// Requests served at once for a single access key, more are rejected with
+    /// `503 SlowDown`. 0 lifts the limit.
+    #[arg(long, default_value = "0")]
+    max_concurrent_requests_per_key: usize,
+
+    /// Sync every written file and its directory to disk before acknowledging the write, so
+    /// stored objects survive a crash or power loss at the cost of throughput.
+    #[arg(long)]
+    durable: bool,
+
+    /// Accept requests signed with AWS Signature Version 2, for legacy clients that cannot
+    /// sign with version 4. They are rejected with `403 AccessDenied` otherwise.
+    #[arg(long)]
+    allow_sigv2: bool,
+
+    /// Compress listings and small objects with gzip or deflate for clients that accept it.
+    #[arg(long)]
+    compress: bool,
+
+    /// Format of the log output, including the per request access log.
+    #[arg(long, value_enum, default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+
+    /// Seconds to wait for in-flight connections on shutdown, 0 waits indefinitely.
+    #[arg(long, default_value = "10")]
+    shutdown_timeout_secs: u64,
+
     /// Root directory of stored data.
-    root: PathBuf,
+    #[arg(required = true)]
+    root: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Checks that the data of every stored object version exists, then exits.
+    ///
+    /// Findings are reported in the log, nothing is changed without `--fix`.
+    Scrub {
+        /// Deletes the rows of object versions whose data is missing.
+        #[arg(long)]
+        fix: bool,
+
+        /// Also recomputes the MD5 of the data and compares it with the stored `ETag`.
+        #[arg(long)]
+        verify_checksums: bool,
+
+        /// Root directory of stored data.
+        root: PathBuf,
+    },
+
+    /// Moves the data of every stored object version to the `storage.layout` of the
+    /// settings, then exits.
+    ///
+    /// Stop the server first, it may otherwise overwrite an object being moved.
+    Relocate {
+        /// Root directory of stored data.
+        root: PathBuf,
+    },
+
+    /// Registers the files under a directory as objects of a bucket, then exits.
+    ///
+    /// Keys are the paths of the files relative to the directory.
+    Import {
+        /// Moves the files under the root instead of copying them.
+        #[arg(long = "move")]
+        move_files: bool,
+
+        /// Existing bucket the objects are added to.
+        bucket: String,
+
+        /// Directory holding the files.
+        dir: PathBuf,
+
+        /// Root directory of stored data.
+        root: PathBuf,
+    },
+
+    /// Writes the objects of a bucket to a directory, at their keys, then exits.
+    Export {
+        /// Bucket whose objects are written.
+        bucket: String,
+
+        /// Directory the files are written to, created if missing.
+        dir: PathBuf,
+
+        /// Root directory of stored data.
+        root: PathBuf,
+    },
+
+    /// Applies bucket lifecycle rules.
+    Lifecycle {
+        #[command(subcommand)]
+        command: LifecycleCommand,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum LifecycleCommand {
+    /// Deletes the objects of a bucket that expired by its lifecycle rules now, then exits.
+    Run {
+        /// Only lists the objects that would expire, nothing is deleted.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Bucket whose rules are applied.
+        bucket: String,
+
+        /// Root directory of stored data.
+        root: PathBuf,
+    },
+}
+
+fn parse_credential(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((access_key, secret_key)) if !access_key.is_empty() && !secret_key.is_empty() => {
+            Ok((access_key.to_owned(), secret_key.to_owned()))
+        }
+        _ => Err("expected <access-key>:<secret-key>".to_owned()),
+    }
 }
 
 fn settings() -> Result<beggar::Settings, config::ConfigError> {
@@ -52,17 +255,35 @@ fn settings() -> Result<beggar::Settings, config::ConfigError> {
     s.try_deserialize()
 }
 
-fn setup_tracing() {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum LogFormat {
+    /// Human readable, multi-line events.
+    Pretty,
+    /// One JSON object per line, for log shippers.
+    Json,
+}
+
+fn setup_tracing(log_format: LogFormat) {
     use tracing_subscriber::EnvFilter;
 
     let env_filter = EnvFilter::from_default_env();
-    let enable_color = std::io::stdout().is_terminal();
 
-    tracing_subscriber::fmt()
-        .pretty()
-        .with_env_filter(env_filter)
-        .with_ansi(enable_color)
-        .init();
+    match log_format {
+        LogFormat::Pretty => {
+            let enable_color = std::io::stdout().is_terminal();
+            tracing_subscriber::fmt()
+                .pretty()
+                .with_env_filter(env_filter)
+                .with_ansi(enable_color)
+                .init();
+        }
+        LogFormat::Json => {
+            tracing_subscriber::fmt()
+                .json()
+                .with_env_filter(env_filter)
+                .init();
+        }
+    }
 }
 
 fn check_cli_args(opt: &Opt) {
@@ -88,10 +309,39 @@ fn main() -> Result {
     let opt = Opt::parse();
     check_cli_args(&opt);
 
-    setup_tracing();
+    setup_tracing(opt.log_format);
     run(opt)
 }
 
+/// Waits for in-flight connections to finish, giving up after `shutdown_timeout` seconds.
+async fn drain_connections(
+    graceful: hyper_util::server::graceful::GracefulShutdown,
+    metrics: &Metrics,
+    shutdown_timeout: u64,
+) {
+    info!(
+        active_connections = metrics.active_connections(),
+        "draining connections"
+    );
+
+    if shutdown_timeout == 0 {
+        graceful.shutdown().await;
+        tracing::debug!("Gracefully shut down!");
+    } else {
+        tokio::select! {
+            () = graceful.shutdown() => {
+                 tracing::debug!("Gracefully shut down!");
+            },
+            () = tokio::time::sleep(std::time::Duration::from_secs(shutdown_timeout)) => {
+                 tracing::warn!(
+                     active_connections = metrics.active_connections(),
+                     "Waited {shutdown_timeout} seconds for graceful shutdown, aborting..."
+                 );
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn run(opt: Opt) -> Result {
     // load application settings / configuration
@@ -107,23 +357,315 @@ async fn run(opt: Opt) -> Result {
 
     info!(host = ?s.datasource.host, port = s.datasource.port, "settings loaded");
 
-    // Use the asynchronous connect method instead of new
-    let ds = match PostgresDatastore::connect(&s).await {
-        Ok(ds) => ds,
-        Err(e) => {
-            error!("Failed to connect to database: {}", e);
-            return Err(e);
+    match s.datasource.driver {
+        Driver::Postgres => {
+            // Use the asynchronous connect method instead of new
+            let ds = match PostgresDatastore::connect(&s).await {
+                Ok(ds) => ds,
+                Err(e) => {
+                    error!("Failed to connect to database: {}", e);
+                    return Err(e);
+                }
+            };
+
+            // Run migrations after successful connection
+            if let Err(e) = ds.migrate().await {
+                error!("Failed to run database migrations: {}", e);
+                return Err(e);
+            }
+
+            start(opt, &s, ds).await
+        }
+        #[cfg(feature = "mysql")]
+        Driver::Mysql => {
+            let ds = match beggar::MySqlDatastore::connect(&s).await {
+                Ok(ds) => ds,
+                Err(e) => {
+                    error!("Failed to connect to database: {}", e);
+                    return Err(e);
+                }
+            };
+
+            if let Err(e) = ds.migrate().await {
+                error!("Failed to run database migrations: {}", e);
+                return Err(e);
+            }
+
+            start(opt, &s, ds).await
+        }
+        #[cfg(not(feature = "mysql"))]
+        Driver::Mysql => {
+            let msg = "the mysql driver requires beggar to be built with the `mysql` feature";
+            error!(msg);
+            Err(beggar::Error::from_string(msg))
+        }
+    }
+}
+
+/// Runs the subcommand if one was given, otherwise serves.
+async fn start<T: DataStore + Clone>(opt: Opt, settings: &Settings, ds: T) -> Result {
+    match &opt.command {
+        None => serve(opt, settings, ds).await,
+        Some(Command::Scrub {
+            fix,
+            verify_checksums,
+            root,
+        }) => {
+            let options = ScrubOptions {
+                fix: *fix,
+                verify_checksums: *verify_checksums,
+            };
+            run_scrub(ds, root, options).await
+        }
+        Some(Command::Relocate { root }) => run_relocate(ds, root, settings.storage.layout).await,
+        Some(Command::Import {
+            move_files,
+            bucket,
+            dir,
+            root,
+        }) => {
+            let fs = storage_backend(&opt, settings, root, None, ds)?;
+            run_import(fs, bucket, dir, *move_files).await
+        }
+        Some(Command::Export { bucket, dir, root }) => {
+            run_export(&opt, settings, bucket, dir, root, ds).await
+        }
+        Some(Command::Lifecycle {
+            command:
+                LifecycleCommand::Run {
+                    dry_run,
+                    bucket,
+                    root,
+                },
+        }) => run_lifecycle(&opt, settings, bucket, root, *dry_run, ds).await,
+    }
+}
+
+async fn run_scrub<T: DataStore>(ds: T, root: &Path, options: ScrubOptions) -> Result {
+    let report = scrub(&ds, root, options).await;
+    ds.close().await;
+    let report = report?;
+    info!(
+        checked = report.checked,
+        missing = report.missing,
+        corrupt = report.corrupt,
+        removed = report.removed,
+        "scrub finished"
+    );
+    if report.missing > report.removed || report.corrupt > 0 {
+        return Err(beggar::Error::from_string(
+            "the scrub found object versions with missing or corrupt data",
+        ));
+    }
+    Ok(())
+}
+
+async fn run_relocate<T: DataStore>(ds: T, root: &Path, layout: Layout) -> Result {
+    let moved = relocate(&ds, root, layout).await;
+    ds.close().await;
+    let moved = moved?;
+    info!(?layout, moved, "relocation finished");
+    Ok(())
+}
+
+async fn run_import<T: DataStore>(
+    fs: StorageBackend<T>,
+    bucket: &str,
+    dir: &Path,
+    move_files: bool,
+) -> Result {
+    let imported = fs.import_dir(bucket, dir, move_files).await;
+    fs.datastore.close().await;
+    let imported = imported?;
+    info!(bucket, imported, "import finished");
+    Ok(())
+}
+
+async fn run_export<T: DataStore>(
+    opt: &Opt,
+    settings: &Settings,
+    bucket: &str,
+    dir: &Path,
+    root: &Path,
+    ds: T,
+) -> Result {
+    let fs = storage_backend(opt, settings, root, None, ds)?;
+    let exported = fs.export_dir(bucket, dir).await;
+    fs.datastore.close().await;
+    let exported = exported?;
+    info!(bucket, exported, "export finished");
+    Ok(())
+}
+
+async fn run_lifecycle<T: DataStore>(
+    opt: &Opt,
+    settings: &Settings,
+    bucket: &str,
+    root: &Path,
+    dry_run: bool,
+    ds: T,
+) -> Result {
+    let fs = storage_backend(opt, settings, root, None, ds)?;
+    let expired = fs.expire_objects(bucket, dry_run).await;
+    fs.datastore.close().await;
+    let expired = expired?;
+    info!(bucket, expired, dry_run, "lifecycle run finished");
+    Ok(())
+}
+
+/// Keeps the disk gauges of the storage root up to date.
+async fn refresh_disk_usage(metrics: std::sync::Arc<Metrics>, root: PathBuf) {
+    let mut interval = tokio::time::interval(DISK_USAGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let metrics = metrics.clone();
+        let root = root.clone();
+        match tokio::task::spawn_blocking(move || metrics.refresh_disk_usage(&root)).await {
+            Ok(Ok(usage)) => debug!(?usage, "disk usage refreshed"),
+            Ok(Err(e)) => warn!("failed to read the disk usage: {e}"),
+            Err(e) => warn!("disk usage refresh panicked: {e}"),
         }
+    }
+}
+
+/// Starts serving the admin routes on `admin_addr` if given, otherwise returns them
+/// to be served on the S3 port.
+async fn bind_admin(
+    admin_addr: Option<SocketAddr>,
+    admin: AdminLayer,
+) -> Result<Option<AdminLayer>> {
+    let Some(admin_addr) = admin_addr else {
+        return Ok(Some(admin));
     };
+    let listener = TcpListener::bind(admin_addr).await?;
+    info!(
+        "admin routes are served at http://{}",
+        listener.local_addr()?
+    );
+    tokio::spawn(serve_admin(listener, admin));
+    Ok(None)
+}
 
-    // Run migrations after successful connection
-    if let Err(e) = ds.migrate().await {
-        error!("Failed to run database migrations: {}", e);
-        return Err(e);
+/// Serves the admin routes alone, anything else answers 404.
+async fn serve_admin(listener: TcpListener, admin: AdminLayer) {
+    let http_server = ConnBuilder::new(TokioExecutor::new());
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                error!("error accepting admin connection: {err}");
+                continue;
+            }
+        };
+        let service = TowerToHyperService::new(tower::Layer::layer(&admin, NotFound));
+        let conn = http_server
+            .serve_connection(TokioIo::new(stream), service)
+            .into_owned();
+        tokio::spawn(async move {
+            if let Err(err) = conn.await {
+                debug!("admin connection failed: {err}");
+            }
+        });
     }
+}
 
-    // Setup S3 provider
-    let fs = match StorageBackend::new(opt.root, ds) {
+/// Removes multipart uploads older than `ttl` every `period`.
+async fn sweep_stale_multipart_uploads<T: DataStore>(ds: T, period: Duration, ttl: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let Some(older_than) = chrono::TimeDelta::from_std(ttl)
+            .ok()
+            .and_then(|ttl| chrono::Utc::now().naive_utc().checked_sub_signed(ttl))
+        else {
+            continue;
+        };
+        match clean_stale_multipart_uploads(&ds, older_than).await {
+            Ok(0) => debug!("no stale multipart uploads"),
+            Ok(count) => info!(count, "stale multipart uploads removed"),
+            Err(e) => warn!("failed to remove stale multipart uploads: {e}"),
+        }
+    }
+}
+
+/// Deletes the objects expired by the lifecycle rules of every bucket every `period`.
+///
+/// The sweeper shares the metadata cache of the server, which stops serving an expired
+/// object as soon as it is deleted.
+async fn sweep_expired_objects<T: DataStore>(fs: StorageBackend<T>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        let buckets = match fs.datastore.list_buckets().await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                warn!("failed to list the buckets to expire objects in: {e}");
+                continue;
+            }
+        };
+        for bucket in buckets {
+            match fs.expire_objects(&bucket.name, false).await {
+                Ok(0) => debug!(bucket = %bucket.name, "no expired objects"),
+                Ok(count) => info!(bucket = %bucket.name, count, "expired objects deleted"),
+                Err(e) => warn!(bucket = %bucket.name, "failed to expire objects: {e}"),
+            }
+        }
+    }
+}
+
+/// Starts the background sweeps that are enabled.
+fn spawn_sweepers<T: DataStore + Clone>(
+    opt: &Opt,
+    settings: &Settings,
+    root: &Path,
+    ds: &T,
+    caches: &Caches,
+) -> Result {
+    if opt.multipart_cleanup_interval_secs > 0 {
+        tokio::spawn(sweep_stale_multipart_uploads(
+            ds.clone(),
+            Duration::from_secs(opt.multipart_cleanup_interval_secs),
+            Duration::from_secs(opt.multipart_upload_ttl_secs),
+        ));
+    }
+    // expiring deletes objects, which a read-only server refuses
+    if opt.lifecycle_interval_secs > 0 && !opt.read_only {
+        let fs = storage_backend(opt, settings, root, None, ds.clone())?.with_caches(caches);
+        tokio::spawn(sweep_expired_objects(
+            fs,
+            Duration::from_secs(opt.lifecycle_interval_secs),
+        ));
+    }
+    Ok(())
+}
+
+/// Collects the accepted access keys, from the command line first, then from the settings.
+/// A key given more than once keeps its first secret key.
+fn auth(opt: &Opt, credentials: &[Credential]) -> MultiKeyAuth {
+    let mut auth = MultiKeyAuth::new();
+    let single = opt.access_key.iter().zip(&opt.secret_key);
+    let listed = opt.credential.iter().map(|(ak, sk)| (ak, sk));
+    let configured = credentials.iter().map(|c| (&c.access_key, &c.secret_key));
+    for (access_key, secret_key) in single.chain(listed).chain(configured) {
+        if !auth.register(access_key.clone(), secret_key.as_str().into()) {
+            warn!(
+                access_key,
+                "access key given more than once, the first one is used"
+            );
+        }
+    }
+    auth
+}
+
+/// Sets up the S3 provider from the command line options and the settings.
+fn storage_backend<T: DataStore>(
+    opt: &Opt,
+    settings: &Settings,
+    root: &Path,
+    cache: Option<&beggar::Cache>,
+    ds: T,
+) -> Result<StorageBackend<T>> {
+    let mut fs = match StorageBackend::new(root, ds) {
         Ok(fs) => fs,
         Err(e) => {
             error!("Failed to initialize storage backend: {}", e);
@@ -131,23 +673,103 @@ async fn run(opt: Opt) -> Result {
         }
     };
 
+    if let Some(cache) = cache
+        && let Some(capacity) = NonZeroUsize::new(cache.capacity)
+    {
+        fs = fs.with_item_cache(
+            capacity,
+            Duration::from_secs(cache.ttl_secs),
+            Duration::from_secs(cache.negative_ttl_secs),
+        );
+        info!(
+            capacity,
+            ttl_secs = cache.ttl_secs,
+            negative_ttl_secs = cache.negative_ttl_secs,
+            "object metadata cache is enabled"
+        );
+    }
+    Ok(fs
+        .with_auto_create_buckets(opt.auto_create_buckets)
+        .with_domains(opt.domain.clone())
+        .with_region(opt.region.clone())
+        .with_direct_assembly(opt.direct_multipart_assembly)
+        .with_read_only(opt.read_only)
+        .with_durable(opt.durable)
+        .with_max_copy_size(opt.max_copy_size)
+        .with_max_buckets(opt.max_buckets)
+        .with_layout(settings.storage.layout))
+}
+
+async fn serve<T: DataStore + Clone>(opt: Opt, settings: &Settings, ds: T) -> Result {
+    let Some(root) = opt.root.clone() else {
+        return Err(beggar::Error::from_string("the root directory is required"));
+    };
+    // Setup S3 provider
+    let fs = storage_backend(&opt, settings, &root, settings.cache.as_ref(), ds.clone())?;
+    let caches = fs.caches();
+    spawn_sweepers(&opt, settings, &root, &ds, &caches)?;
+
     // Setup S3 service
     let service = {
+        let virtual_hosts = fs.virtual_hosts()?;
         let mut b = S3ServiceBuilder::new(fs);
 
+        // Route virtual-hosted-style requests to their bucket
+        if let Some(virtual_hosts) = virtual_hosts {
+            b.set_host(virtual_hosts);
+            info!(domains = ?opt.domain, "virtual-hosted-style requests are enabled");
+        }
+
         // Enable authentication
-        if let (Some(ak), Some(sk)) = (opt.access_key, opt.secret_key) {
-            b.set_auth(SimpleAuth::from_single(ak, sk));
-            info!("authentication is enabled");
+        let auth = auth(&opt, &settings.credentials);
+        if !auth.is_empty() {
+            b.set_auth(auth);
+            // unsigned requests may still read public objects, checked without the cache
+            let fs = storage_backend(&opt, settings, &root, None, ds.clone())?;
+            b.set_access(PublicReadAccess::new(fs));
         }
 
         b.build().into_shared()
     };
 
+    let maintenance = opt.maintenance_message.map(MaintenanceLayer::new);
+    if maintenance.is_some() {
+        info!("maintenance mode is enabled, writes are rejected");
+    }
+
+    let clock_skew = (opt.max_clock_skew_secs > 0)
+        .then(|| ClockSkewLayer::new(Duration::from_secs(opt.max_clock_skew_secs)));
+
+    let deny_sig_v2 = (!opt.allow_sigv2).then_some(DenySigV2Layer);
+    let compression = compression_layer(opt.compress);
+
+    let concurrency_limit =
+        (opt.max_concurrent_requests > 0 || opt.max_concurrent_requests_per_key > 0).then(|| {
+            info!(
+                max_requests = opt.max_concurrent_requests,
+                max_requests_per_key = opt.max_concurrent_requests_per_key,
+                "concurrency limit is enabled"
+            );
+            ConcurrencyLimitLayer::new(
+                opt.max_concurrent_requests,
+                opt.max_concurrent_requests_per_key,
+            )
+        });
+
     // Run server
     let listener = TcpListener::bind((opt.host.as_str(), opt.port)).await?;
     let local_addr = listener.local_addr()?;
 
+    let metrics = Metrics::new();
+    tokio::spawn(refresh_disk_usage(metrics.clone(), root));
+    if opt.admin_token.is_none() {
+        warn!("admin routes answer 401 Unauthorized until --admin-token is set");
+    }
+    let admin = AdminLayer::new(metrics.clone())
+        .with_caches(caches)
+        .with_readiness(ds.clone())
+        .with_token(opt.admin_token);
+    let s3_admin = bind_admin(opt.admin_addr, admin).await?;
     let http_server = ConnBuilder::new(TokioExecutor::new());
     let graceful = hyper_util::server::graceful::GracefulShutdown::new();
 
@@ -156,7 +778,7 @@ async fn run(opt: Opt) -> Result {
     info!("server is running at http://{local_addr}");
 
     loop {
-        let (stream, _) = tokio::select! {
+        let (stream, remote_addr) = tokio::select! {
             res =  listener.accept() => {
                 match res {
                     Ok(conn) => conn,
@@ -172,16 +794,20 @@ async fn run(opt: Opt) -> Result {
         };
 
         let io = TokioIo::new(stream);
-
-        // let svc = ServiceBuilder::new().layer_fn(Logger::new).service(hyper_service.
-        // clone());
+        let connection = metrics.connection_opened();
 
         let conn = http_server.serve_connection(
             io,
             TowerToHyperService::new(
                 tower::ServiceBuilder::new()
+                    .layer(AccessLogLayer::new(remote_addr))
+                    .layer(compression.clone())
+                    .option_layer(s3_admin.clone())
+                    .option_layer(concurrency_limit.clone())
+                    .option_layer(maintenance.clone())
+                    .option_layer(clock_skew)
+                    .option_layer(deny_sig_v2)
                     // .layer(CorsLayer::very_permissive())
-                    // .layer(ConcurrencyLimitLayer::new(2))
                     // .layer(RequestBodyLimitLayer::new(4096))
                     .service(service.clone()),
             ),
@@ -189,17 +815,12 @@ async fn run(opt: Opt) -> Result {
         let conn = graceful.watch(conn.into_owned());
         tokio::spawn(async move {
             let _ = conn.await;
+            drop(connection);
         });
     }
 
-    tokio::select! {
-        () = graceful.shutdown() => {
-             tracing::debug!("Gracefully shut down!");
-        },
-        () = tokio::time::sleep(std::time::Duration::from_secs(10)) => {
-             tracing::debug!("Waited 10 seconds for graceful shutdown, aborting...");
-        }
-    }
+    drain_connections(graceful, &metrics, opt.shutdown_timeout_secs).await;
+    ds.close().await;
 
     info!("server is stopped");
     Ok(())
@@ -0,0 +1,161 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::{Method, Request, Response, StatusCode, header};
+use tower::{Layer, Service};
+
+/// Seconds clients are asked to wait before retrying a rejected write.
+const RETRY_AFTER_SECS: &str = "120";
+
+/// Rejects mutating requests with `503 ServiceUnavailable` while the server is in maintenance.
+///
+/// The operator supplied message is returned in the S3 error body.
+/// Reads (`GET` and `HEAD`) keep being served.
+#[derive(Debug, Clone)]
+pub struct MaintenanceLayer {
+    message: Arc<str>,
+}
+
+impl MaintenanceLayer {
+    #[must_use]
+    pub fn new(message: impl Into<Arc<str>>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl<S> Layer<S> for MaintenanceLayer {
+    type Service = MaintenanceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MaintenanceService {
+            inner,
+            message: Arc::clone(&self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceService<S> {
+    inner: S,
+    message: Arc<str>,
+}
+
+impl<S> MaintenanceService<S> {
+    fn reject<RB>(&self) -> Response<RB>
+    where
+        RB: From<String>,
+    {
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <Error><Code>ServiceUnavailable</Code><Message>{}</Message></Error>",
+            escape_xml(&self.message)
+        );
+
+        let mut res = Response::new(RB::from(body));
+        *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        res.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/xml"),
+        );
+        res.headers_mut().insert(
+            header::RETRY_AFTER,
+            header::HeaderValue::from_static(RETRY_AFTER_SECS),
+        );
+        res
+    }
+}
+
+fn is_read(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl<S, B, RB> Service<Request<B>> for MaintenanceService<S>
+where
+    S: Service<Request<B>, Response = Response<RB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    RB: From<String> + Send + 'static,
+{
+    type Response = Response<RB>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if !is_read(req.method()) {
+            let res = self.reject();
+            return Box::pin(async move { Ok(res) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+
+    async fn call(method: Method, message: &str) -> Response<String> {
+        let inner = service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new("s3".to_string()))
+        });
+        let svc = MaintenanceLayer::new(message).layer(inner);
+        let req = Request::builder()
+            .method(method)
+            .uri("/bucket/key")
+            .body(())
+            .unwrap();
+        svc.oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_rejected_with_message() {
+        let res = call(Method::PUT, "disk replacement until 14:00 <UTC>").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers()[header::RETRY_AFTER], RETRY_AFTER_SECS);
+        assert!(res.body().contains("<Code>ServiceUnavailable</Code>"));
+        assert!(
+            res.body()
+                .contains("<Message>disk replacement until 14:00 &lt;UTC&gt;</Message>")
+        );
+
+        let res = call(Method::DELETE, "maintenance").await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_reads_pass_through() {
+        let res = call(Method::GET, "maintenance").await;
+        assert_eq!(res.body(), "s3");
+
+        let res = call(Method::HEAD, "maintenance").await;
+        assert_eq!(res.body(), "s3");
+    }
+}
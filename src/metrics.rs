@@ -0,0 +1,194 @@
+use std::{
+    fmt::Write,
+    io,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+/// Space of the filesystem holding the storage root, in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    /// Space left for writes, blocks reserved for the super user excluded.
+    pub free_bytes: u64,
+    pub used_bytes: u64,
+}
+
+/// Process wide gauges exposed on `/_admin/metrics` and the health endpoint.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    active_connections: AtomicUsize,
+    disk_total_bytes: AtomicU64,
+    disk_free_bytes: AtomicU64,
+    disk_used_bytes: AtomicU64,
+}
+
+impl Metrics {
+    #[must_use]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Marks a connection as accepted.
+    /// The connection stays counted until the returned guard is dropped.
+    #[must_use]
+    pub fn connection_opened(self: &Arc<Self>) -> ConnectionGuard {
+        self.active_connections.fetch_add(1, Ordering::SeqCst);
+        ConnectionGuard {
+            metrics: Arc::clone(self),
+        }
+    }
+
+    /// Number of HTTP connections currently being served.
+    #[must_use]
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::SeqCst)
+    }
+
+    /// Samples the disk usage of the filesystem holding `root`.
+    /// The gauges read zero until the first refresh.
+    pub fn refresh_disk_usage(&self, root: &Path) -> io::Result<DiskUsage> {
+        let total_bytes = fs2::total_space(root)?;
+        let usage = DiskUsage {
+            total_bytes,
+            free_bytes: fs2::available_space(root)?,
+            used_bytes: total_bytes.saturating_sub(fs2::free_space(root)?),
+        };
+        self.disk_total_bytes
+            .store(usage.total_bytes, Ordering::SeqCst);
+        self.disk_free_bytes
+            .store(usage.free_bytes, Ordering::SeqCst);
+        self.disk_used_bytes
+            .store(usage.used_bytes, Ordering::SeqCst);
+        Ok(usage)
+    }
+
+    /// Disk usage as of the last refresh.
+    #[must_use]
+    pub fn disk_usage(&self) -> DiskUsage {
+        DiskUsage {
+            total_bytes: self.disk_total_bytes.load(Ordering::SeqCst),
+            free_bytes: self.disk_free_bytes.load(Ordering::SeqCst),
+            used_bytes: self.disk_used_bytes.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Renders all gauges in the Prometheus text exposition format.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP beggar_active_connections Number of currently open HTTP connections."
+        );
+        let _ = writeln!(out, "# TYPE beggar_active_connections gauge");
+        let _ = writeln!(
+            out,
+            "beggar_active_connections {}",
+            self.active_connections()
+        );
+
+        let disk = self.disk_usage();
+        for (name, help, value) in [
+            (
+                "beggar_disk_total_bytes",
+                "Size of the filesystem holding the storage root.",
+                disk.total_bytes,
+            ),
+            (
+                "beggar_disk_free_bytes",
+                "Bytes left for writes on the filesystem holding the storage root.",
+                disk.free_bytes,
+            ),
+            (
+                "beggar_disk_used_bytes",
+                "Bytes in use on the filesystem holding the storage root.",
+                disk.used_bytes,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            let _ = writeln!(out, "{name} {value}");
+        }
+        out
+    }
+}
+
+/// Decrements the active connection gauge when the connection completes.
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    metrics: Arc<Metrics>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.metrics
+            .active_connections
+            .fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_lifecycle() {
+        let metrics = Metrics::new();
+        assert_eq!(metrics.active_connections(), 0);
+
+        let first = metrics.connection_opened();
+        let second = metrics.connection_opened();
+        assert_eq!(metrics.active_connections(), 2);
+
+        drop(first);
+        assert_eq!(metrics.active_connections(), 1);
+
+        drop(second);
+        assert_eq!(metrics.active_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_guard_moved_into_task() {
+        let metrics = Metrics::new();
+        let guard = metrics.connection_opened();
+
+        let handle = tokio::spawn(async move {
+            let _guard = guard;
+        });
+        handle.await.unwrap();
+
+        assert_eq!(metrics.active_connections(), 0);
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        let _guard = metrics.connection_opened();
+
+        let output = metrics.render();
+        assert!(output.contains("# TYPE beggar_active_connections gauge"));
+        assert!(output.contains("beggar_active_connections 1\n"));
+        assert!(output.contains("# TYPE beggar_disk_free_bytes gauge"));
+    }
+
+    #[test]
+    fn test_refresh_disk_usage() {
+        let metrics = Metrics::new();
+        assert_eq!(metrics.disk_usage(), DiskUsage::default());
+
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let usage = metrics.refresh_disk_usage(tmp_dir.path()).unwrap();
+        assert!(usage.total_bytes > 0);
+        assert!(usage.free_bytes > 0);
+        assert!(usage.used_bytes <= usage.total_bytes);
+        assert!(usage.free_bytes <= usage.total_bytes);
+        assert_eq!(metrics.disk_usage(), usage);
+
+        let output = metrics.render();
+        assert!(output.contains(&format!("beggar_disk_total_bytes {}\n", usage.total_bytes)));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use bytes::Bytes;
+use futures::{SinkExt, Stream, StreamExt, channel::mpsc, stream};
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+
+use crate::MultipartUploadPart;
+
+/// File name marker of the file parts are assembled in, followed by the part size.
+const ASSEMBLY_MARKER: &str = ".assembly-";
+
+/// Parts read ahead of the one being written when parts are copied on completion.
+const READ_AHEAD_PARTS: usize = 4;
+/// Chunks of each part buffered ahead of the writer.
+const READ_AHEAD_CHUNKS: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where a part is written in the assembly file of its upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Placement {
+    pub(crate) part_size: u64,
+    pub(crate) offset: u64,
+}
+
+#[derive(Debug)]
+struct Cursor {
+    part_size: u64,
+    next_part_number: i32,
+    /// A part shorter than the part size was written, it must be the last one.
+    closed: bool,
+}
+
+/// Places the parts of multipart uploads straight into a sparse assembly file, at the
+/// offset their part number gives, so completing the upload is a rename instead of a copy.
+///
+/// The size of part 1 is taken as the part size of the upload.
+/// Parts are only placed while they arrive in order with a known length,
+/// anything else is staged in its own file and copied on completion.
+#[derive(Debug, Default)]
+pub(crate) struct DirectAssembly {
+    cursors: Mutex<HashMap<String, Cursor>>,
+}
+
+impl DirectAssembly {
+    /// Reserves the range of a part in the assembly file, `None` when it must be staged.
+    pub(crate) fn claim(
+        &self,
+        upload_id: &str,
+        part_number: i32,
+        content_length: Option<u64>,
+    ) -> Option<Placement> {
+        let len = content_length.filter(|len| *len > 0)?;
+        let mut cursors = self.cursors.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(cursor) = cursors.get_mut(upload_id) else {
+            if part_number != 1 {
+                return None;
+            }
+            cursors.insert(
+                upload_id.to_owned(),
+                Cursor {
+                    part_size: len,
+                    next_part_number: 2,
+                    closed: false,
+                },
+            );
+            return Some(Placement {
+                part_size: len,
+                offset: 0,
+            });
+        };
+        if cursor.closed || cursor.next_part_number != part_number || len > cursor.part_size {
+            return None;
+        }
+        cursor.next_part_number += 1;
+        cursor.closed = len < cursor.part_size;
+        Some(Placement {
+            part_size: cursor.part_size,
+            offset: part_offset(cursor.part_size, part_number),
+        })
+    }
+
+    pub(crate) fn forget(&self, upload_id: &str) {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(upload_id);
+    }
+}
+
+pub(crate) fn assembly_file_name(upload_id: &str, part_size: u64) -> String {
+    format!(".upload_id-{upload_id}{ASSEMBLY_MARKER}{part_size}")
+}
+
+/// Part size of the upload when `data_location` is an assembly file.
+pub(crate) fn assembly_part_size(data_location: &str) -> Option<u64> {
+    let file_name = Path::new(data_location).file_name()?.to_str()?;
+    let (_, part_size) = file_name.rsplit_once(ASSEMBLY_MARKER)?;
+    part_size.parse().ok()
+}
+
+pub(crate) fn part_offset(part_size: u64, part_number: i32) -> u64 {
+    part_size * u64::try_from(part_number - 1).unwrap_or_default()
+}
+
+/// Length of a part, only its range counts when it lives in an assembly file.
+pub(crate) async fn part_len(data_location: &str, part_number: i32) -> io::Result<u64> {
+    let len = fs::metadata(data_location).await?.len();
+    Ok(match assembly_part_size(data_location) {
+        Some(part_size) => len
+            .saturating_sub(part_offset(part_size, part_number))
+            .min(part_size),
+        None => len,
+    })
+}
+
+/// Opens the bytes of a part, only its range when it lives in an assembly file.
+async fn open_part(data_location: &str, part_number: i32) -> io::Result<impl AsyncRead + use<>> {
+    let len = part_len(data_location, part_number).await?;
+    let mut file = fs::File::open(data_location).await?;
+    if let Some(part_size) = assembly_part_size(data_location) {
+        file.seek(io::SeekFrom::Start(part_offset(part_size, part_number)))
+            .await?;
+    }
+    Ok(file.take(len))
+}
+
+/// Reads a part in a task of its own, buffering up to [`READ_AHEAD_CHUNKS`] chunks.
+fn spawn_part_reader(data_location: String, part_number: i32) -> mpsc::Receiver<io::Result<Bytes>> {
+    let (mut tx, rx) = mpsc::channel(READ_AHEAD_CHUNKS);
+    tokio::spawn(async move {
+        let mut chunks = match open_part(&data_location, part_number).await {
+            Ok(reader) => ReaderStream::with_capacity(reader, CHUNK_SIZE),
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return;
+            }
+        };
+        while let Some(chunk) = chunks.next().await {
+            // the receiver is gone when the completion failed
+            if tx.send(chunk).await.is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// The bytes of the parts, in part order.
+///
+/// Up to [`READ_AHEAD_PARTS`] parts are read concurrently so reading the next parts
+/// overlaps with writing the current one, while memory stays bounded by the chunks
+/// buffered for each of them.
+pub(crate) fn read_parts(
+    parts: &[MultipartUploadPart],
+) -> impl Stream<Item = io::Result<Bytes>> + use<> {
+    let parts = parts
+        .iter()
+        .map(|part| (part.data_location.clone(), part.part_number))
+        .collect::<Vec<_>>();
+    stream::iter(parts)
+        .map(|(data_location, part_number)| async move {
+            spawn_part_reader(data_location, part_number)
+        })
+        .buffered(READ_AHEAD_PARTS)
+        .flatten()
+}
+
+/// The assembly file and the length of the object when every part of the upload,
+/// numbered from 1 without gaps, was written directly.
+pub(crate) async fn complete_assembly(
+    parts: &[MultipartUploadPart],
+) -> io::Result<Option<(String, u64)>> {
+    let Some(last) = parts.last() else {
+        return Ok(None);
+    };
+    let Some(part_size) = assembly_part_size(&last.data_location) else {
+        return Ok(None);
+    };
+    let complete = parts.iter().zip(1..).all(|(part, number)| {
+        part.part_number == number && part.data_location == last.data_location
+    });
+    if !complete {
+        return Ok(None);
+    }
+    let len = part_offset(part_size, last.part_number)
+        + part_len(&last.data_location, last.part_number).await?;
+    Ok(Some((last.data_location.clone(), len)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_in_order() {
+        let assembly = DirectAssembly::default();
+        assert_eq!(
+            assembly.claim("u", 1, Some(5)),
+            Some(Placement {
+                part_size: 5,
+                offset: 0
+            })
+        );
+        assert_eq!(
+            assembly.claim("u", 2, Some(5)),
+            Some(Placement {
+                part_size: 5,
+                offset: 5
+            })
+        );
+        // the short last part closes the upload
+        assert_eq!(
+            assembly.claim("u", 3, Some(2)),
+            Some(Placement {
+                part_size: 5,
+                offset: 10
+            })
+        );
+        assert_eq!(assembly.claim("u", 4, Some(2)), None);
+    }
+
+    #[test]
+    fn test_claim_falls_back() {
+        let assembly = DirectAssembly::default();
+        // unknown length or a first part that is not part 1
+        assert_eq!(assembly.claim("u", 1, None), None);
+        assert_eq!(assembly.claim("u", 2, Some(5)), None);
+
+        assert!(assembly.claim("u", 1, Some(5)).is_some());
+        // out of order, re-uploaded and oversized parts
+        assert_eq!(assembly.claim("u", 3, Some(5)), None);
+        assert_eq!(assembly.claim("u", 1, Some(5)), None);
+        assert_eq!(assembly.claim("u", 2, Some(6)), None);
+
+        assembly.forget("u");
+        assert!(assembly.claim("u", 1, Some(6)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_read_parts_in_order() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let assembly = tmp_dir
+            .path()
+            .join(assembly_file_name("u", 3))
+            .display()
+            .to_string();
+        // parts 1 and 2 were assembled directly, the others staged
+        fs::write(&assembly, b"aaabbb").await.unwrap();
+        let mut parts = (1..=2)
+            .map(|part_number| part(part_number, &assembly))
+            .collect::<Vec<_>>();
+        let mut expected = b"aaabbb".to_vec();
+        for part_number in 3..=64 {
+            let path = tmp_dir.path().join(format!("part-{part_number}"));
+            let content = format!("<{part_number}>").repeat(usize::try_from(part_number).unwrap());
+            fs::write(&path, &content).await.unwrap();
+            expected.extend_from_slice(content.as_bytes());
+            parts.push(part(part_number, &path.display().to_string()));
+        }
+
+        let chunks = read_parts(&parts).collect::<Vec<_>>().await;
+        let content = chunks
+            .into_iter()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap()
+            .concat();
+        assert_eq!(content, expected);
+
+        // a missing part fails the read
+        fs::remove_file(tmp_dir.path().join("part-10"))
+            .await
+            .unwrap();
+        let chunks = read_parts(&parts).collect::<Vec<_>>().await;
+        assert!(chunks.iter().any(Result::is_err));
+    }
+
+    /// Reading many small staged parts one after another, as completion did before
+    /// [`read_parts`], against reading them ahead.
+    /// Run with `cargo test --release bench_read_parts -- --ignored --nocapture`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "benchmark"]
+    async fn bench_read_parts() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let mut parts = Vec::new();
+        for part_number in 1..=2000 {
+            let path = tmp_dir.path().join(format!("part-{part_number}"));
+            fs::write(&path, vec![b'x'; 16 * 1024]).await.unwrap();
+            parts.push(part(part_number, &path.display().to_string()));
+        }
+
+        let start = std::time::Instant::now();
+        let mut sequential = 0;
+        for part in &parts {
+            let reader = open_part(&part.data_location, part.part_number)
+                .await
+                .unwrap();
+            let mut chunks = ReaderStream::with_capacity(reader, CHUNK_SIZE);
+            while let Some(chunk) = chunks.next().await {
+                sequential += chunk.unwrap().len();
+            }
+        }
+        let sequential_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut read_ahead = 0;
+        let mut chunks = std::pin::pin!(read_parts(&parts));
+        while let Some(chunk) = chunks.next().await {
+            read_ahead += chunk.unwrap().len();
+        }
+        let read_ahead_time = start.elapsed();
+
+        assert_eq!(sequential, read_ahead);
+        println!(
+            "{} parts, {sequential} bytes: sequential {sequential_time:?}, read ahead {read_ahead_time:?}",
+            parts.len()
+        );
+    }
+
+    fn part(part_number: i32, data_location: &str) -> MultipartUploadPart {
+        MultipartUploadPart {
+            upload_id: "u".to_owned(),
+            part_number,
+            md5: String::new(),
+            data_location: data_location.to_owned(),
+            last_modified: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn test_assembly_part_size() {
+        let name = assembly_file_name("abc", 5_242_880);
+        assert_eq!(
+            assembly_part_size(&format!("/data/{name}")),
+            Some(5_242_880)
+        );
+        assert_eq!(assembly_part_size("/data/.upload_id-abc.part-1"), None);
+    }
+}
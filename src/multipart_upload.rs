@@ -8,6 +8,9 @@ pub struct MultipartUpload {
     pub metadata: String,
     pub last_modified: chrono::NaiveDateTime,
     pub access_key: String,
+    pub storage_class: Option<String>,
+    /// Content headers given at initiation, as the JSON of their internal info entries.
+    pub content_headers: Option<String>,
 }
 
 impl MultipartUpload {
@@ -24,6 +27,8 @@ pub struct MultipartUploadBuilder {
     key: Option<String>,
     metadata: Option<String>,
     access_key: Option<String>,
+    storage_class: Option<String>,
+    content_headers: Option<String>,
 }
 
 /// Builder for [`MultipartUpload`].
@@ -63,6 +68,20 @@ impl MultipartUploadBuilder {
         self
     }
 
+    /// Sets the storage class requested at initiation.
+    #[must_use]
+    pub fn storage_class(mut self, storage_class: Option<String>) -> Self {
+        self.storage_class = storage_class;
+        self
+    }
+
+    /// Sets the content headers given at initiation.
+    #[must_use]
+    pub fn content_headers(mut self, content_headers: Option<String>) -> Self {
+        self.content_headers = content_headers;
+        self
+    }
+
     /// Builds a [`MultipartUpload`] from this builder.
     ///
     /// # Panics
@@ -78,6 +97,8 @@ impl MultipartUploadBuilder {
             metadata: self.metadata.expect("metadata is required"),
             last_modified: chrono::Utc::now().naive_utc(),
             access_key: self.access_key.expect("access_key is required"),
+            storage_class: self.storage_class,
+            content_headers: self.content_headers,
         }
     }
 }
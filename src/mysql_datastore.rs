@@ -0,0 +1,1273 @@
+use core::fmt;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::datastore::{
+    MAX_QUERY_SIZE, acquire_for_read, connect_with_retry, like_prefix, sanitize_for_logging,
+};
+use crate::error::Result;
+use crate::{
+    DataStore, DatabaseHealth, MultipartUpload, MultipartUploadPart, S3Bucket, S3ItemDetail,
+    Settings,
+};
+
+/// `DataStore` backed by `MySQL` or `MariaDB`.
+///
+/// Queries are checked at runtime, the offline query data in `.sqlx` only covers Postgres.
+#[derive(Clone)]
+pub struct MySqlDatastore {
+    pool: MySqlPool,
+    read_acquire_timeout: Duration,
+}
+
+impl MySqlDatastore {
+    /// Connects to the database asynchronously
+    /// This is the only way to create a new `MySqlDatastore` instance
+    #[instrument(level = "info", name = "db_connect", skip(settings))]
+    pub async fn connect(settings: &Settings) -> Result<Self> {
+        let host = &settings.datasource.host;
+        let port = settings.datasource.port;
+        let db = &settings.datasource.db;
+        let user = &settings.datasource.user;
+        let password = &settings.datasource.password;
+
+        let max_connections = settings.datasource.max_connections;
+        let min_connections = settings.datasource.min_connections;
+        let test_before_acquire = settings.datasource.test_before_acquire;
+        let acquire_slow_threshold =
+            Duration::from_millis(settings.datasource.acquire_slow_threshold);
+
+        // Log sanitized connection info (no password)
+        let sanitized_connection = format!("mysql://{user}:*****@{host}:{port}/{db}");
+        info!(target: "database", connection = %sanitized_connection, "Initializing database connection");
+
+        let connect_options = MySqlConnectOptions::new()
+            .host(host)
+            .port(port)
+            .username(user)
+            .password(password)
+            .database(db)
+            .charset("utf8mb4");
+
+        let pool_options = MySqlPoolOptions::new()
+            .max_connections(max_connections)
+            .min_connections(min_connections)
+            .acquire_timeout(settings.datasource.acquire_timeout())
+            .acquire_slow_threshold(acquire_slow_threshold)
+            .test_before_acquire(test_before_acquire);
+
+        let pool = connect_with_retry(
+            settings.datasource.connect_max_attempts(),
+            settings.datasource.connect_retry_base_delay(),
+            || async {
+                debug!(target: "database", "Creating connection pool");
+                let pool = match pool_options
+                    .clone()
+                    .connect_with(connect_options.clone())
+                    .await
+                {
+                    Ok(pool) => pool,
+                    Err(e) => {
+                        warn!(
+                            target: "database",
+                            error = %e,
+                            host = %host,
+                            port = %port,
+                            db = %db,
+                            "Failed to establish database connection"
+                        );
+                        return Err(e.into());
+                    }
+                };
+                // Verify we can actually execute a query
+                if let Err(e) = sqlx::query("SELECT 1").execute(&pool).await {
+                    warn!(target: "database", error = %e, "Database connection validation failed");
+                    return Err(e.into());
+                }
+                Ok(pool)
+            },
+        )
+        .await?;
+        info!(target: "database", "Database connection established and validated");
+
+        Ok(Self {
+            pool,
+            read_acquire_timeout: settings.datasource.read_acquire_timeout(),
+        })
+    }
+
+    #[instrument(level = "info", name = "db_migration", skip(self))]
+    pub async fn migrate(&self) -> Result<()> {
+        info!(target: "database", "Running database migrations");
+        match sqlx::migrate!("./migrations_mysql").run(&self.pool).await {
+            Ok(()) => {
+                info!(target: "database", "Database migrations completed successfully");
+                Ok(())
+            }
+            Err(e) => {
+                error!(error = %e, "Database migration failed");
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DataStore for MySqlDatastore {
+    #[instrument(level = "debug", name = "save_item", skip(self, item), fields(bucket = %item.bucket, key = %item.key))]
+    async fn save_s3_item_detail(&self, item: &S3ItemDetail) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO s3_item_detail (bucket, `key`, version_id, metadata, internal_info, last_modified, md5, data_location, delete_marker)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+            metadata = VALUES(metadata),
+            internal_info = VALUES(internal_info),
+            last_modified = VALUES(last_modified),
+            md5 = VALUES(md5),
+            data_location = VALUES(data_location),
+            delete_marker = VALUES(delete_marker)
+            ",
+        )
+        .bind(&item.bucket)
+        .bind(&item.key)
+        .bind(&item.version_id)
+        .bind(&item.metadata)
+        .bind(&item.internal_info)
+        .bind(item.last_modified)
+        .bind(&item.e_tag)
+        .bind(&item.data_location)
+        .bind(item.delete_marker)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(target: "storage", bucket = %item.bucket, key = %item.key, "S3 item detail saved successfully");
+                Ok(())
+            }
+            Err(e) => {
+                error!(target: "storage", error = %e, bucket = %item.bucket, key = %item.key, "Failed to save S3 item detail");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item", skip(self), fields(bucket = %bucket, key = %key))]
+    async fn get_s3_item_detail(&self, bucket: &str, key: &str) -> Result<Option<S3ItemDetail>> {
+        let mut conn = acquire_for_read(&self.pool, self.read_acquire_timeout).await?;
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = ? AND `key` = ?
+            ORDER BY last_modified DESC
+            LIMIT 1
+            ",
+        )
+        .bind(bucket)
+        .bind(key)
+        .fetch_optional(&mut *conn)
+        .await
+        {
+            Ok(result) => {
+                debug!(found = result.is_some(), "S3 item detail lookup completed");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    "Failed to retrieve S3 item detail"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item_version", skip(self), fields(bucket = %bucket, key = %key, version_id = %version_id))]
+    async fn get_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<Option<S3ItemDetail>> {
+        let mut conn = acquire_for_read(&self.pool, self.read_acquire_timeout).await?;
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = ? AND `key` = ? AND version_id = ?
+            ",
+        )
+        .bind(bucket)
+        .bind(key)
+        .bind(version_id)
+        .fetch_optional(&mut *conn)
+        .await
+        {
+            Ok(result) => {
+                debug!(found = result.is_some(), "S3 item detail version lookup completed");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    version_id = %sanitize_for_logging(version_id),
+                    "Failed to retrieve S3 item detail version"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_items_with_filter", skip(self), fields(bucket = %bucket, filter = %filter))]
+    async fn get_s3_item_detail_with_filter(
+        &self,
+        bucket: &str,
+        filter: &str,
+    ) -> Result<Vec<S3ItemDetail>> {
+        // Only the latest version of each key is listed, MySQL has no DISTINCT ON
+        let filter_with_wildcard = like_prefix(filter);
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, e_tag, data_location, delete_marker
+            FROM (
+                SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker,
+                ROW_NUMBER() OVER (PARTITION BY `key` ORDER BY last_modified DESC) AS version_rank
+                FROM s3_item_detail
+                WHERE bucket = ? AND `key` LIKE ?
+            ) latest
+            WHERE version_rank = 1
+            ORDER BY `key` ASC
+            LIMIT ?
+            ",
+        )
+        .bind(bucket)
+        .bind(filter_with_wildcard)
+        .bind(MAX_QUERY_SIZE)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved S3 items with filter");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    filter = %sanitize_for_logging(filter),
+                    "Failed to retrieve S3 items with filter"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item_versions_with_filter", skip(self), fields(bucket = %bucket, filter = %filter))]
+    async fn get_s3_item_versions_with_filter(
+        &self,
+        bucket: &str,
+        filter: &str,
+    ) -> Result<Vec<S3ItemDetail>> {
+        let filter_with_wildcard = like_prefix(filter);
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE bucket = ? AND `key` LIKE ?
+            ORDER BY `key` ASC, last_modified DESC, version_id ASC
+            LIMIT ?
+            ",
+        )
+        .bind(bucket)
+        .bind(filter_with_wildcard)
+        .bind(MAX_QUERY_SIZE)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved S3 item versions with filter");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    filter = %sanitize_for_logging(filter),
+                    "Failed to retrieve S3 item versions with filter"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_latest_items_page", skip(self), fields(bucket = %bucket, prefix = %prefix))]
+    async fn list_latest_items_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        start_after: &str,
+        descending: bool,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        let prefix_with_wildcard = like_prefix(prefix);
+        let (after, order) = if descending {
+            ("<", "DESC")
+        } else {
+            (">", "ASC")
+        };
+        let query = format!(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, e_tag, data_location, delete_marker
+            FROM (
+                SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker,
+                ROW_NUMBER() OVER (PARTITION BY `key` ORDER BY last_modified DESC) AS version_rank
+                FROM s3_item_detail
+                WHERE bucket = ? AND `key` LIKE ? AND (? = '' OR `key` {after} ?)
+            ) latest
+            WHERE version_rank = 1 AND NOT delete_marker
+            ORDER BY `key` {order}
+            LIMIT ?
+            "
+        );
+        match sqlx::query_as::<_, S3ItemDetail>(&query)
+            .bind(bucket)
+            .bind(prefix_with_wildcard)
+            .bind(start_after)
+            .bind(start_after)
+            .bind(limit.min(MAX_QUERY_SIZE))
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved page of S3 items");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    prefix = %sanitize_for_logging(prefix),
+                    "Failed to retrieve page of S3 items"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_versions_page", skip(self), fields(bucket = %bucket, prefix = %prefix))]
+    async fn list_versions_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: &str,
+        version_id_marker: &str,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        let prefix_with_wildcard = like_prefix(prefix);
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker
+            FROM s3_item_detail item
+            WHERE bucket = ? AND `key` LIKE ?
+            AND (? = '' OR `key` > ? OR (`key` = ? AND EXISTS (
+                SELECT 1 FROM s3_item_detail marker
+                WHERE marker.bucket = item.bucket AND marker.`key` = item.`key` AND marker.version_id = ?
+                AND (item.last_modified < marker.last_modified
+                    OR (item.last_modified = marker.last_modified AND item.version_id > marker.version_id))
+            )))
+            ORDER BY `key` ASC, last_modified DESC, version_id ASC
+            LIMIT ?
+            ",
+        )
+        .bind(bucket)
+        .bind(prefix_with_wildcard)
+        .bind(key_marker)
+        .bind(key_marker)
+        .bind(key_marker)
+        .bind(version_id_marker)
+        .bind(limit.min(MAX_QUERY_SIZE))
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved page of S3 item versions");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    prefix = %sanitize_for_logging(prefix),
+                    "Failed to retrieve page of S3 item versions"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_item_versions_page", skip(self))]
+    async fn list_item_versions_page(
+        &self,
+        after_bucket: &str,
+        after_key: &str,
+        after_version_id: &str,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE (bucket, `key`, version_id) > (?, ?, ?)
+            ORDER BY bucket, `key`, version_id
+            LIMIT ?
+            ",
+        )
+        .bind(after_bucket)
+        .bind(after_key)
+        .bind(after_version_id)
+        .bind(limit.min(MAX_QUERY_SIZE))
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved page of S3 item versions");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(after_bucket),
+                    key = %sanitize_for_logging(after_key),
+                    "Failed to retrieve page of S3 item versions"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_item_by_data_location", skip(self), fields(data_location = %data_location))]
+    async fn get_item_by_data_location(&self, data_location: &str) -> Result<Option<S3ItemDetail>> {
+        match sqlx::query_as::<_, S3ItemDetail>(
+            r"
+            SELECT bucket, `key`, version_id, metadata, internal_info, last_modified, md5 AS e_tag, data_location, delete_marker
+            FROM s3_item_detail
+            WHERE data_location = ?
+            ",
+        )
+        .bind(data_location)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(found = result.is_some(), "S3 item detail lookup by data location completed");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    data_location = %sanitize_for_logging(data_location),
+                    "Failed to retrieve S3 item detail by data location"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "info", name = "delete_item_version", skip(self), fields(bucket = %bucket, key = %key, version_id = %version_id))]
+    async fn delete_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<()> {
+        match sqlx::query(
+            r"
+            DELETE FROM s3_item_detail
+            WHERE bucket = ? AND `key` = ? AND version_id = ?
+            ",
+        )
+        .bind(bucket)
+        .bind(key)
+        .bind(version_id)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                info!(target: "storage", rows_affected = %result.rows_affected(), "S3 item detail version deleted");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    key = %sanitize_for_logging(key),
+                    version_id = %sanitize_for_logging(version_id),
+                    "Failed to delete S3 item detail version"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "info", name = "move_item", skip(self, item), fields(bucket = %item.bucket, key = %item.key, to_bucket = %bucket, to_key = %key))]
+    async fn move_s3_item_detail(
+        &self,
+        item: &S3ItemDetail,
+        bucket: &str,
+        key: &str,
+        data_location: &str,
+    ) -> Result<bool> {
+        match sqlx::query(
+            r"
+            UPDATE s3_item_detail
+            SET bucket = ?, `key` = ?, data_location = ?
+            WHERE bucket = ? AND `key` = ? AND version_id = ?
+            ",
+        )
+        .bind(bucket)
+        .bind(key)
+        .bind(data_location)
+        .bind(&item.bucket)
+        .bind(&item.key)
+        .bind(&item.version_id)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                info!(target: "storage", rows_affected = %result.rows_affected(), "S3 item detail moved");
+                Ok(result.rows_affected() > 0)
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(&item.bucket),
+                    key = %sanitize_for_logging(&item.key),
+                    to_bucket = %sanitize_for_logging(bucket),
+                    to_key = %sanitize_for_logging(key),
+                    "Failed to move S3 item detail"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "create_bucket", skip(self), fields(bucket = %bucket.name))]
+    async fn create_bucket(&self, bucket: &S3Bucket) -> Result<bool> {
+        match sqlx::query(
+            r"
+            INSERT IGNORE INTO bucket (name, created_at, owner)
+            VALUES (?, ?, ?)
+            ",
+        )
+        .bind(&bucket.name)
+        .bind(bucket.created_at)
+        .bind(&bucket.owner)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.rows_affected() > 0),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(&bucket.name),
+                    "Failed to create bucket"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "delete_bucket", skip(self), fields(bucket = %bucket))]
+    async fn delete_bucket(&self, bucket: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let result = async {
+            sqlx::query("DELETE FROM bucket_config WHERE bucket = ?")
+                .bind(bucket)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM bucket WHERE name = ?")
+                .bind(bucket)
+                .execute(&mut *tx)
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(_) => {
+                tx.commit().await?;
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to delete bucket"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "list_buckets", skip(self))]
+    async fn list_buckets(&self) -> Result<Vec<S3Bucket>> {
+        match sqlx::query_as::<_, S3Bucket>(
+            r"
+            SELECT name, created_at, owner
+            FROM bucket
+            ORDER BY name
+            LIMIT ?
+            ",
+        )
+        .bind(MAX_QUERY_SIZE)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(count = result.len(), "Retrieved all buckets");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to retrieve all buckets");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "count_buckets", skip(self))]
+    async fn count_buckets(&self) -> Result<i64> {
+        match sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM bucket")
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(count) => Ok(count),
+            Err(e) => {
+                error!(error = %e, "Failed to count buckets");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "bucket_exists", skip(self), fields(bucket = %bucket))]
+    async fn bucket_exists(&self, bucket: &str) -> Result<bool> {
+        match sqlx::query_scalar::<_, String>("SELECT name FROM bucket WHERE name = ?")
+            .bind(bucket)
+            .fetch_optional(&self.pool)
+            .await
+        {
+            Ok(result) => Ok(result.is_some()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to check bucket existence"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_bucket", skip(self), fields(bucket = %bucket))]
+    async fn get_bucket(&self, bucket: &str) -> Result<Option<S3Bucket>> {
+        match sqlx::query_as::<_, S3Bucket>(
+            r"
+            SELECT name, created_at, owner
+            FROM bucket
+            WHERE name = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_bucket_versioning", skip(self), fields(bucket = %bucket))]
+    async fn get_bucket_versioning(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            r"
+            SELECT versioning_status
+            FROM bucket_config
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.flatten()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket versioning status"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_versioning", skip(self), fields(bucket = %bucket, status = %status))]
+    async fn save_bucket_versioning(&self, bucket: &str, status: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO bucket_config (bucket, versioning_status)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+            versioning_status = VALUES(versioning_status)
+            ",
+        )
+        .bind(bucket)
+        .bind(status)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), status = %status, "Bucket versioning status saved");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket versioning status"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "get_bucket_ownership", skip(self), fields(bucket = %bucket))]
+    async fn get_bucket_ownership(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            r"
+            SELECT object_ownership
+            FROM bucket_config
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.flatten()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket object ownership"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_ownership", skip(self), fields(bucket = %bucket, ownership = %ownership))]
+    async fn save_bucket_ownership(&self, bucket: &str, ownership: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO bucket_config (bucket, object_ownership)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+            object_ownership = VALUES(object_ownership)
+            ",
+        )
+        .bind(bucket)
+        .bind(ownership)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), ownership = %ownership, "Bucket object ownership saved");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket object ownership"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_bucket_acl(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            r"
+            SELECT acl
+            FROM bucket_config
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.flatten()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket ACL"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_acl", skip(self), fields(bucket = %bucket, acl = %acl))]
+    async fn save_bucket_acl(&self, bucket: &str, acl: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO bucket_config (bucket, acl)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+            acl = VALUES(acl)
+            ",
+        )
+        .bind(bucket)
+        .bind(acl)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), acl = %acl, "Bucket ACL saved");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket ACL"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_bucket_default_content_type(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            r"
+            SELECT default_content_type
+            FROM bucket_config
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.flatten()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket default content type"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_default_content_type", skip(self), fields(bucket = %bucket, content_type = %content_type))]
+    async fn save_bucket_default_content_type(
+        &self,
+        bucket: &str,
+        content_type: &str,
+    ) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO bucket_config (bucket, default_content_type)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+            default_content_type = VALUES(default_content_type)
+            ",
+        )
+        .bind(bucket)
+        .bind(content_type)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), content_type = %content_type, "Bucket default content type saved");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket default content type"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_bucket_encryption(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            r"
+            SELECT encryption
+            FROM bucket_config
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.flatten()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket encryption"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_encryption", skip(self), fields(bucket = %bucket))]
+    async fn save_bucket_encryption(&self, bucket: &str, encryption: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO bucket_config (bucket, encryption)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+            encryption = VALUES(encryption)
+            ",
+        )
+        .bind(bucket)
+        .bind(encryption)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), "Bucket encryption saved");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket encryption"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_bucket_lifecycle(&self, bucket: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, Option<String>>(
+            r"
+            SELECT lifecycle
+            FROM bucket_config
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.flatten()),
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to retrieve bucket lifecycle"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "save_bucket_lifecycle", skip(self), fields(bucket = %bucket))]
+    async fn save_bucket_lifecycle(&self, bucket: &str, lifecycle: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO bucket_config (bucket, lifecycle)
+            VALUES (?, ?)
+            ON DUPLICATE KEY UPDATE
+            lifecycle = VALUES(lifecycle)
+            ",
+        )
+        .bind(bucket)
+        .bind(lifecycle)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), "Bucket lifecycle saved");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to save bucket lifecycle"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "delete_bucket_lifecycle", skip(self), fields(bucket = %bucket))]
+    async fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            UPDATE bucket_config
+            SET lifecycle = NULL
+            WHERE bucket = ?
+            ",
+        )
+        .bind(bucket)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(bucket = %sanitize_for_logging(bucket), "Bucket lifecycle deleted");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    bucket = %sanitize_for_logging(bucket),
+                    "Failed to delete bucket lifecycle"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn save_multipart_upload(&self, upload: &MultipartUpload) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO multipart_upload (upload_id, bucket, `key`, last_modified, metadata, access_key, storage_class, content_headers)
+            VALUES (?, ?, ?, CURRENT_TIMESTAMP(6), ?, ?, ?, ?)
+            ON DUPLICATE KEY UPDATE
+            metadata = VALUES(metadata),
+            access_key = VALUES(access_key),
+            storage_class = VALUES(storage_class),
+            content_headers = VALUES(content_headers)
+            ",
+        )
+        .bind(&upload.upload_id)
+        .bind(&upload.bucket)
+        .bind(&upload.key)
+        .bind(&upload.metadata)
+        .bind(&upload.access_key)
+        .bind(&upload.storage_class)
+        .bind(&upload.content_headers)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                info!(upload_id = %upload.upload_id, "Multipart upload saved successfully");
+                Ok(())
+            }
+            Err(e) => {
+                error!(error = %e, upload_id = %upload.upload_id, "Failed to save multipart upload");
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn save_multipart_upload_part(&self, part: &MultipartUploadPart) -> Result<()> {
+        match sqlx::query(
+            r"
+            INSERT INTO multipart_upload_part (upload_id, part_number, last_modified, md5, data_location)
+            VALUES (?, ?, CURRENT_TIMESTAMP(6), ?, ?)
+            ON DUPLICATE KEY UPDATE
+            md5 = VALUES(md5),
+            data_location = VALUES(data_location)
+            ",
+        )
+        .bind(&part.upload_id)
+        .bind(part.part_number)
+        .bind(&part.md5)
+        .bind(&part.data_location)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(_) => {
+                debug!(upload_id = %part.upload_id, part_number = part.part_number, "Multipart upload part saved successfully");
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    error = %e,
+                    upload_id = %part.upload_id,
+                    part_number = part.part_number,
+                    "Failed to save multipart upload part"
+                );
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_access_key_by_upload_id(&self, upload_id: &str) -> Result<Option<String>> {
+        match sqlx::query_scalar::<_, String>(
+            r"
+            SELECT access_key
+            FROM multipart_upload
+            WHERE upload_id = ?
+            ",
+        )
+        .bind(upload_id)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                error!(error = %e, upload_id = %upload_id, "Failed to retrieve access key by upload ID");
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_parts_by_upload_id(&self, upload_id: &str) -> Result<Vec<MultipartUploadPart>> {
+        match sqlx::query_as::<_, MultipartUploadPart>(
+            r"
+            SELECT upload_id, part_number, md5, last_modified, data_location
+            FROM multipart_upload_part
+            WHERE upload_id = ?
+            ORDER BY part_number ASC
+            LIMIT ?
+            ",
+        )
+        .bind(upload_id)
+        .bind(MAX_QUERY_SIZE)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                debug!(upload_id = %upload_id, count = result.len(), "Retrieved parts by upload ID");
+                Ok(result)
+            }
+            Err(e) => {
+                error!(error = %e, upload_id = %upload_id, "Failed to retrieve parts by upload ID");
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_multipart_upload_by_upload_id(
+        &self,
+        upload_id: &str,
+    ) -> Result<Option<MultipartUpload>> {
+        match sqlx::query_as::<_, MultipartUpload>(
+            r"
+            SELECT upload_id, bucket, `key`, last_modified, metadata, access_key, storage_class, content_headers
+            FROM multipart_upload
+            WHERE upload_id = ?
+            ",
+        )
+        .bind(upload_id)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                error!(error = %e, upload_id = %upload_id, "Failed to retrieve multipart upload by ID");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "info", name = "delete_multipart_upload", skip(self), fields(upload_id = %upload_id))]
+    async fn delete_multipart_upload_by_upload_id(&self, upload_id: &str) -> Result<()> {
+        match sqlx::query(
+            r"
+            DELETE FROM multipart_upload
+            WHERE upload_id = ?
+            ",
+        )
+        .bind(upload_id)
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => {
+                info!(
+                    target: "storage",
+                    upload_id = %upload_id,
+                    rows_affected = %result.rows_affected(),
+                    "Multipart upload deleted"
+                );
+                Ok(())
+            }
+            Err(e) => {
+                error!(error = %e, upload_id = %upload_id, "Failed to delete multipart upload by ID");
+                Err(e.into())
+            }
+        }
+    }
+
+    async fn get_stale_multipart_uploads(
+        &self,
+        older_than: NaiveDateTime,
+    ) -> Result<Vec<MultipartUpload>> {
+        match sqlx::query_as::<_, MultipartUpload>(
+            r"
+            SELECT upload_id, bucket, `key`, last_modified, metadata, access_key, storage_class, content_headers
+            FROM multipart_upload
+            WHERE last_modified < ?
+            ORDER BY last_modified ASC
+            LIMIT ?
+            ",
+        )
+        .bind(older_than)
+        .bind(MAX_QUERY_SIZE)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                error!(error = %e, older_than = %older_than, "Failed to retrieve stale multipart uploads");
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(level = "debug", name = "db_health_check", skip(self))]
+    async fn health(&self) -> DatabaseHealth {
+        let start = Instant::now();
+        let ok = match sqlx::query("SELECT 1").execute(&self.pool).await {
+            Ok(_) => true,
+            Err(e) => {
+                error!(target: "database", error = %e, "Database connection health check failed");
+                false
+            }
+        };
+        DatabaseHealth::new(ok, start.elapsed(), self.pool.size(), self.pool.num_idle())
+    }
+
+    async fn close(&self) {
+        info!(target: "database", "Closing connection pool");
+        self.pool.close().await;
+    }
+}
+
+impl fmt::Debug for MySqlDatastore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MySqlDatastore").finish()
+    }
+}
@@ -0,0 +1,237 @@
+use chrono::NaiveDateTime;
+use s3s::{
+    S3Result,
+    dto::{ObjectLockLegalHoldStatus, ObjectLockRetentionMode},
+    s3_error,
+};
+
+use crate::storage_backend::InternalInfo;
+
+const MODE: &str = "object_lock_mode";
+const RETAIN_UNTIL_DATE: &str = "object_lock_retain_until_date";
+const LEGAL_HOLD: &str = "object_lock_legal_hold";
+
+/// Retention of an object version, kept in its internal info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retention {
+    pub mode: ObjectLockRetentionMode,
+    pub retain_until: NaiveDateTime,
+}
+
+impl Retention {
+    fn is_governance(&self) -> bool {
+        self.mode.as_str() == ObjectLockRetentionMode::GOVERNANCE
+    }
+}
+
+/// The retention a write asks for, the mode and the retain until date go together
+/// and the date must lie ahead of `now`.
+pub fn check_request(
+    mode: Option<&str>,
+    retain_until: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+) -> S3Result<Option<Retention>> {
+    let (mode, retain_until) = match (mode, retain_until) {
+        (None, None) => return Ok(None),
+        (Some(mode), Some(retain_until)) => (mode, retain_until),
+        _ => {
+            return Err(s3_error!(
+                InvalidArgument,
+                "the object lock mode and retain until date must be given together"
+            ));
+        }
+    };
+    if ![
+        ObjectLockRetentionMode::GOVERNANCE,
+        ObjectLockRetentionMode::COMPLIANCE,
+    ]
+    .contains(&mode)
+    {
+        return Err(s3_error!(
+            InvalidArgument,
+            "unknown object lock mode {mode}"
+        ));
+    }
+    if retain_until <= now {
+        return Err(s3_error!(
+            InvalidArgument,
+            "the retain until date must be in the future"
+        ));
+    }
+    Ok(Some(Retention {
+        mode: ObjectLockRetentionMode::from(mode.to_owned()),
+        retain_until,
+    }))
+}
+
+pub fn from_internal_info(info: &InternalInfo) -> Option<Retention> {
+    let get = |name: &str| info.get(name).and_then(|v| v.as_str());
+    let mode = ObjectLockRetentionMode::from(get(MODE)?.to_owned());
+    let retain_until = chrono::DateTime::parse_from_rfc3339(get(RETAIN_UNTIL_DATE)?)
+        .ok()?
+        .naive_utc();
+    Some(Retention { mode, retain_until })
+}
+
+pub fn modify_internal_info(info: &mut InternalInfo, retention: &Retention) {
+    info.insert(
+        MODE.to_owned(),
+        serde_json::Value::String(retention.mode.as_str().to_owned()),
+    );
+    info.insert(
+        RETAIN_UNTIL_DATE.to_owned(),
+        serde_json::Value::String(retention.retain_until.and_utc().to_rfc3339()),
+    );
+}
+
+pub fn legal_hold_from_internal_info(info: &InternalInfo) -> bool {
+    info.get(LEGAL_HOLD).and_then(|v| v.as_str()) == Some(ObjectLockLegalHoldStatus::ON)
+}
+
+pub fn modify_legal_hold(info: &mut InternalInfo, status: &ObjectLockLegalHoldStatus) {
+    info.insert(
+        LEGAL_HOLD.to_owned(),
+        serde_json::Value::String(status.as_str().to_owned()),
+    );
+}
+
+/// Checks replacing the retention of a version at `now`.
+///
+/// Retention can always be extended. Shortening it, or turning `COMPLIANCE` into
+/// `GOVERNANCE`, is refused unless `GOVERNANCE` retention is bypassed.
+pub fn check_update(
+    current: Option<&Retention>,
+    retention: &Retention,
+    now: NaiveDateTime,
+    bypass_governance: bool,
+) -> S3Result<()> {
+    let Some(current) = current.filter(|current| current.retain_until > now) else {
+        return Ok(());
+    };
+    let weakened = retention.retain_until < current.retain_until
+        || (!current.is_governance() && retention.is_governance());
+    if weakened {
+        check_delete(Some(current), now, bypass_governance)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks the permanent deletion of a version against its retention at `now`.
+///
+/// `GOVERNANCE` retention is lifted when the caller may bypass it and asked to,
+/// `COMPLIANCE` retention never is.
+pub fn check_delete(
+    retention: Option<&Retention>,
+    now: NaiveDateTime,
+    bypass_governance: bool,
+) -> S3Result<()> {
+    match retention {
+        Some(retention) if retention.retain_until > now => {
+            if bypass_governance && retention.is_governance() {
+                Ok(())
+            } else {
+                Err(s3_error!(
+                    AccessDenied,
+                    "the object is locked until {}",
+                    retention.retain_until.and_utc().to_rfc3339()
+                ))
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A version under legal hold cannot be deleted, whatever its retention.
+pub fn check_legal_hold(info: &InternalInfo) -> S3Result<()> {
+    if legal_hold_from_internal_info(info) {
+        return Err(s3_error!(AccessDenied, "the object is under legal hold"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn retention(mode: &str, retain_until: NaiveDateTime) -> InternalInfo {
+        serde_json::from_value(serde_json::json!({
+            "object_lock_mode": mode,
+            "object_lock_retain_until_date": retain_until.and_utc().to_rfc3339(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_delete() {
+        let now = chrono::Utc::now().naive_utc();
+        let later = now + TimeDelta::days(1);
+
+        let governance = from_internal_info(&retention("GOVERNANCE", later)).unwrap();
+        assert!(check_delete(Some(&governance), now, false).is_err());
+        assert!(check_delete(Some(&governance), now, true).is_ok());
+        // retention that has elapsed no longer applies
+        assert!(check_delete(Some(&governance), later, false).is_ok());
+
+        let compliance = from_internal_info(&retention("COMPLIANCE", later)).unwrap();
+        assert!(check_delete(Some(&compliance), now, true).is_err());
+
+        assert!(check_delete(None, now, false).is_ok());
+        assert!(from_internal_info(&InternalInfo::new()).is_none());
+    }
+
+    #[test]
+    fn test_check_request() {
+        let now = chrono::Utc::now().naive_utc();
+        let later = now + TimeDelta::days(1);
+
+        let retention = check_request(Some("COMPLIANCE"), Some(later), now)
+            .unwrap()
+            .unwrap();
+        let mut info = InternalInfo::new();
+        modify_internal_info(&mut info, &retention);
+        assert_eq!(from_internal_info(&info), Some(retention));
+
+        assert!(check_request(None, None, now).unwrap().is_none());
+        assert!(check_request(Some("COMPLIANCE"), None, now).is_err());
+        assert!(check_request(Some("LOCKED"), Some(later), now).is_err());
+        assert!(check_request(Some("GOVERNANCE"), Some(now), now).is_err());
+    }
+
+    #[test]
+    fn test_check_update() {
+        let now = chrono::Utc::now().naive_utc();
+        let later = now + TimeDelta::days(1);
+        let retention = |mode: &str, retain_until| {
+            from_internal_info(&self::retention(mode, retain_until)).unwrap()
+        };
+
+        let governance = retention("GOVERNANCE", later);
+        let extended = retention("GOVERNANCE", later + TimeDelta::days(1));
+        assert!(check_update(Some(&governance), &extended, now, false).is_ok());
+        assert!(check_update(Some(&extended), &governance, now, false).is_err());
+        assert!(check_update(Some(&extended), &governance, now, true).is_ok());
+
+        let compliance = retention("COMPLIANCE", later);
+        assert!(check_update(Some(&compliance), &governance, now, true).is_err());
+        assert!(check_update(Some(&governance), &compliance, now, false).is_ok());
+    }
+
+    #[test]
+    fn test_legal_hold() {
+        let mut info = InternalInfo::new();
+        assert!(check_legal_hold(&info).is_ok());
+        modify_legal_hold(
+            &mut info,
+            &ObjectLockLegalHoldStatus::from_static(ObjectLockLegalHoldStatus::ON),
+        );
+        assert!(check_legal_hold(&info).is_err());
+        modify_legal_hold(
+            &mut info,
+            &ObjectLockLegalHoldStatus::from_static(ObjectLockLegalHoldStatus::OFF),
+        );
+        assert!(check_legal_hold(&info).is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+use std::ops::Range;
+
+use hyper::StatusCode;
+use s3s::{S3Error, S3ErrorCode, S3Result};
+
+use crate::storage_backend::InternalInfo;
+
+/// Sizes of the parts of a completed multipart upload, in part order.
+const PART_SIZES: &str = "part_sizes";
+
+pub fn modify_internal_info(info: &mut InternalInfo, part_sizes: &[u64]) {
+    info.insert(PART_SIZES.to_owned(), serde_json::json!(part_sizes));
+}
+
+pub fn from_internal_info(info: &InternalInfo) -> Option<Vec<u64>> {
+    serde_json::from_value(info.get(PART_SIZES)?.clone()).ok()
+}
+
+/// Size of the stored data of an object, listed without looking at the file.
+const SIZE: &str = "size";
+
+pub fn modify_size(info: &mut InternalInfo, size: u64) {
+    info.insert(SIZE.to_owned(), serde_json::json!(size));
+}
+
+/// The recorded size, `None` for objects stored before it was recorded.
+pub fn size_from_internal_info(info: &InternalInfo) -> Option<u64> {
+    info.get(SIZE)?.as_u64()
+}
+
+/// `InvalidPartNumber`, which s3s has no error code for, answered with `416` as on S3.
+fn invalid_part_number() -> S3Error {
+    let mut err = S3Error::with_message(
+        S3ErrorCode::Custom("InvalidPartNumber".into()),
+        "the requested partnumber is not satisfiable",
+    );
+    err.set_status_code(StatusCode::RANGE_NOT_SATISFIABLE);
+    err
+}
+
+/// Byte range of part `part_number` of an object of `len` bytes, and the parts count when
+/// the object was uploaded in parts.
+///
+/// Parts are numbered from 1 in the order they make up the object. An object that was not
+/// uploaded in parts is a single part.
+pub fn part_range(
+    info: Option<&InternalInfo>,
+    part_number: i32,
+    len: u64,
+) -> S3Result<(Range<u64>, Option<i32>)> {
+    let part_sizes = info.and_then(from_internal_info);
+    let index = usize::try_from(part_number - 1).ok();
+    let Some(part_sizes) = part_sizes else {
+        return match index {
+            Some(0) => Ok((0..len, None)),
+            _ => Err(invalid_part_number()),
+        };
+    };
+    let Some(index) = index.filter(|index| *index < part_sizes.len()) else {
+        return Err(invalid_part_number());
+    };
+    let start = part_sizes[..index].iter().sum::<u64>();
+    let end = (start + part_sizes[index]).min(len);
+    let parts_count = i32::try_from(part_sizes.len()).ok();
+    Ok((start.min(end)..end, parts_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_range() {
+        let mut info = InternalInfo::new();
+        modify_internal_info(&mut info, &[5, 5, 2]);
+        assert_eq!(from_internal_info(&info), Some(vec![5, 5, 2]));
+
+        assert_eq!(part_range(Some(&info), 1, 12).unwrap(), (0..5, Some(3)));
+        assert_eq!(part_range(Some(&info), 3, 12).unwrap(), (10..12, Some(3)));
+        assert!(part_range(Some(&info), 0, 12).is_err());
+        let err = part_range(Some(&info), 4, 12).unwrap_err();
+        assert_eq!(*err.code(), S3ErrorCode::Custom("InvalidPartNumber".into()));
+        assert_eq!(err.status_code(), Some(StatusCode::RANGE_NOT_SATISFIABLE));
+
+        // an object put in one piece
+        assert_eq!(part_range(None, 1, 7).unwrap(), (0..7, None));
+        assert!(part_range(Some(&InternalInfo::new()), 2, 7).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{NaiveDateTime, TimeDelta};
+use s3s::dto::{RestoreStatus, Timestamp};
+
+use crate::storage_backend::InternalInfo;
+
+/// Restore state recorded by `RestoreObject`.
+///
+/// Objects are never archived, a restore completes immediately and only the requested
+/// availability window is kept so it can be reported back.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RestoreState {
+    /// End of the availability window, `None` when no number of days was requested.
+    pub expiry_date: Option<NaiveDateTime>,
+}
+
+impl RestoreState {
+    /// A restore requested at `now` for `days`, expiring at midnight UTC after the last day.
+    pub fn new(now: NaiveDateTime, days: Option<i32>) -> Self {
+        let expiry_date = days.map(|days| {
+            (now.date() + TimeDelta::days(i64::from(days) + 1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or(now)
+        });
+        Self { expiry_date }
+    }
+
+    /// Whether the restored copy is still available at `now`.
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.expiry_date.is_none_or(|expiry_date| expiry_date > now)
+    }
+
+    /// Restore status of the object in listings.
+    pub fn status(&self) -> RestoreStatus {
+        RestoreStatus {
+            is_restore_in_progress: Some(false),
+            restore_expiry_date: self
+                .expiry_date
+                .map(|d| Timestamp::from(std::time::SystemTime::from(d.and_utc()))),
+        }
+    }
+
+    /// Value of the `x-amz-restore` header.
+    pub fn header_value(&self) -> String {
+        match self.expiry_date {
+            Some(expiry_date) => format!(
+                "ongoing-request=\"false\", expiry-date=\"{}\"",
+                expiry_date.format("%a, %d %b %Y %H:%M:%S GMT")
+            ),
+            None => "ongoing-request=\"false\"".to_owned(),
+        }
+    }
+}
+
+pub fn modify_internal_info(info: &mut InternalInfo, state: &RestoreState) {
+    let expiry_date = state
+        .expiry_date
+        .map(|d| d.and_utc().to_rfc3339())
+        .map_or(serde_json::Value::Null, serde_json::Value::String);
+    info.insert(
+        "restore".to_owned(),
+        serde_json::json!({ "expiry_date": expiry_date }),
+    );
+}
+
+/// The restore state to report at `now`, a restore whose availability lapsed is not.
+pub fn active_from_internal_info(info: &InternalInfo, now: NaiveDateTime) -> Option<RestoreState> {
+    from_internal_info(info).filter(|state| state.is_active(now))
+}
+
+pub fn from_internal_info(info: &InternalInfo) -> Option<RestoreState> {
+    let restore = info.get("restore")?;
+    let expiry_date = restore
+        .get("expiry_date")
+        .and_then(|v| v.as_str())
+        .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+        .map(|v| v.naive_utc());
+    Some(RestoreState { expiry_date })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn test_expiry_is_rounded_to_midnight() {
+        let now = NaiveDate::from_ymd_opt(2012, 12, 18)
+            .unwrap()
+            .and_hms_opt(15, 30, 0)
+            .unwrap();
+        let state = RestoreState::new(now, Some(2));
+        assert_eq!(
+            state.header_value(),
+            "ongoing-request=\"false\", expiry-date=\"Fri, 21 Dec 2012 00:00:00 GMT\""
+        );
+        assert_eq!(
+            RestoreState::new(now, None).header_value(),
+            "ongoing-request=\"false\""
+        );
+    }
+
+    #[test]
+    fn test_internal_info_round_trip() {
+        let mut info = InternalInfo::new();
+        assert!(from_internal_info(&info).is_none());
+
+        let now = NaiveDate::from_ymd_opt(2012, 12, 18)
+            .unwrap()
+            .and_hms_opt(15, 30, 0)
+            .unwrap();
+        let state = RestoreState::new(now, Some(1));
+        modify_internal_info(&mut info, &state);
+        assert_eq!(from_internal_info(&info), Some(state));
+
+        modify_internal_info(&mut info, &RestoreState::default());
+        assert_eq!(from_internal_info(&info), Some(RestoreState::default()));
+    }
+
+    #[test]
+    fn test_restore_lapses_at_expiry() {
+        let now = NaiveDate::from_ymd_opt(2012, 12, 18)
+            .unwrap()
+            .and_hms_opt(15, 30, 0)
+            .unwrap();
+        let mut info = InternalInfo::new();
+        modify_internal_info(&mut info, &RestoreState::new(now, Some(1)));
+
+        assert!(active_from_internal_info(&info, now).is_some());
+        let expiry_date = from_internal_info(&info).unwrap().expiry_date.unwrap();
+        assert!(active_from_internal_info(&info, expiry_date).is_none());
+
+        // without a number of days the restore does not lapse
+        modify_internal_info(&mut info, &RestoreState::new(now, None));
+        assert!(active_from_internal_info(&info, now + TimeDelta::days(365)).is_some());
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// A bucket as tracked in the `bucket` table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct S3Bucket {
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    /// Access key of the creator, `None` for buckets created anonymously or before
+    /// buckets were tracked.
+    pub owner: Option<String>,
+}
+
+impl S3Bucket {
+    /// A bucket created now.
+    #[must_use]
+    pub fn new(name: String, owner: Option<String>) -> Self {
+        Self {
+            name,
+            created_at: chrono::Utc::now().naive_utc(),
+            owner,
+        }
+    }
+}
@@ -1,26 +1,33 @@
 use chrono::NaiveDateTime;
 use serde::Serialize;
 
+/// Version id of objects written while versioning is not enabled on the bucket.
+pub const NULL_VERSION_ID: &str = "null";
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, sqlx::FromRow)]
 pub struct S3ItemDetail {
     pub bucket: String,
     pub key: String,
+    pub version_id: String,
     pub e_tag: String,
     pub last_modified: NaiveDateTime,
     pub data_location: String,
     pub metadata: String,
     pub internal_info: String,
+    pub delete_marker: bool,
 }
 
 #[derive(Debug, Default)]
 pub struct S3ItemDetailBuilder {
     bucket: Option<String>,
     key: Option<String>,
+    version_id: Option<String>,
     e_tag: Option<String>,
     last_modified: Option<NaiveDateTime>,
     data_location: Option<String>,
     metadata: Option<String>,
     internal_info: Option<String>,
+    delete_marker: bool,
 }
 
 impl S3ItemDetail {
@@ -43,6 +50,13 @@ impl S3ItemDetailBuilder {
         self
     }
 
+    /// Sets the version id, defaults to [`NULL_VERSION_ID`] when not set.
+    #[must_use]
+    pub fn version_id(mut self, version_id: Option<String>) -> Self {
+        self.version_id = version_id;
+        self
+    }
+
     #[must_use]
     pub fn e_tag(mut self, e_tag: String) -> Self {
         self.e_tag = Some(e_tag);
@@ -73,6 +87,13 @@ impl S3ItemDetailBuilder {
         self
     }
 
+    /// Marks the item as a delete marker of a versioned bucket.
+    #[must_use]
+    pub fn delete_marker(mut self, delete_marker: bool) -> Self {
+        self.delete_marker = delete_marker;
+        self
+    }
+
     /// Creates a new [`S3ItemDetail`] from the builder.
     ///
     /// # Panics
@@ -84,11 +105,17 @@ impl S3ItemDetailBuilder {
         S3ItemDetail {
             bucket: self.bucket.expect("bucket is required"),
             key: self.key.expect("key is required"),
+            version_id: self
+                .version_id
+                .unwrap_or_else(|| NULL_VERSION_ID.to_string()),
             e_tag: self.e_tag.expect("e_tag is required"),
-            last_modified: chrono::Utc::now().naive_utc(),
+            last_modified: self
+                .last_modified
+                .unwrap_or_else(|| chrono::Utc::now().naive_utc()),
             data_location: self.data_location.expect("data_location is required"),
             metadata: self.metadata.expect("metadata is required"),
             internal_info: self.internal_info.expect("internal_info is required"),
+            delete_marker: self.delete_marker,
         }
     }
 }
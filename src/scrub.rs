@@ -0,0 +1,380 @@
+use std::{fs::Metadata, io, path::Path};
+
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+use crate::{
+    DataStore, NULL_VERSION_ID, S3ItemDetail,
+    datastore::MAX_QUERY_SIZE,
+    error::*,
+    settings::Layout,
+    sse_c,
+    storage_backend::{InternalInfo, data_location},
+    utils::{self, resolve_abs_path},
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrubOptions {
+    /// Deletes the rows of versions whose data is missing.
+    pub fix: bool,
+    /// Recomputes the MD5 of the data and compares it with the stored `ETag`.
+    pub verify_checksums: bool,
+}
+
+/// What a scrub found, counted in object versions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrubReport {
+    pub checked: u64,
+    /// Versions whose data file is gone.
+    pub missing: u64,
+    /// Versions whose data no longer matches their `ETag`.
+    pub corrupt: u64,
+    /// Rows of missing versions deleted with [`ScrubOptions::fix`].
+    pub removed: u64,
+}
+
+/// Metadata of the data of a version, `None` when it is missing.
+async fn data_metadata(path: &Path) -> Result<Option<Metadata>> {
+    match fs::metadata(path).await {
+        Ok(metadata) => Ok(Some(metadata)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The MD5 of the data when it can be compared with the `ETag`.
+///
+/// Data encrypted with a customer key is stored as ciphertext while the `ETag` is the MD5
+/// of the plaintext, it cannot be verified without the key.
+async fn actual_md5(path: &Path, item: &S3ItemDetail) -> Result<Option<String>> {
+    let info = serde_json::from_str::<InternalInfo>(&item.internal_info).unwrap_or_default();
+    if sse_c::from_internal_info(&info).is_some() {
+        debug!(bucket = %item.bucket, key = %item.key, "encrypted with a customer key, checksum skipped");
+        return Ok(None);
+    }
+    utils::md5_sum(path).await.map(Some)
+}
+
+/// Checks that the data of every stored object version is on disk under `root`,
+/// and with [`ScrubOptions::verify_checksums`] that it still matches its `ETag`.
+///
+/// Findings are only reported unless [`ScrubOptions::fix`] is set, which deletes the
+/// rows of versions whose data is missing. Corrupt data is never removed, it may still be
+/// restored from a backup. A running server may keep serving a deleted row from its
+/// object metadata cache until the entry expires.
+pub async fn scrub<T: DataStore>(
+    datastore: &T,
+    root: &Path,
+    options: ScrubOptions,
+) -> Result<ScrubReport> {
+    let root = root.canonicalize()?;
+    let mut report = ScrubReport::default();
+    let mut after = (String::new(), String::new(), String::new());
+    loop {
+        let items = datastore
+            .list_item_versions_page(&after.0, &after.1, &after.2, MAX_QUERY_SIZE)
+            .await?;
+        let Some(last) = items.last() else {
+            break;
+        };
+        after = (
+            last.bucket.clone(),
+            last.key.clone(),
+            last.version_id.clone(),
+        );
+
+        for item in items.iter().filter(|item| !item.delete_marker) {
+            report.checked += 1;
+            let path = resolve_abs_path(&root, &item.data_location)?;
+            match data_metadata(&path).await? {
+                None => {
+                    report.missing += 1;
+                    warn!(
+                        bucket = %item.bucket,
+                        key = %item.key,
+                        version_id = %item.version_id,
+                        path = %path.display(),
+                        "object data is missing"
+                    );
+                    if options.fix {
+                        datastore
+                            .delete_s3_item_detail_version(
+                                &item.bucket,
+                                &item.key,
+                                &item.version_id,
+                            )
+                            .await?;
+                        report.removed += 1;
+                        info!(
+                            bucket = %item.bucket,
+                            key = %item.key,
+                            version_id = %item.version_id,
+                            "dangling object version removed"
+                        );
+                    }
+                }
+                // directory objects have no data to verify
+                Some(metadata) if metadata.is_file() && options.verify_checksums => {
+                    if let Some(md5) = actual_md5(&path, item).await?
+                        && md5 != item.e_tag
+                    {
+                        report.corrupt += 1;
+                        warn!(
+                            bucket = %item.bucket,
+                            key = %item.key,
+                            version_id = %item.version_id,
+                            path = %path.display(),
+                            e_tag = %item.e_tag,
+                            md5 = %md5,
+                            "object data does not match its ETag"
+                        );
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Moves the data of every stored object version to where `layout` puts it, returning how
+/// many versions were moved.
+///
+/// Meant to be run while the server is stopped, it could otherwise overwrite an object
+/// being moved or keep serving a moved one from the old location out of its object
+/// metadata cache. Directory markers get a new empty directory, the old one stays as long
+/// as other objects are stored under it.
+pub async fn relocate<T: DataStore>(datastore: &T, root: &Path, layout: Layout) -> Result<u64> {
+    let root = root.canonicalize()?;
+    let mut moved = 0;
+    let mut after = (String::new(), String::new(), String::new());
+    loop {
+        let items = datastore
+            .list_item_versions_page(&after.0, &after.1, &after.2, MAX_QUERY_SIZE)
+            .await?;
+        let Some(last) = items.last() else {
+            break;
+        };
+        after = (
+            last.bucket.clone(),
+            last.key.clone(),
+            last.version_id.clone(),
+        );
+
+        for item in items.iter().filter(|item| !item.delete_marker) {
+            let version_id =
+                (item.version_id != NULL_VERSION_ID).then_some(item.version_id.as_str());
+            let location = data_location(layout, &item.bucket, &item.key, version_id);
+            if location != item.data_location
+                && relocate_item(datastore, &root, item, &location).await?
+            {
+                moved += 1;
+            }
+        }
+    }
+    Ok(moved)
+}
+
+/// Moves the data of a version to `location`, `false` when there was nothing to move.
+async fn relocate_item<T: DataStore>(
+    datastore: &T,
+    root: &Path,
+    item: &S3ItemDetail,
+    location: &str,
+) -> Result<bool> {
+    let from = resolve_abs_path(&root.to_path_buf(), &item.data_location)?;
+    let to = resolve_abs_path(&root.to_path_buf(), location)?;
+    let Some(metadata) = data_metadata(&from).await? else {
+        warn!(
+            bucket = %item.bucket,
+            key = %item.key,
+            version_id = %item.version_id,
+            path = %from.display(),
+            "object data is missing, not relocated"
+        );
+        return Ok(false);
+    };
+    if metadata.is_dir() {
+        fs::create_dir_all(&to).await?;
+    } else {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(&from, &to).await?;
+    }
+
+    let moved = datastore
+        .move_s3_item_detail(item, &item.bucket, &item.key, location)
+        .await;
+    if !matches!(moved, Ok(true)) {
+        // the data goes back to where the row, if any, points
+        let result = if metadata.is_dir() {
+            fs::remove_dir(&to).await
+        } else {
+            fs::rename(&to, &from).await
+        };
+        if let Err(e) = result {
+            warn!(error = %e, from = %to.display(), to = %from.display(), "failed to move object data back");
+        }
+        return moved;
+    }
+    if metadata.is_dir() {
+        // other objects may still be stored under it
+        let _ = fs::remove_dir(&from).await;
+    }
+    remove_empty_parents(root, &from).await;
+    debug!(
+        bucket = %item.bucket,
+        key = %item.key,
+        version_id = %item.version_id,
+        from = %item.data_location,
+        to = %location,
+        "object data relocated"
+    );
+    Ok(true)
+}
+
+/// Removes the directories left empty above `path`, up to the directories right under the
+/// root like the one of a bucket.
+async fn remove_empty_parents(root: &Path, path: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir.parent() == Some(root) || fs::remove_dir(dir).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Pool, Postgres};
+
+    use super::*;
+    use crate::PostgresDatastore;
+
+    fn item(key: &str, e_tag: &str) -> S3ItemDetail {
+        S3ItemDetail::builder()
+            .bucket("bucket".to_string())
+            .key(key.to_string())
+            .e_tag(e_tag.to_string())
+            .data_location(format!("bucket/{key}"))
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .build()
+    }
+
+    #[sqlx::test]
+    async fn test_scrub(pool: Pool<Postgres>) {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let root = tmp_dir.path();
+        fs::create_dir_all(root.join("bucket/dir/")).await.unwrap();
+        fs::write(root.join("bucket/intact"), "intact")
+            .await
+            .unwrap();
+        fs::write(root.join("bucket/corrupt"), "corrupt")
+            .await
+            .unwrap();
+
+        let ds = PostgresDatastore::with_pool(pool);
+        for item in [
+            item(
+                "intact",
+                &utils::hex(<md5::Md5 as md5::Digest>::digest("intact")),
+            ),
+            item("corrupt", "d41d8cd98f00b204e9800998ecf8427e"),
+            item("dir/", "d41d8cd98f00b204e9800998ecf8427e"),
+            item("missing", "d41d8cd98f00b204e9800998ecf8427e"),
+        ] {
+            ds.save_s3_item_detail(&item).await.unwrap();
+        }
+
+        let options = ScrubOptions {
+            fix: false,
+            verify_checksums: true,
+        };
+        let report = scrub(&ds, root, options).await.unwrap();
+        assert_eq!(
+            report,
+            ScrubReport {
+                checked: 4,
+                missing: 1,
+                corrupt: 1,
+                removed: 0,
+            }
+        );
+        assert!(
+            ds.get_s3_item_detail("bucket", "missing")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let options = ScrubOptions {
+            fix: true,
+            verify_checksums: false,
+        };
+        let report = scrub(&ds, root, options).await.unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(
+            ds.get_s3_item_detail("bucket", "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        // corrupt data is only reported
+        assert!(
+            ds.get_s3_item_detail("bucket", "corrupt")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let report = scrub(&ds, root, options).await.unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.missing, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_relocate(pool: Pool<Postgres>) {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let root = tmp_dir.path();
+        fs::create_dir_all(root.join("bucket/dir/")).await.unwrap();
+        fs::write(root.join("bucket/dir/a"), "a").await.unwrap();
+
+        let ds = PostgresDatastore::with_pool(pool);
+        for item in [
+            item("dir/", "d41d8cd98f00b204e9800998ecf8427e"),
+            item("dir/a", ""),
+        ] {
+            ds.save_s3_item_detail(&item).await.unwrap();
+        }
+
+        let moved = relocate(&ds, root, Layout::Sharded).await.unwrap();
+        assert_eq!(moved, 2);
+        let item = ds
+            .get_s3_item_detail("bucket", "dir/a")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            item.data_location,
+            data_location(Layout::Sharded, "bucket", "dir/a", None)
+        );
+        assert_eq!(
+            fs::read_to_string(root.join(&item.data_location))
+                .await
+                .unwrap(),
+            "a"
+        );
+        // the emptied directories are gone, the bucket stays
+        assert!(!root.join("bucket/dir").exists());
+        assert!(root.join("bucket").is_dir());
+
+        assert_eq!(relocate(&ds, root, Layout::Sharded).await.unwrap(), 0);
+        assert_eq!(relocate(&ds, root, Layout::Flat).await.unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(root.join("bucket/dir/a")).await.unwrap(),
+            "a"
+        );
+        assert!(!root.join(".hashed/bucket").exists());
+    }
+}
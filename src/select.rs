@@ -0,0 +1,910 @@
+use std::borrow::Cow;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt, pin_mut};
+use s3s::{
+    S3Error, S3Result,
+    dto::{
+        CSVInput, CSVOutput, CompressionType, EndEvent, ExpressionType, FileHeaderInfo,
+        OutputSerialization, QuoteFields, RecordsEvent, SelectObjectContentEvent,
+        SelectObjectContentEventStream, SelectObjectContentRequest, Stats, StatsEvent,
+    },
+    s3_error,
+};
+use transform_stream::AsyncTryStream;
+
+/// Bytes of the object read at a time, each read sends at most one `Records` event.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A column as referenced by the query, `_1` by position or `name` from the header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Column {
+    Position(usize),
+    /// Unquoted names match header names regardless of case, `"quoted"` ones exactly.
+    Name {
+        name: String,
+        exact: bool,
+    },
+}
+
+/// A query of the supported SQL subset:
+///
+/// `SELECT * | column, ... FROM S3Object [[AS] alias] [WHERE column = 'value' [AND ...]] [LIMIT n]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Query {
+    /// `None` for `SELECT *`
+    columns: Option<Vec<Column>>,
+    conditions: Vec<(Column, String)>,
+    limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    QuotedName(String),
+    Literal(String),
+    Symbol(char),
+}
+
+fn unsupported(message: impl Into<String>) -> S3Error {
+    let message = message.into();
+    s3_error!(InvalidRequest, "unsupported SQL expression: {message}")
+}
+
+fn tokenize(sql: &str) -> S3Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '*' | ',' | '.' | '=' => tokens.push(Token::Symbol(c)),
+            '\'' | '"' => {
+                // the delimiter is escaped by doubling it
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(d) if d == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            s.push(c);
+                        }
+                        Some(d) if d == c => break,
+                        Some(d) => s.push(d),
+                        None => return Err(unsupported("unterminated quote")),
+                    }
+                }
+                tokens.push(if c == '\'' {
+                    Token::Literal(s)
+                } else {
+                    Token::QuotedName(s)
+                });
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut s = String::from(c);
+                while let Some(&d) = chars.peek().filter(|d| d.is_alphanumeric() || **d == '_') {
+                    s.push(d);
+                    chars.next();
+                }
+                tokens.push(Token::Word(s));
+            }
+            c => return Err(unsupported(format!("unexpected character {c:?}"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.tokens
+            .next_if(|t| matches!(t, Token::Word(w) if w.eq_ignore_ascii_case(keyword)))
+            .is_some()
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> S3Result<()> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(unsupported(format!("expected {keyword}")))
+        }
+    }
+
+    fn symbol(&mut self, symbol: char) -> bool {
+        self.tokens.next_if_eq(&Token::Symbol(symbol)).is_some()
+    }
+
+    /// A column as written, `name` or `alias.name`, checked against the alias after `FROM`.
+    fn path(&mut self) -> S3Result<Vec<Token>> {
+        let mut path = Vec::new();
+        loop {
+            match self.tokens.next() {
+                Some(token @ (Token::Word(_) | Token::QuotedName(_))) => path.push(token),
+                _ => return Err(unsupported("expected a column")),
+            }
+            if !self.symbol('.') {
+                return Ok(path);
+            }
+        }
+    }
+}
+
+fn column(path: &[Token], alias: &str) -> S3Result<Column> {
+    let name = match path {
+        [name] => name,
+        [Token::Word(a), name]
+            if a.eq_ignore_ascii_case(alias) || a.eq_ignore_ascii_case("S3Object") =>
+        {
+            name
+        }
+        _ => return Err(unsupported("columns must be of the form alias.name")),
+    };
+    Ok(match name {
+        Token::Word(w) => match w.strip_prefix('_').map(str::parse::<usize>) {
+            Some(Ok(n)) if n >= 1 => Column::Position(n - 1),
+            _ => Column::Name {
+                name: w.clone(),
+                exact: false,
+            },
+        },
+        Token::QuotedName(name) => Column::Name {
+            name: name.clone(),
+            exact: true,
+        },
+        _ => return Err(unsupported("expected a column")),
+    })
+}
+
+impl Query {
+    fn parse(sql: &str) -> S3Result<Self> {
+        let mut p = Parser {
+            tokens: tokenize(sql)?.into_iter().peekable(),
+        };
+        p.expect_keyword("SELECT")?;
+        let paths = if p.symbol('*') {
+            None
+        } else {
+            let mut paths = vec![p.path()?];
+            while p.symbol(',') {
+                paths.push(p.path()?);
+            }
+            Some(paths)
+        };
+
+        p.expect_keyword("FROM")?;
+        if !p.keyword("S3Object") {
+            return Err(unsupported("only FROM S3Object is supported"));
+        }
+        p.keyword("AS");
+        let alias = p.tokens.next_if(|t| {
+            matches!(t, Token::Word(w)
+                if !w.eq_ignore_ascii_case("WHERE") && !w.eq_ignore_ascii_case("LIMIT"))
+        });
+        let alias = match alias {
+            Some(Token::Word(w)) => w,
+            _ => "S3Object".to_owned(),
+        };
+        let columns = paths
+            .map(|paths| {
+                paths
+                    .into_iter()
+                    .map(|path| column(&path, &alias))
+                    .collect()
+            })
+            .transpose()?;
+
+        let mut conditions = Vec::new();
+        if p.keyword("WHERE") {
+            loop {
+                let column = column(&p.path()?, &alias)?;
+                if !p.symbol('=') {
+                    return Err(unsupported("only = comparisons are supported"));
+                }
+                let Some(Token::Literal(value)) = p.tokens.next() else {
+                    return Err(unsupported("columns can only be compared to a 'string'"));
+                };
+                conditions.push((column, value));
+                if !p.keyword("AND") {
+                    break;
+                }
+            }
+        }
+
+        let limit = if p.keyword("LIMIT") {
+            match p.tokens.next() {
+                Some(Token::Word(n)) => Some(n.parse().map_err(|_| unsupported("invalid LIMIT"))?),
+                _ => return Err(unsupported("invalid LIMIT")),
+            }
+        } else {
+            None
+        };
+
+        if let Some(token) = p.tokens.next() {
+            return Err(unsupported(format!("unexpected {token:?}")));
+        }
+        Ok(Self {
+            columns,
+            conditions,
+            limit,
+        })
+    }
+}
+
+/// A delimiter or quote of the input, which must be a single ASCII character.
+fn single_byte(value: Option<&str>, default: u8, what: &str) -> S3Result<u8> {
+    match value.map(str::as_bytes) {
+        None => Ok(default),
+        Some(&[b]) if b.is_ascii() => Ok(b),
+        _ => Err(s3_error!(
+            InvalidRequest,
+            "the {what} must be a single ASCII character"
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CsvState {
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// A quote in a quoted field, closing it unless another one follows
+    QuoteInQuoted,
+    /// The escape character in a quoted field, when it is not the quote
+    Escaped,
+    Comment,
+}
+
+/// Splits CSV data into records as it arrives.
+#[derive(Debug)]
+struct CsvReader {
+    field_delimiter: u8,
+    record_delimiter: u8,
+    quote: u8,
+    escape: u8,
+    comments: Option<u8>,
+    allow_quoted_record_delimiter: bool,
+    state: CsvState,
+    fields: Vec<String>,
+    field: Vec<u8>,
+    /// Whether anything of the current record was read
+    in_record: bool,
+}
+
+impl CsvReader {
+    fn new(input: &CSVInput) -> S3Result<Self> {
+        // `\r\n` is read as `\n`, a `\r` ending a record is always dropped
+        let record_delimiter = match input.record_delimiter.as_deref() {
+            Some("\r\n") => b'\n',
+            other => single_byte(other, b'\n', "record delimiter")?,
+        };
+        let quote = single_byte(input.quote_character.as_deref(), b'"', "quote character")?;
+        Ok(Self {
+            field_delimiter: single_byte(
+                input.field_delimiter.as_deref(),
+                b',',
+                "field delimiter",
+            )?,
+            record_delimiter,
+            quote,
+            escape: single_byte(
+                input.quote_escape_character.as_deref(),
+                quote,
+                "quote escape character",
+            )?,
+            comments: match input.comments.as_deref() {
+                Some("") => None,
+                other => Some(single_byte(other, b'#', "comment character")?),
+            },
+            allow_quoted_record_delimiter: input.allow_quoted_record_delimiter.unwrap_or(false),
+            state: CsvState::FieldStart,
+            fields: Vec::new(),
+            field: Vec::new(),
+            in_record: false,
+        })
+    }
+
+    fn end_field(&mut self) {
+        let field = std::mem::take(&mut self.field);
+        self.fields
+            .push(String::from_utf8_lossy(&field).into_owned());
+        self.state = CsvState::FieldStart;
+    }
+
+    fn end_record(&mut self, records: &mut Vec<Vec<String>>) {
+        if self.record_delimiter == b'\n' && self.field.last() == Some(&b'\r') {
+            self.field.pop();
+        }
+        let empty_line = self.fields.is_empty()
+            && self.field.is_empty()
+            && self.state != CsvState::QuoteInQuoted;
+        self.end_field();
+        let fields = std::mem::take(&mut self.fields);
+        if !empty_line {
+            records.push(fields);
+        }
+        self.in_record = false;
+    }
+
+    fn push(&mut self, data: &[u8], records: &mut Vec<Vec<String>>) {
+        for &b in data {
+            let record_start = !self.in_record;
+            self.in_record = true;
+            match self.state {
+                CsvState::Comment => {
+                    if b == self.record_delimiter {
+                        self.state = CsvState::FieldStart;
+                        self.in_record = false;
+                    }
+                }
+                CsvState::FieldStart if record_start && Some(b) == self.comments => {
+                    self.state = CsvState::Comment;
+                }
+                CsvState::FieldStart if b == self.quote => self.state = CsvState::Quoted,
+                CsvState::Quoted if b == self.escape && self.escape != self.quote => {
+                    self.state = CsvState::Escaped;
+                }
+                CsvState::Quoted if b == self.quote => self.state = CsvState::QuoteInQuoted,
+                CsvState::Quoted
+                    if b == self.record_delimiter && !self.allow_quoted_record_delimiter =>
+                {
+                    self.end_record(records);
+                }
+                CsvState::Quoted => self.field.push(b),
+                CsvState::Escaped => {
+                    self.field.push(b);
+                    self.state = CsvState::Quoted;
+                }
+                CsvState::QuoteInQuoted if b == self.quote && self.escape == self.quote => {
+                    self.field.push(b);
+                    self.state = CsvState::Quoted;
+                }
+                CsvState::FieldStart | CsvState::Unquoted | CsvState::QuoteInQuoted => {
+                    if b == self.field_delimiter {
+                        self.end_field();
+                    } else if b == self.record_delimiter {
+                        self.end_record(records);
+                    } else {
+                        // text after a closing quote is kept, as most readers do
+                        self.field.push(b);
+                        self.state = CsvState::Unquoted;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ends the last record when the data does not end with a record delimiter.
+    fn finish(&mut self, records: &mut Vec<Vec<String>>) {
+        if self.in_record && self.state != CsvState::Comment {
+            self.end_record(records);
+        }
+        self.in_record = false;
+    }
+}
+
+#[derive(Debug)]
+enum Output {
+    Csv {
+        field_delimiter: String,
+        record_delimiter: String,
+        quote: char,
+        escape: char,
+        always_quote: bool,
+    },
+    Json {
+        record_delimiter: String,
+    },
+}
+
+fn single_char(value: Option<&str>, default: char, what: &str) -> S3Result<char> {
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(s3_error!(
+            InvalidRequest,
+            "the {what} must be a single character"
+        )),
+    }
+}
+
+impl Output {
+    fn new(output: &OutputSerialization) -> S3Result<Self> {
+        match (&output.csv, &output.json) {
+            (Some(csv), None) => Self::csv(csv),
+            (None, Some(json)) => Ok(Self::Json {
+                record_delimiter: json
+                    .record_delimiter
+                    .clone()
+                    .unwrap_or_else(|| "\n".to_owned()),
+            }),
+            _ => Err(s3_error!(
+                InvalidRequest,
+                "the output serialization must be either CSV or JSON"
+            )),
+        }
+    }
+
+    fn csv(csv: &CSVOutput) -> S3Result<Self> {
+        let quote = single_char(csv.quote_character.as_deref(), '"', "quote character")?;
+        Ok(Self::Csv {
+            field_delimiter: csv
+                .field_delimiter
+                .clone()
+                .unwrap_or_else(|| ",".to_owned()),
+            record_delimiter: csv
+                .record_delimiter
+                .clone()
+                .unwrap_or_else(|| "\n".to_owned()),
+            quote,
+            escape: single_char(
+                csv.quote_escape_character.as_deref(),
+                quote,
+                "quote escape character",
+            )?,
+            always_quote: csv
+                .quote_fields
+                .as_ref()
+                .is_some_and(|q| q.as_str() == QuoteFields::ALWAYS),
+        })
+    }
+
+    /// Appends a record of `(name, value)` pairs, names are only written in JSON.
+    fn write(&self, record: &[(Cow<'_, str>, &str)], out: &mut Vec<u8>) {
+        match self {
+            Self::Csv {
+                field_delimiter,
+                record_delimiter,
+                quote,
+                escape,
+                always_quote,
+            } => {
+                for (i, (_, value)) in record.iter().enumerate() {
+                    if i > 0 {
+                        out.extend_from_slice(field_delimiter.as_bytes());
+                    }
+                    let needs_quotes = *always_quote
+                        || value.contains(field_delimiter.as_str())
+                        || value.contains(record_delimiter.as_str())
+                        || value.contains(['\r', '\n', *quote]);
+                    if needs_quotes {
+                        let escaped = value.replace(*quote, &format!("{escape}{quote}"));
+                        out.extend_from_slice(format!("{quote}{escaped}{quote}").as_bytes());
+                    } else {
+                        out.extend_from_slice(value.as_bytes());
+                    }
+                }
+                out.extend_from_slice(record_delimiter.as_bytes());
+            }
+            Self::Json { record_delimiter } => {
+                out.push(b'{');
+                for (i, (name, value)) in record.iter().enumerate() {
+                    if i > 0 {
+                        out.push(b',');
+                    }
+                    out.extend_from_slice(
+                        serde_json::Value::from(name.as_ref())
+                            .to_string()
+                            .as_bytes(),
+                    );
+                    out.push(b':');
+                    out.extend_from_slice(serde_json::Value::from(*value).to_string().as_bytes());
+                }
+                out.push(b'}');
+                out.extend_from_slice(record_delimiter.as_bytes());
+            }
+        }
+    }
+}
+
+/// The columns of a query resolved to positions once the header is known.
+#[derive(Debug)]
+struct Plan {
+    /// `None` for `SELECT *`
+    columns: Option<Vec<(String, usize)>>,
+    conditions: Vec<(usize, String)>,
+}
+
+/// Runs a `SelectObjectContent` request over CSV data, uncompressed and read in order.
+#[derive(Debug)]
+pub struct Select {
+    query: Query,
+    reader: CsvReader,
+    output: Output,
+    header_info: FileHeaderInfo,
+    /// The first record is the header and has not been read yet
+    header_pending: bool,
+    names: Option<Vec<String>>,
+    plan: Option<Plan>,
+    returned: u64,
+}
+
+impl Select {
+    /// Checks the request, only SQL over uncompressed CSV is supported.
+    pub fn new(request: &SelectObjectContentRequest) -> S3Result<Self> {
+        if request.expression_type.as_str() != ExpressionType::SQL {
+            return Err(s3_error!(InvalidRequest, "the expression type must be SQL"));
+        }
+        let input = &request.input_serialization;
+        if input
+            .compression_type
+            .as_ref()
+            .is_some_and(|c| c.as_str() != CompressionType::NONE)
+        {
+            return Err(s3_error!(
+                NotImplemented,
+                "compressed input is not supported"
+            ));
+        }
+        if request.scan_range.is_some() {
+            return Err(s3_error!(NotImplemented, "scan ranges are not supported"));
+        }
+        let csv = match (&input.csv, &input.json, &input.parquet) {
+            (Some(csv), None, None) => csv,
+            (None, None, None) => {
+                return Err(s3_error!(
+                    InvalidRequest,
+                    "the input serialization must be CSV"
+                ));
+            }
+            _ => {
+                return Err(s3_error!(NotImplemented, "only CSV input is supported"));
+            }
+        };
+        let header_info = csv
+            .file_header_info
+            .clone()
+            .unwrap_or_else(|| FileHeaderInfo::from_static(FileHeaderInfo::NONE));
+        Ok(Self {
+            query: Query::parse(&request.expression)?,
+            reader: CsvReader::new(csv)?,
+            output: Output::new(&request.output_serialization)?,
+            header_pending: header_info.as_str() != FileHeaderInfo::NONE,
+            header_info,
+            names: None,
+            plan: None,
+            returned: 0,
+        })
+    }
+
+    /// Whether the `LIMIT` of the query is reached, the rest of the object need not be read.
+    pub fn is_done(&self) -> bool {
+        self.query.limit.is_some_and(|limit| self.returned >= limit)
+    }
+
+    /// Reads the next bytes of the object and appends the matching records to `out`.
+    pub fn push(&mut self, data: &[u8], out: &mut Vec<u8>) -> S3Result<()> {
+        let mut records = Vec::new();
+        self.reader.push(data, &mut records);
+        self.records(records, out)
+    }
+
+    /// Reads the end of the object.
+    pub fn finish(&mut self, out: &mut Vec<u8>) -> S3Result<()> {
+        let mut records = Vec::new();
+        self.reader.finish(&mut records);
+        self.records(records, out)
+    }
+
+    fn resolve(&self, column: &Column) -> S3Result<(String, usize)> {
+        match column {
+            Column::Position(i) => Ok((format!("_{}", i + 1), *i)),
+            Column::Name { name, exact } => {
+                let Some(names) = &self.names else {
+                    return Err(s3_error!(
+                        InvalidRequest,
+                        "columns can only be referenced by name with FileHeaderInfo USE"
+                    ));
+                };
+                let position = names.iter().position(|n| {
+                    if *exact {
+                        n == name
+                    } else {
+                        n.eq_ignore_ascii_case(name)
+                    }
+                });
+                match position {
+                    Some(i) => Ok((names[i].clone(), i)),
+                    None => Err(s3_error!(
+                        InvalidRequest,
+                        "the header has no column {name:?}"
+                    )),
+                }
+            }
+        }
+    }
+
+    fn plan(&self) -> S3Result<Plan> {
+        let columns = match &self.query.columns {
+            None => None,
+            Some(columns) => Some(
+                columns
+                    .iter()
+                    .map(|c| self.resolve(c))
+                    .collect::<S3Result<_>>()?,
+            ),
+        };
+        let conditions = self
+            .query
+            .conditions
+            .iter()
+            .map(|(c, value)| Ok((self.resolve(c)?.1, value.clone())))
+            .collect::<S3Result<_>>()?;
+        Ok(Plan {
+            columns,
+            conditions,
+        })
+    }
+
+    fn records(&mut self, records: Vec<Vec<String>>, out: &mut Vec<u8>) -> S3Result<()> {
+        for fields in records {
+            if self.header_pending {
+                self.header_pending = false;
+                if self.header_info.as_str() == FileHeaderInfo::USE {
+                    self.names = Some(fields);
+                }
+                continue;
+            }
+            if self.is_done() {
+                break;
+            }
+            if self.plan.is_none() {
+                self.plan = Some(self.plan()?);
+            }
+            let Some(plan) = &self.plan else { continue };
+            let matches = plan
+                .conditions
+                .iter()
+                .all(|(i, value)| fields.get(*i) == Some(value));
+            if !matches {
+                continue;
+            }
+            // a column past the end of a record is empty
+            let record = match &plan.columns {
+                None => fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| {
+                        let name = match self.names.as_ref().and_then(|names| names.get(i)) {
+                            Some(name) => Cow::Borrowed(name.as_str()),
+                            None => Cow::Owned(format!("_{}", i + 1)),
+                        };
+                        (name, value.as_str())
+                    })
+                    .collect::<Vec<_>>(),
+                Some(columns) => columns
+                    .iter()
+                    .map(|(name, i)| {
+                        let value = fields.get(*i).map_or("", String::as_str);
+                        (Cow::Borrowed(name.as_str()), value)
+                    })
+                    .collect(),
+            };
+            self.output.write(&record, out);
+            self.returned += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The events answering a select over the object `data`: a `Records` event for each chunk
+/// with matching records, then `Stats` and `End`.
+pub fn event_stream<S>(mut select: Select, data: S) -> SelectObjectContentEventStream
+where
+    S: Stream<Item = std::io::Result<Bytes>> + Send + Sync + 'static,
+{
+    let records = |payload: Vec<u8>| {
+        SelectObjectContentEvent::Records(RecordsEvent {
+            payload: Some(payload.into()),
+        })
+    };
+    SelectObjectContentEventStream::new(AsyncTryStream::<_, S3Error, _>::new(|mut y| async move {
+        pin_mut!(data);
+        let mut scanned: u64 = 0;
+        let mut returned: u64 = 0;
+        while let Some(chunk) = data.next().await {
+            let chunk = try_!(chunk);
+            scanned += chunk.len() as u64;
+            let mut out = Vec::new();
+            select.push(&chunk, &mut out)?;
+            if !out.is_empty() {
+                returned += out.len() as u64;
+                y.yield_ok(records(out)).await;
+            }
+            if select.is_done() {
+                break;
+            }
+        }
+        let mut out = Vec::new();
+        select.finish(&mut out)?;
+        if !out.is_empty() {
+            returned += out.len() as u64;
+            y.yield_ok(records(out)).await;
+        }
+
+        let stats = Stats {
+            bytes_processed: i64::try_from(scanned).ok(),
+            bytes_returned: i64::try_from(returned).ok(),
+            bytes_scanned: i64::try_from(scanned).ok(),
+        };
+        y.yield_ok(SelectObjectContentEvent::Stats(StatsEvent {
+            details: Some(stats),
+        }))
+        .await;
+        y.yield_ok(SelectObjectContentEvent::End(EndEvent {})).await;
+        Ok(())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use s3s::{
+        S3ErrorCode,
+        dto::{InputSerialization, JSONOutput},
+    };
+
+    use super::*;
+
+    fn request(sql: &str, header: &str, output: OutputSerialization) -> SelectObjectContentRequest {
+        SelectObjectContentRequest {
+            expression: sql.to_owned(),
+            expression_type: ExpressionType::from_static(ExpressionType::SQL),
+            input_serialization: InputSerialization {
+                csv: Some(CSVInput {
+                    file_header_info: Some(FileHeaderInfo::from(header.to_owned())),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            output_serialization: output,
+            request_progress: None,
+            scan_range: None,
+        }
+    }
+
+    fn csv_output() -> OutputSerialization {
+        OutputSerialization {
+            csv: Some(CSVOutput::default()),
+            json: None,
+        }
+    }
+
+    /// Runs the query over `data` fed a few bytes at a time.
+    fn select(req: &SelectObjectContentRequest, data: &str) -> S3Result<String> {
+        let mut select = Select::new(req)?;
+        let mut out = Vec::new();
+        for chunk in data.as_bytes().chunks(3) {
+            select.push(chunk, &mut out)?;
+        }
+        select.finish(&mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    const DATA: &str = "name,city,note\r\n\
+                        alice,Paris,\"likes \"\"tea\"\", cake\"\r\n\
+                        # a comment\r\n\
+                        bob,Oslo,\r\n\
+                        \r\n\
+                        carol,Paris,\"two\nlines\"";
+
+    #[test]
+    fn test_select_csv() {
+        let req = request("SELECT * FROM S3Object", "NONE", csv_output());
+        let out = select(&req, "a,b\n\"c,d\",e\n").unwrap();
+        assert_eq!(out, "a,b\n\"c,d\",e\n");
+
+        let req = request(
+            "select s.name, s.\"note\" from s3object s where s.city = 'Paris'",
+            "USE",
+            csv_output(),
+        );
+        let mut req = req;
+        req.input_serialization
+            .csv
+            .as_mut()
+            .unwrap()
+            .allow_quoted_record_delimiter = Some(true);
+        let out = select(&req, DATA).unwrap();
+        assert_eq!(
+            out,
+            "alice,\"likes \"\"tea\"\", cake\"\ncarol,\"two\nlines\"\n"
+        );
+
+        let req = request(
+            "SELECT _1 FROM S3Object WHERE _2 = 'Oslo' AND _3 = '' LIMIT 5",
+            "IGNORE",
+            csv_output(),
+        );
+        assert_eq!(select(&req, DATA).unwrap(), "bob\n");
+
+        let req = request(
+            "SELECT s._1 FROM S3Object AS s LIMIT 1",
+            "IGNORE",
+            csv_output(),
+        );
+        assert_eq!(select(&req, DATA).unwrap(), "alice\n");
+    }
+
+    #[test]
+    fn test_select_json() {
+        let output = OutputSerialization {
+            csv: None,
+            json: Some(JSONOutput::default()),
+        };
+        let req = request(
+            "SELECT * FROM S3Object WHERE city = 'Oslo'",
+            "USE",
+            output.clone(),
+        );
+        assert_eq!(
+            select(&req, DATA).unwrap(),
+            "{\"name\":\"bob\",\"city\":\"Oslo\",\"note\":\"\"}\n"
+        );
+
+        let req = request("SELECT _2, _9 FROM S3Object LIMIT 1", "NONE", output);
+        assert_eq!(
+            select(&req, "a,b\nc,d\n").unwrap(),
+            "{\"_2\":\"b\",\"_9\":\"\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_sql() {
+        for sql in [
+            "SELECT COUNT(*) FROM S3Object",
+            "SELECT * FROM S3Object[*]",
+            "SELECT * FROM S3Object WHERE _1 > '1'",
+            "SELECT * FROM S3Object WHERE _1 = 1",
+            "SELECT * FROM other",
+            "SELECT * FROM S3Object s WHERE t._1 = 'a'",
+            "SELECT * FROM S3Object ORDER BY _1",
+            "SELECT * FROM S3Object WHERE _1 = 'a",
+            "DELETE FROM S3Object",
+        ] {
+            let req = request(sql, "NONE", csv_output());
+            let err = Select::new(&req).unwrap_err();
+            assert_eq!(*err.code(), S3ErrorCode::InvalidRequest, "{sql}");
+        }
+
+        // names need a header
+        let req = request("SELECT name FROM S3Object", "NONE", csv_output());
+        let err = select(&req, "a\n").unwrap_err();
+        assert_eq!(*err.code(), S3ErrorCode::InvalidRequest);
+        let req = request("SELECT missing FROM S3Object", "USE", csv_output());
+        assert!(select(&req, DATA).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_event_stream() {
+        let req = request(
+            "SELECT name FROM S3Object WHERE city = 'Oslo'",
+            "USE",
+            csv_output(),
+        );
+        let data = futures::stream::iter(
+            DATA.as_bytes()
+                .chunks(16)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect::<Vec<_>>(),
+        );
+        let events: Vec<_> = event_stream(Select::new(&req).unwrap(), data)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        let [
+            SelectObjectContentEvent::Records(records),
+            SelectObjectContentEvent::Stats(stats),
+            SelectObjectContentEvent::End(_),
+        ] = events.as_slice()
+        else {
+            panic!("unexpected events {events:?}");
+        };
+        assert_eq!(records.payload.as_deref(), Some(&b"bob\n"[..]));
+        let stats = stats.details.as_ref().unwrap();
+        assert_eq!(stats.bytes_scanned, i64::try_from(DATA.len()).ok());
+        assert_eq!(stats.bytes_returned, Some(4));
+    }
+}
@@ -0,0 +1,165 @@
+use s3s::{
+    S3Result,
+    dto::{
+        ServerSideEncryption, ServerSideEncryptionByDefault, ServerSideEncryptionConfiguration,
+        ServerSideEncryptionRule,
+    },
+    s3_error,
+};
+
+use crate::storage_backend::InternalInfo;
+
+/// Checks the `x-amz-server-side-encryption` header of an upload.
+///
+/// Objects are not encrypted at rest, the requested algorithm is only recorded and echoed
+/// back so clients that always send it keep working. Customer-provided keys (SSE-C) are
+/// handled separately and cannot be combined with it.
+pub fn check_request(
+    sse: Option<ServerSideEncryption>,
+    has_customer_key: bool,
+) -> S3Result<Option<ServerSideEncryption>> {
+    let Some(sse) = sse else {
+        return Ok(None);
+    };
+    check_algorithm(&sse)?;
+    if has_customer_key {
+        return Err(s3_error!(
+            InvalidArgument,
+            "server-side encryption cannot be combined with a customer-provided key"
+        ));
+    }
+    Ok(Some(sse))
+}
+
+fn check_algorithm(sse: &ServerSideEncryption) -> S3Result<()> {
+    if [
+        ServerSideEncryption::AES256,
+        ServerSideEncryption::AWS_KMS,
+        ServerSideEncryption::AWS_KMS_DSSE,
+    ]
+    .contains(&sse.as_str())
+    {
+        Ok(())
+    } else {
+        Err(s3_error!(
+            InvalidArgument,
+            "the server-side encryption algorithm {} is not supported",
+            sse.as_str()
+        ))
+    }
+}
+
+/// Checks the default encryption configuration of a bucket.
+///
+/// Like the `x-amz-server-side-encryption` header it is only stored and returned,
+/// uploads to the bucket are not encrypted by it.
+pub fn check_bucket_config(config: &ServerSideEncryptionConfiguration) -> S3Result<()> {
+    if config.rules.is_empty() {
+        return Err(s3_error!(MalformedXML, "at least one rule is required"));
+    }
+    for rule in &config.rules {
+        let Some(default) = &rule.apply_server_side_encryption_by_default else {
+            return Err(s3_error!(
+                MalformedXML,
+                "a rule must apply server-side encryption by default"
+            ));
+        };
+        check_algorithm(&default.sse_algorithm)?;
+    }
+    Ok(())
+}
+
+pub fn bucket_config_to_json(config: &ServerSideEncryptionConfiguration) -> String {
+    let rules = config
+        .rules
+        .iter()
+        .map(|rule| {
+            let default = rule.apply_server_side_encryption_by_default.as_ref();
+            serde_json::json!({
+                "sse_algorithm": default.map(|d| d.sse_algorithm.as_str()),
+                "kms_master_key_id": default.and_then(|d| d.kms_master_key_id.as_deref()),
+                "bucket_key_enabled": rule.bucket_key_enabled,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({ "rules": rules }).to_string()
+}
+
+pub fn bucket_config_from_json(json: &str) -> Option<ServerSideEncryptionConfiguration> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let rules = value
+        .get("rules")?
+        .as_array()?
+        .iter()
+        .map(|rule| ServerSideEncryptionRule {
+            apply_server_side_encryption_by_default: rule
+                .get("sse_algorithm")
+                .and_then(|v| v.as_str())
+                .map(|sse_algorithm| ServerSideEncryptionByDefault {
+                    kms_master_key_id: rule
+                        .get("kms_master_key_id")
+                        .and_then(|v| v.as_str())
+                        .map(str::to_owned),
+                    sse_algorithm: ServerSideEncryption::from(sse_algorithm.to_owned()),
+                }),
+            bucket_key_enabled: rule
+                .get("bucket_key_enabled")
+                .and_then(serde_json::Value::as_bool),
+        })
+        .collect();
+    Some(ServerSideEncryptionConfiguration { rules })
+}
+
+pub fn modify_internal_info(info: &mut InternalInfo, sse: &ServerSideEncryption) {
+    info.insert(
+        "server_side_encryption".to_owned(),
+        serde_json::Value::String(sse.as_str().to_owned()),
+    );
+}
+
+pub fn from_internal_info(info: &InternalInfo) -> Option<ServerSideEncryption> {
+    info.get("server_side_encryption")
+        .and_then(|v| v.as_str())
+        .map(|v| ServerSideEncryption::from(v.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_request_and_round_trip() {
+        assert_eq!(check_request(None, true).unwrap(), None);
+
+        let sse = check_request(Some(ServerSideEncryption::from_static("aws:kms")), false)
+            .unwrap()
+            .unwrap();
+        let mut info = InternalInfo::new();
+        assert_eq!(from_internal_info(&info), None);
+        modify_internal_info(&mut info, &sse);
+        assert_eq!(from_internal_info(&info), Some(sse.clone()));
+
+        assert!(check_request(Some(sse), true).is_err());
+        assert!(check_request(Some(ServerSideEncryption::from_static("rot13")), false).is_err());
+    }
+
+    #[test]
+    fn test_bucket_config_round_trip() {
+        let config = ServerSideEncryptionConfiguration {
+            rules: vec![ServerSideEncryptionRule {
+                apply_server_side_encryption_by_default: Some(ServerSideEncryptionByDefault {
+                    kms_master_key_id: Some("key".to_owned()),
+                    sse_algorithm: ServerSideEncryption::from_static(ServerSideEncryption::AWS_KMS),
+                }),
+                bucket_key_enabled: Some(true),
+            }],
+        };
+        check_bucket_config(&config).unwrap();
+        let json = bucket_config_to_json(&config);
+        let stored = bucket_config_from_json(&json).unwrap();
+        assert_eq!(bucket_config_to_json(&stored), json);
+
+        let empty = ServerSideEncryptionConfiguration { rules: vec![] };
+        assert!(check_bucket_config(&empty).is_err());
+    }
+}
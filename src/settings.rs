@@ -1,12 +1,84 @@
+use std::time::Duration;
+
 use serde::Deserialize;
 
+use crate::datastore::{
+    ADMIN_STATEMENT_TIMEOUT_MS, CONNECT_ATTEMPTS, CONNECT_RETRY_BASE_DELAY_MS, CONNECTION_TIMEOUT,
+    STATEMENT_TIMEOUT_MS,
+};
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Settings {
     pub datasource: Ds,
+    /// Object metadata cache, disabled when absent.
+    pub cache: Option<Cache>,
+    /// Access keys accepted besides the one given on the command line.
+    #[serde(default)]
+    pub credentials: Vec<Credential>,
+    #[serde(default)]
+    pub storage: Storage,
+}
+
+/// How object data is laid out under the root directory.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Storage {
+    #[serde(default)]
+    pub layout: Layout,
+}
+
+/// Where the data of an object is written, see `StorageBackend::with_layout`.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Along the key, at `bucket/key`.
+    #[default]
+    Flat,
+    /// Under the hash of the key, spread over two levels of 256 directories so a bucket
+    /// of millions of keys does not end up in a single huge directory.
+    Sharded,
+}
+
+/// An access key and its secret key.
+#[derive(Deserialize, Clone)]
+pub struct Credential {
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("access_key", &self.access_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// In-process LRU cache of the latest object metadata, see `StorageBackend::with_item_cache`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Cache {
+    /// Maximum number of objects kept, 0 disables the cache.
+    pub capacity: usize,
+    /// Seconds an entry is served before it is looked up again.
+    pub ttl_secs: u64,
+    /// Seconds a lookup of a missing object is remembered, 0 (the default) disables it.
+    #[serde(default)]
+    pub negative_ttl_secs: u64,
+}
+
+/// Database the metadata is stored in.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Driver {
+    #[default]
+    Postgres,
+    /// `MySQL` or `MariaDB`, requires the `mysql` feature.
+    Mysql,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Ds {
+    #[serde(default)]
+    pub driver: Driver,
     pub host: String,
     pub port: u16,
     pub db: String,
@@ -17,4 +89,67 @@ pub struct Ds {
     pub min_connections: u32,
     pub test_before_acquire: bool,
     pub acquire_slow_threshold: u64,
+    /// Milliseconds writes wait for a pooled connection, 30 seconds when absent.
+    #[serde(default)]
+    pub acquire_timeout_ms: Option<u64>,
+    /// Milliseconds object metadata lookups (`GET`/`HEAD`) wait for a pooled connection,
+    /// the write timeout when absent.
+    #[serde(default)]
+    pub read_acquire_timeout_ms: Option<u64>,
+    /// Attempts made to connect at startup before giving up, 5 when absent.
+    #[serde(default)]
+    pub connect_max_attempts: Option<u32>,
+    /// Milliseconds waited before the first connection retry, doubled on every retry.
+    /// 500 when absent.
+    #[serde(default)]
+    pub connect_retry_base_delay_ms: Option<u64>,
+    /// Milliseconds a SQL statement may run before it is cancelled, 30 seconds when absent.
+    #[serde(default)]
+    pub statement_timeout_ms: Option<u64>,
+    /// Milliseconds maintenance queries, like the sweep for stale multipart uploads, may run.
+    /// 5 minutes when absent.
+    #[serde(default)]
+    pub admin_statement_timeout_ms: Option<u64>,
+}
+
+impl Ds {
+    #[must_use]
+    pub fn acquire_timeout(&self) -> Duration {
+        self.acquire_timeout_ms.map_or(
+            Duration::from_secs(CONNECTION_TIMEOUT),
+            Duration::from_millis,
+        )
+    }
+
+    #[must_use]
+    pub fn read_acquire_timeout(&self) -> Duration {
+        self.read_acquire_timeout_ms
+            .map_or_else(|| self.acquire_timeout(), Duration::from_millis)
+    }
+
+    #[must_use]
+    pub fn connect_max_attempts(&self) -> u32 {
+        self.connect_max_attempts.unwrap_or(CONNECT_ATTEMPTS).max(1)
+    }
+
+    #[must_use]
+    pub fn statement_timeout(&self) -> Duration {
+        Duration::from_millis(self.statement_timeout_ms.unwrap_or(STATEMENT_TIMEOUT_MS))
+    }
+
+    #[must_use]
+    pub fn admin_statement_timeout(&self) -> Duration {
+        Duration::from_millis(
+            self.admin_statement_timeout_ms
+                .unwrap_or(ADMIN_STATEMENT_TIMEOUT_MS),
+        )
+    }
+
+    #[must_use]
+    pub fn connect_retry_base_delay(&self) -> Duration {
+        Duration::from_millis(
+            self.connect_retry_base_delay_ms
+                .unwrap_or(CONNECT_RETRY_BASE_DELAY_MS),
+        )
+    }
 }
@@ -0,0 +1,145 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use hyper::{Request, Response, StatusCode, header};
+use tower::{Layer, Service};
+
+/// Rejects requests signed with AWS Signature Version 2 with `403 AccessDenied`.
+///
+/// s3s verifies version 2 signatures, from the `Authorization` header and from presigned
+/// URLs, against the same credentials as version 4 ones. They are weaker, HMAC-SHA1 over
+/// fewer parts of the request, so the server only accepts them with `--allow-sigv2`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenySigV2Layer;
+
+impl<S> Layer<S> for DenySigV2Layer {
+    type Service = DenySigV2Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        DenySigV2Service { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DenySigV2Service<S> {
+    inner: S,
+}
+
+/// Whether a request is signed with version 2, `AWS <key>:<signature>` or a presigned URL
+/// with `AWSAccessKeyId` and `Signature`.
+fn is_sig_v2<B>(req: &Request<B>) -> bool {
+    if let Some(authorization) = req.headers().get(header::AUTHORIZATION) {
+        return authorization.as_bytes().starts_with(b"AWS ");
+    }
+    let Some(query) = req.uri().query() else {
+        return false;
+    };
+    let has_param = |name: &str| {
+        query
+            .split('&')
+            .any(|pair| pair.split('=').next() == Some(name))
+    };
+    has_param("AWSAccessKeyId") && has_param("Signature")
+}
+
+fn reject<RB>() -> Response<RB>
+where
+    RB: From<String>,
+{
+    let body = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                <Error><Code>AccessDenied</Code>\
+                <Message>Signature Version 2 is not allowed, sign requests with AWS4-HMAC-SHA256.</Message></Error>"
+        .to_owned();
+
+    let mut res = Response::new(RB::from(body));
+    *res.status_mut() = StatusCode::FORBIDDEN;
+    res.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/xml"),
+    );
+    res
+}
+
+impl<S, B, RB> Service<Request<B>> for DenySigV2Service<S>
+where
+    S: Service<Request<B>, Response = Response<RB>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    RB: From<String> + Send + 'static,
+{
+    type Response = Response<RB>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if is_sig_v2(&req) {
+            let res = reject();
+            return Box::pin(async move { Ok(res) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{ServiceExt, service_fn};
+
+    use super::*;
+
+    async fn call(req: Request<()>) -> Response<String> {
+        let inner = service_fn(|_req: Request<()>| async {
+            Ok::<_, Infallible>(Response::new("s3".to_string()))
+        });
+        DenySigV2Layer.layer(inner).oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sig_v2_is_rejected() {
+        let req = Request::builder()
+            .uri("/bucket/key")
+            .header(header::AUTHORIZATION, "AWS AKID:c2lnbmF0dXJl")
+            .body(())
+            .unwrap();
+        let res = call(req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert!(res.body().contains("<Code>AccessDenied</Code>"));
+
+        let req = Request::builder()
+            .uri("/bucket/key?AWSAccessKeyId=AKID&Expires=1700000000&Signature=c2ln")
+            .body(())
+            .unwrap();
+        assert_eq!(call(req).await.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_sig_v4_passes() {
+        let req = Request::builder()
+            .uri("/bucket/key")
+            .header(
+                header::AUTHORIZATION,
+                "AWS4-HMAC-SHA256 Credential=AKID/20250101/us-east-1/s3/aws4_request, \
+                 SignedHeaders=host, Signature=0",
+            )
+            .body(())
+            .unwrap();
+        assert_eq!(call(req).await.body(), "s3");
+
+        let req = Request::builder()
+            .uri("/bucket/key?X-Amz-Credential=AKID%2F20250101&X-Amz-Signature=0")
+            .body(())
+            .unwrap();
+        assert_eq!(call(req).await.body(), "s3");
+
+        let req = Request::builder().uri("/bucket/key").body(()).unwrap();
+        assert_eq!(call(req).await.body(), "s3");
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+
+use aes::Aes256;
+use bytes::{Bytes, BytesMut};
+use ctr::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use md5::{Digest, Md5};
+use s3s::{S3Result, s3_error};
+
+use crate::storage_backend::InternalInfo;
+
+/// The only algorithm S3 accepts for customer-provided keys.
+pub const SSE_C_ALGORITHM: &str = "AES256";
+
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// A customer-provided encryption key (SSE-C), as sent in the
+/// `x-amz-server-side-encryption-customer-*` headers.
+///
+/// Only the base64 MD5 of the key is ever persisted.
+#[derive(Clone)]
+pub struct CustomerKey {
+    key: [u8; 32],
+    key_md5: String,
+}
+
+impl fmt::Debug for CustomerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomerKey")
+            .field("key_md5", &self.key_md5)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CustomerKey {
+    /// Parses the SSE-C request headers, `None` when the request does not use SSE-C.
+    pub fn from_headers(
+        algorithm: Option<&str>,
+        key: Option<&str>,
+        key_md5: Option<&str>,
+    ) -> S3Result<Option<Self>> {
+        let (algorithm, key, key_md5) = match (algorithm, key, key_md5) {
+            (None, None, None) => return Ok(None),
+            (Some(algorithm), Some(key), Some(key_md5)) => (algorithm, key, key_md5),
+            _ => {
+                return Err(s3_error!(
+                    InvalidArgument,
+                    "the SSE-C algorithm, key and key MD5 must be provided together"
+                ));
+            }
+        };
+        if algorithm != SSE_C_ALGORITHM {
+            return Err(s3_error!(InvalidEncryptionAlgorithmError));
+        }
+
+        let key = base64_simd::STANDARD
+            .decode_to_vec(key)
+            .ok()
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+            .ok_or_else(|| {
+                s3_error!(
+                    InvalidArgument,
+                    "the SSE-C key must be a base64 encoded 256-bit key"
+                )
+            })?;
+        let actual_md5 = base64_simd::STANDARD.encode_to_string(Md5::digest(key));
+        if actual_md5 != key_md5 {
+            return Err(s3_error!(
+                InvalidArgument,
+                "the SSE-C key MD5 does not match the key"
+            ));
+        }
+        Ok(Some(Self {
+            key,
+            key_md5: actual_md5,
+        }))
+    }
+
+    pub fn key_md5(&self) -> &str {
+        &self.key_md5
+    }
+
+    /// A keystream positioned at byte `offset` of the object, encryption and decryption
+    /// are the same operation.
+    pub fn cipher(&self, iv: &[u8; 16], offset: u64) -> ObjectCipher {
+        let mut cipher = Aes256Ctr::new(&self.key.into(), iv.into());
+        cipher.seek(offset);
+        ObjectCipher(cipher)
+    }
+}
+
+/// AES-256 in counter mode, applied chunk by chunk to an object body.
+pub struct ObjectCipher(Aes256Ctr);
+
+impl ObjectCipher {
+    pub fn apply(&mut self, bytes: &Bytes) -> Bytes {
+        let mut buf = BytesMut::from(bytes.as_ref());
+        self.0.apply_keystream(&mut buf);
+        buf.freeze()
+    }
+}
+
+/// SSE-C parameters of a stored object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseCInfo {
+    pub key_md5: String,
+    pub iv: [u8; 16],
+}
+
+pub fn modify_internal_info(info: &mut InternalInfo, key: &CustomerKey, iv: &[u8; 16]) {
+    info.insert(
+        "sse_customer_algorithm".to_owned(),
+        serde_json::Value::String(SSE_C_ALGORITHM.to_owned()),
+    );
+    info.insert(
+        "sse_customer_key_md5".to_owned(),
+        serde_json::Value::String(key.key_md5.clone()),
+    );
+    info.insert(
+        "sse_customer_iv".to_owned(),
+        serde_json::Value::String(base64_simd::STANDARD.encode_to_string(iv)),
+    );
+}
+
+pub fn from_internal_info(info: &InternalInfo) -> Option<SseCInfo> {
+    let get = |name: &str| info.get(name).and_then(|v| v.as_str());
+    let key_md5 = get("sse_customer_key_md5")?.to_owned();
+    let iv = base64_simd::STANDARD
+        .decode_to_vec(get("sse_customer_iv")?)
+        .ok()
+        .and_then(|iv| <[u8; 16]>::try_from(iv).ok())?;
+    Some(SseCInfo { key_md5, iv })
+}
+
+/// Checks the key sent with a read against the one the object was stored with.
+///
+/// Encrypted objects can only be read with the same key.
+/// Sending a key for an object stored without SSE-C is rejected as well.
+pub fn check_key(stored: Option<&SseCInfo>, key: Option<&CustomerKey>) -> S3Result<()> {
+    match (stored, key) {
+        (None, None) => Ok(()),
+        (Some(stored), Some(key)) if stored.key_md5 == key.key_md5 => Ok(()),
+        (Some(_), _) => Err(s3_error!(
+            AccessDenied,
+            "the object was stored with a different SSE-C key"
+        )),
+        (None, Some(_)) => Err(s3_error!(
+            InvalidRequest,
+            "the object was not stored with SSE-C"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded_key(key: &[u8; 32]) -> (String, String) {
+        (
+            base64_simd::STANDARD.encode_to_string(key),
+            base64_simd::STANDARD.encode_to_string(Md5::digest(key)),
+        )
+    }
+
+    #[test]
+    fn test_from_headers() {
+        assert!(
+            CustomerKey::from_headers(None, None, None)
+                .unwrap()
+                .is_none()
+        );
+
+        let (key, key_md5) = encoded_key(&[7; 32]);
+        let parsed = CustomerKey::from_headers(Some("AES256"), Some(&key), Some(&key_md5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.key_md5(), key_md5);
+
+        let err =
+            CustomerKey::from_headers(Some("aws:kms"), Some(&key), Some(&key_md5)).unwrap_err();
+        assert_eq!(
+            *err.code(),
+            s3s::S3ErrorCode::InvalidEncryptionAlgorithmError
+        );
+
+        let (_, other_md5) = encoded_key(&[8; 32]);
+        assert!(CustomerKey::from_headers(Some("AES256"), Some(&key), Some(&other_md5)).is_err());
+        assert!(CustomerKey::from_headers(Some("AES256"), Some(&key), None).is_err());
+        assert!(
+            CustomerKey::from_headers(Some("AES256"), Some("c2hvcnQ="), Some(&key_md5)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_cipher_round_trip_at_offset() {
+        let (key, key_md5) = encoded_key(&[7; 32]);
+        let key = CustomerKey::from_headers(Some("AES256"), Some(&key), Some(&key_md5))
+            .unwrap()
+            .unwrap();
+        let iv = [1; 16];
+        let plain = Bytes::from_static(b"the quick brown fox jumps over the lazy dog");
+
+        let encrypted = key.cipher(&iv, 0).apply(&plain);
+        assert_ne!(encrypted, plain);
+        assert_eq!(key.cipher(&iv, 0).apply(&encrypted), plain);
+        // a range read decrypts from the middle of the object
+        assert_eq!(
+            key.cipher(&iv, 10).apply(&encrypted.slice(10..)),
+            plain.slice(10..)
+        );
+    }
+
+    #[test]
+    fn test_internal_info_round_trip_and_check_key() {
+        let (encoded, key_md5) = encoded_key(&[7; 32]);
+        let key = CustomerKey::from_headers(Some("AES256"), Some(&encoded), Some(&key_md5))
+            .unwrap()
+            .unwrap();
+        let mut info = InternalInfo::new();
+        modify_internal_info(&mut info, &key, &[3; 16]);
+
+        let stored = from_internal_info(&info).unwrap();
+        assert_eq!(stored.iv, [3; 16]);
+        assert!(check_key(Some(&stored), Some(&key)).is_ok());
+        assert!(check_key(Some(&stored), None).is_err());
+        assert!(check_key(None, Some(&key)).is_err());
+        assert!(check_key(None, None).is_ok());
+        assert!(from_internal_info(&InternalInfo::new()).is_none());
+    }
+}
@@ -1,38 +1,250 @@
 use std::{
-    env,
+    collections::BTreeSet,
+    env, io,
+    num::NonZeroUsize,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
+    time::Duration,
 };
 
+use bytes::Bytes;
+use chrono::NaiveDateTime;
+use futures::{Stream, TryStreamExt};
 use md5::{Digest, Md5};
 use s3s::{
-    S3Result,
+    S3, S3ErrorCode, S3Request, S3Result, StdError,
     auth::Credentials,
-    dto::{self, PartNumber},
+    dto::{
+        self, BucketVersioningStatus, ObjectOwnership, PartNumber,
+        ServerSideEncryptionConfiguration, StreamingBlob,
+    },
+    host::MultiDomain,
     s3_error,
 };
 use tokio::{
     fs,
     fs::File,
-    io::{AsyncReadExt, BufWriter},
+    io::{AsyncSeekExt, AsyncWriteExt, BufWriter},
 };
 use tracing::{debug, info, warn};
+use uuid::Uuid;
 
 use crate::{
-    DataStore, MultipartUpload, MultipartUploadPart, S3ItemDetail,
+    DataStore, MultipartUpload, MultipartUploadPart, S3Bucket, S3ItemDetail, acl,
+    cache::{Caches, ItemCache},
+    content_headers::{self, ContentHeaders},
+    datastore::MAX_QUERY_SIZE,
     error::*,
+    lifecycle::{self, ExpirationRule},
+    multipart_assembly::{DirectAssembly, assembly_file_name, assembly_part_size},
+    object_lock, object_parts, server_side_encryption,
+    settings::Layout,
+    sse_c,
+    transform::ObjectTransform,
     utils::{self, hex, resolve_abs_path},
 };
 
+/// Buckets that may exist at once, the default quota of an AWS account.
+pub const DEFAULT_MAX_BUCKETS: u64 = 100;
+
+/// Largest object or part a single copy may produce, as on AWS.
+pub const DEFAULT_MAX_COPY_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Objects listed at a time when looking for expired ones.
+const EXPIRE_PAGE_SIZE: usize = 500;
+
+/// Objects listed at a time when exporting a bucket.
+const EXPORT_PAGE_SIZE: usize = 500;
+
 #[derive(Debug)]
 pub struct StorageBackend<T: DataStore> {
     pub(crate) root: PathBuf,
-    tmp_file_counter: AtomicU64,
+    item_cache: Option<Arc<ItemCache>>,
+    pub(crate) auto_create_buckets: bool,
+    pub(crate) domains: Vec<String>,
+    pub(crate) region: Option<String>,
+    pub(crate) direct_assembly: Option<DirectAssembly>,
+    pub(crate) read_only: bool,
+    pub(crate) durable: bool,
+    pub(crate) max_copy_size: u64,
+    pub(crate) max_buckets: u64,
+    pub(crate) layout: Layout,
+    pub(crate) transform: Option<Arc<dyn ObjectTransform>>,
     pub datastore: T,
 }
 
 pub(crate) type InternalInfo = serde_json::Map<String, serde_json::Value>;
 
+/// Key order of [`StorageBackend::list_objects_page`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// One page of a [`StorageBackend::list_objects_page`] listing.
+#[derive(Debug)]
+pub struct ObjectPage {
+    pub items: Vec<S3ItemDetail>,
+    /// Opaque token to request the next page with, `None` on the last page.
+    pub next_continuation_token: Option<String>,
+}
+
+/// Continuation tokens are the last key or common prefix of a page, opaque to clients.
+pub(crate) fn encode_continuation_token(key: &str) -> String {
+    base64_simd::URL_SAFE_NO_PAD.encode_to_string(key)
+}
+
+/// Returns `None` for tokens that were not handed out by [`encode_continuation_token`].
+pub(crate) fn decode_continuation_token(token: &str) -> Option<String> {
+    base64_simd::URL_SAFE_NO_PAD
+        .decode_to_vec(token)
+        .ok()
+        .and_then(|key| String::from_utf8(key).ok())
+}
+
+/// One page of a listing grouping keys by delimiter, see [`StorageBackend::list_grouped_page`].
+#[derive(Debug)]
+pub(crate) struct GroupedPage {
+    pub(crate) items: Vec<S3ItemDetail>,
+    /// Prefixes the other keys of the page are grouped under, in key order.
+    pub(crate) common_prefixes: Vec<String>,
+    pub(crate) next_continuation_token: Option<String>,
+}
+
+/// The common prefix `key` is grouped under, the part of it up to and including the first
+/// `delimiter` after `prefix`, `None` when it is listed on its own.
+fn common_prefix<'a>(prefix: &str, delimiter: &str, key: &'a str) -> Option<&'a str> {
+    let end = prefix.len() + key.strip_prefix(prefix)?.find(delimiter)? + delimiter.len();
+    Some(&key[..end])
+}
+
+/// Where a listing resumes after `last_listed`, past every key grouped under it when it is
+/// a common prefix.
+fn resume_after(prefix: &str, delimiter: Option<&str>, last_listed: &str) -> String {
+    match delimiter {
+        Some(delimiter) if common_prefix(prefix, delimiter, last_listed) == Some(last_listed) => {
+            format!("{last_listed}{}", char::MAX)
+        }
+        _ => last_listed.to_owned(),
+    }
+}
+
+/// Directory under the root holding the data of versioned objects
+const VERSIONS_DIR: &str = ".versions";
+
+/// Directory under the root holding the data of objects whose key cannot be a path
+const HASHED_DIR: &str = ".hashed";
+
+/// Longest file name common file systems accept, in bytes
+const NAME_MAX: usize = 255;
+
+/// data location of an object relative to the root.
+/// Every version gets its own file, objects without a version live at `bucket/key`.
+///
+/// A key with a segment longer than a file name may be is stored under the hash of the
+/// key instead, the datastore keeps the key itself for listings.
+///
+/// With [`Layout::Sharded`] every object is stored under the hash of its key, in two levels
+/// of directories named after its first bytes: `.hashed/bucket/ab/cd/abcd...` and
+/// `.versions/bucket/ab/cd/<version id>`. They are kept apart from the flat layout, a key
+/// like `ab` stored before the switch would otherwise be in the way.
+pub(crate) fn data_location(
+    layout: Layout,
+    bucket: &str,
+    key: &str,
+    version_id: Option<&str>,
+) -> String {
+    if layout == Layout::Sharded {
+        let hash = hex(Md5::digest(key));
+        let shard = format!("{}/{}", &hash[..2], &hash[2..4]);
+        return match version_id {
+            Some(version_id) => format!("{VERSIONS_DIR}/{bucket}/{shard}/{version_id}"),
+            None => format!("{HASHED_DIR}/{bucket}/{shard}/{hash}"),
+        };
+    }
+    if let Some(version_id) = version_id {
+        return format!("{VERSIONS_DIR}/{bucket}/{version_id}");
+    }
+    let path = key_to_path(key);
+    if path.split('/').any(|segment| segment.len() > NAME_MAX) {
+        format!("{HASHED_DIR}/{bucket}/{}", hex(Md5::digest(key)))
+    } else {
+        bucket.to_string() + "/" + &path
+    }
+}
+
+/// Maps a key to its relative file path.
+///
+/// S3 keys are opaque, `a/./b` and `a/../b` are distinct keys and must not be collapsed
+/// by path resolution. `.` and `..` segments are written as `%2E` and `%2E%2E`, and a
+/// leading `%` of any other segment is escaped as `%25` so the mapping stays one to one.
+///
+/// A leading slash is part of the key, `/a` and `a` are distinct too. The empty segment
+/// before it is written as `%2F`, only the trailing one of a directory marker is kept.
+fn key_to_path(key: &str) -> String {
+    let segments = key.split('/').collect::<Vec<_>>();
+    let last = segments.len() - 1;
+    segments
+        .into_iter()
+        .enumerate()
+        .map(|(i, segment)| match segment {
+            "" if i < last => "%2F".into(),
+            "." => "%2E".into(),
+            ".." => "%2E%2E".into(),
+            s if s.starts_with('%') => format!("%25{}", &s[1..]),
+            s => s.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether a key is written as the same relative path, without `.`, `..` or empty segments
+/// other than the trailing one of a directory marker.
+fn is_plain_path(key: &str) -> bool {
+    let key = key.strip_suffix('/').unwrap_or(key);
+    !key.is_empty()
+        && key
+            .split('/')
+            .all(|s| !s.is_empty() && s != "." && s != ".." && s.len() <= NAME_MAX)
+}
+
+/// The regular files under `dir`, sorted. Symbolic links and other special files are
+/// skipped with a warning.
+async fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            } else {
+                warn!(path = %entry.path().display(), "not a regular file, skipped");
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Moves a file, copying it when it is on another file system, and returns its length.
+async fn move_file(from: &Path, to: &Path) -> io::Result<u64> {
+    match fs::rename(from, to).await {
+        Ok(()) => Ok(fs::metadata(to).await?.len()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            let len = fs::copy(from, to).await?;
+            fs::remove_file(from).await?;
+            Ok(len)
+        }
+        Err(e) => Err(e),
+    }
+}
+
 fn clean_old_tmp_files(root: &Path) -> std::io::Result<()> {
     let entries = match std::fs::read_dir(root) {
         Ok(entries) => Ok(entries),
@@ -53,18 +265,212 @@ fn clean_old_tmp_files(root: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Removes a directory holding nothing but empty directories, like the shards left by the
+/// deleted objects of a bucket. Fails with `DirectoryNotEmpty` when a file is found.
+pub(crate) async fn remove_empty_dirs(path: &Path) -> io::Result<()> {
+    let mut entries = match fs::read_dir(path).await {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        entries => entries?,
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            Box::pin(remove_empty_dirs(&entry.path())).await?;
+        }
+    }
+    fs::remove_dir(path).await
+}
+
+/// Removes the multipart uploads initiated before `older_than` together with their part
+/// files, returning how many were removed.
+///
+/// Uploads that were aborted by a crash or never completed otherwise stay around forever.
+pub async fn clean_stale_multipart_uploads<T: DataStore>(
+    datastore: &T,
+    older_than: NaiveDateTime,
+) -> Result<usize> {
+    let uploads = datastore.get_stale_multipart_uploads(older_than).await?;
+    for upload in &uploads {
+        let parts = datastore.get_parts_by_upload_id(&upload.upload_id).await?;
+        // parts written directly share the assembly file of the upload
+        let data_locations = parts
+            .into_iter()
+            .map(|part| part.data_location)
+            .collect::<BTreeSet<_>>();
+        for data_location in data_locations {
+            match fs::remove_file(&data_location).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        // the parts go with the upload
+        datastore
+            .delete_multipart_upload_by_upload_id(&upload.upload_id)
+            .await?;
+        info!(
+            bucket = %upload.bucket,
+            key = %upload.key,
+            upload_id = %upload.upload_id,
+            initiated = %upload.last_modified,
+            "stale multipart upload removed"
+        );
+    }
+    Ok(uploads.len())
+}
+
 impl<T: DataStore> StorageBackend<T> {
     pub fn new(root: impl AsRef<Path>, datastore: T) -> Result<Self> {
         let root = env::current_dir()?.join(root).canonicalize()?;
         clean_old_tmp_files(&root)?;
-        let tmp_file_counter = AtomicU64::new(0);
         Ok(Self {
             root,
-            tmp_file_counter,
+            item_cache: None,
+            auto_create_buckets: false,
+            domains: Vec::new(),
+            region: None,
+            direct_assembly: None,
+            read_only: false,
+            durable: false,
+            max_copy_size: DEFAULT_MAX_COPY_SIZE,
+            max_buckets: DEFAULT_MAX_BUCKETS,
+            layout: Layout::default(),
+            transform: None,
             datastore,
         })
     }
 
+    /// Lets `PutObject` create a missing bucket instead of failing with `NoSuchBucket`.
+    #[must_use]
+    pub fn with_auto_create_buckets(mut self, enabled: bool) -> Self {
+        self.auto_create_buckets = enabled;
+        self
+    }
+
+    /// Domain names of virtual-hosted-style requests, URLs in responses address the
+    /// bucket the same way the request did.
+    #[must_use]
+    pub fn with_domains(mut self, domains: Vec<String>) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// Routing of virtual-hosted-style requests to their bucket for the S3 service,
+    /// `None` without domains.
+    pub fn virtual_hosts(&self) -> Result<Option<MultiDomain>> {
+        if self.domains.is_empty() {
+            return Ok(None);
+        }
+        MultiDomain::new(&self.domains)
+            .map(Some)
+            .map_err(|e| Error::from_string(format!("invalid domain: {e}")))
+    }
+
+    /// Region reported as the location of every bucket, the only one buckets may be
+    /// created in when set.
+    #[must_use]
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Writes multipart parts arriving in order straight into the final file,
+    /// see [`DirectAssembly`].
+    #[must_use]
+    pub fn with_direct_assembly(mut self, enabled: bool) -> Self {
+        self.direct_assembly = enabled.then(DirectAssembly::default);
+        self
+    }
+
+    /// Syncs written files and their directory to disk before a write is acknowledged, so
+    /// a stored object survives a crash or power loss. Off by default, it costs throughput.
+    #[must_use]
+    pub fn with_durable(mut self, enabled: bool) -> Self {
+        self.durable = enabled;
+        self
+    }
+
+    /// Rejects every mutating request with `AccessDenied`, reads keep being served.
+    #[must_use]
+    pub fn with_read_only(mut self, enabled: bool) -> Self {
+        self.read_only = enabled;
+        self
+    }
+
+    /// Largest range a single copy may produce, larger ones fail with `EntityTooLarge`.
+    #[must_use]
+    pub fn with_max_copy_size(mut self, max_copy_size: u64) -> Self {
+        self.max_copy_size = max_copy_size;
+        self
+    }
+
+    /// Buckets that may exist at once, creating more fails with `TooManyBuckets`.
+    /// 0 lifts the limit.
+    #[must_use]
+    pub fn with_max_buckets(mut self, max_buckets: u64) -> Self {
+        self.max_buckets = max_buckets;
+        self
+    }
+
+    /// Where the data of new objects is written, see [`Layout`]. Objects stored before are
+    /// read from where they are until they are relocated or overwritten.
+    #[must_use]
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Passes the body of the objects served by `GetObject` through `transform`.
+    #[must_use]
+    pub fn with_transform(mut self, transform: impl ObjectTransform) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// Caches the latest metadata of up to `capacity` objects for `ttl`,
+    /// and missing objects for `negative_ttl` unless it is zero.
+    ///
+    /// Lookups of a specific version always go to the datastore.
+    #[must_use]
+    pub fn with_item_cache(
+        mut self,
+        capacity: NonZeroUsize,
+        ttl: Duration,
+        negative_ttl: Duration,
+    ) -> Self {
+        self.item_cache = Some(Arc::new(ItemCache::new(capacity, ttl, negative_ttl)));
+        self
+    }
+
+    /// Handle to flush the in-memory caches from outside the S3 service.
+    #[must_use]
+    pub fn caches(&self) -> Caches {
+        Caches {
+            items: self.item_cache.clone(),
+        }
+    }
+
+    /// Shares the in-memory caches of another backend, its entries are then invalidated
+    /// by the writes of this one too.
+    #[must_use]
+    pub fn with_caches(mut self, caches: &Caches) -> Self {
+        self.item_cache.clone_from(&caches.items);
+        self
+    }
+
+    fn invalidate_cached_item(&self, bucket: &str, key: &str) {
+        if let Some(cache) = &self.item_cache {
+            cache.invalidate(bucket, key);
+        }
+    }
+
+    /// Fails mutating requests when the backend was made read-only.
+    pub(crate) fn check_writable(&self) -> S3Result<()> {
+        if self.read_only {
+            return Err(s3_error!(AccessDenied, "server is read-only"));
+        }
+        Ok(())
+    }
+
     /// Validates an S3 key according to S3 specifications
     ///
     /// S3 key validation rules:
@@ -81,8 +487,9 @@ impl<T: DataStore> StorageBackend<T> {
             return false;
         }
 
-        // Look for unsafe characters - notably "../" path traversal attempts
-        if key.contains("../") || key.contains("./") || key.contains("//") {
+        // `.` and `..` segments are literal, see `key_to_path`. Empty segments have no
+        // file system representation.
+        if key.contains("//") {
             warn!(key = %key, "S3 key contains potentially unsafe path sequences");
             return false;
         }
@@ -110,11 +517,26 @@ impl<T: DataStore> StorageBackend<T> {
         )
     }
 
+    pub(crate) fn resolve_assembly_path(&self, upload_id: &str, part_size: u64) -> Result<PathBuf> {
+        resolve_abs_path(&self.root, assembly_file_name(upload_id, part_size))
+    }
+
     /// resolve object path under the virtual root
     pub(crate) fn get_object_path(&self, bucket: &str, key: &str) -> Result<PathBuf> {
-        let dir = Path::new(&bucket);
-        let file_path = Path::new(&key);
-        resolve_abs_path(&self.root, dir.join(file_path))
+        resolve_abs_path(&self.root, data_location(self.layout, bucket, key, None))
+    }
+
+    /// resolve the path of an object version under the virtual root
+    pub(crate) fn get_object_version_path(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<PathBuf> {
+        resolve_abs_path(
+            &self.root,
+            data_location(self.layout, bucket, key, version_id),
+        )
     }
 
     /// resolve bucket path under the virtual root
@@ -123,20 +545,25 @@ impl<T: DataStore> StorageBackend<T> {
         resolve_abs_path(&self.root, dir)
     }
 
+    /// resolve the directory holding the versioned data of a bucket
+    pub(crate) fn get_bucket_versions_path(&self, bucket: &str) -> Result<PathBuf> {
+        resolve_abs_path(&self.root, Path::new(VERSIONS_DIR).join(bucket))
+    }
+
+    /// resolve the directory holding the data of a bucket stored under hashed keys
+    pub(crate) fn get_bucket_hashed_path(&self, bucket: &str) -> Result<PathBuf> {
+        resolve_abs_path(&self.root, Path::new(HASHED_DIR).join(bucket))
+    }
+
     /// get md5 sum
-    pub(crate) async fn get_md5_sum(&self, bucket: &str, key: &str) -> Result<String> {
-        let object_path = self.get_object_path(bucket, key)?;
-        let mut file = File::open(&object_path).await?;
-        let mut buf = vec![0; 65536];
-        let mut md5_hash = Md5::new();
-        loop {
-            let nread = file.read(&mut buf).await?;
-            if nread == 0 {
-                break;
-            }
-            md5_hash.update(&buf[..nread]);
-        }
-        Ok(hex(md5_hash.finalize()))
+    pub(crate) async fn get_md5_sum(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<String> {
+        let object_path = self.get_object_version_path(bucket, key, version_id)?;
+        utils::md5_sum(&object_path).await
     }
 
     /// verify access key by upload id taken from the database
@@ -158,9 +585,11 @@ impl<T: DataStore> StorageBackend<T> {
     /// This is done by first writing to a temporary location and then moving
     /// the file.
     pub(crate) async fn prepare_file_write<'a>(&self, path: &'a Path) -> Result<FileWriter<'a>> {
+        // unique across restarts and other processes writing to the same root
         let tmp_name = format!(
-            ".tmp.{}.internal.part",
-            self.tmp_file_counter.fetch_add(1, Ordering::SeqCst)
+            ".tmp.{}.{}.internal.part",
+            std::process::id(),
+            Uuid::new_v4().simple()
         );
         let tmp_path = resolve_abs_path(&self.root, tmp_name)?;
         let file = File::create(&tmp_path).await?;
@@ -170,9 +599,85 @@ impl<T: DataStore> StorageBackend<T> {
             dest_path: path,
             writer,
             clean_tmp: true,
+            durable: self.durable,
         })
     }
 
+    /// Writes `stream` at `offset` of the file at `path`, which is created sparse if missing.
+    pub(crate) async fn write_at<S>(&self, path: &Path, offset: u64, stream: S) -> Result<u64>
+    where
+        S: Stream<Item = std::result::Result<Bytes, StdError>> + Unpin,
+    {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .await?;
+        file.seek(io::SeekFrom::Start(offset)).await?;
+        let mut writer = BufWriter::new(file);
+        let size = utils::copy_bytes(stream, &mut writer).await?;
+        self.sync_data_file(path).await?;
+        Ok(size)
+    }
+
+    /// Syncs a data file written in place, copied or renamed to `path`, and the directory
+    /// holding it to disk when writes are durable, see [`Self::with_durable`].
+    pub(crate) async fn sync_data_file(&self, path: &Path) -> io::Result<()> {
+        if !self.durable {
+            return Ok(());
+        }
+        File::open(path).await?.sync_all().await?;
+        match path.parent() {
+            Some(dir) => sync_dir(dir).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Rejects a key whose path collides with an object already stored in the bucket.
+    ///
+    /// Unversioned objects are files along their key and directory markers are directories,
+    /// so `a` and `a/b` cannot both be stored: one needs `a` as a file, the other as a
+    /// directory. Keys never collide in the sharded layout.
+    pub(crate) async fn check_key_collision(&self, bucket: &str, key: &str) -> S3Result<()> {
+        if self.layout == Layout::Sharded {
+            return Ok(());
+        }
+        let bucket_path = self.get_bucket_path(bucket)?;
+        let object_path = self.get_object_path(bucket, key)?;
+        for dir in object_path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&bucket_path) && *dir != bucket_path)
+        {
+            if fs::metadata(dir).await.is_ok_and(|m| m.is_file()) {
+                let prefix = dir.strip_prefix(&bucket_path).unwrap_or(dir);
+                return Err(s3_error!(
+                    InvalidRequest,
+                    "the key {key} cannot be stored, the object {} is in the way",
+                    prefix.display()
+                ));
+            }
+        }
+        let Ok(metadata) = fs::metadata(&object_path).await else {
+            return Ok(());
+        };
+        if key.ends_with('/') && metadata.is_file() {
+            return Err(s3_error!(
+                InvalidRequest,
+                "the key {key} cannot be stored, the object {} is in the way",
+                key.trim_end_matches('/')
+            ));
+        }
+        if !key.ends_with('/') && metadata.is_dir() {
+            return Err(s3_error!(
+                InvalidRequest,
+                "the key {key} cannot be stored, objects with the prefix {key}/ exist"
+            ));
+        }
+        Ok(())
+    }
+
     pub(crate) async fn handle_directory_creation(
         &self,
         content_length: Option<i64>,
@@ -198,25 +703,48 @@ impl<T: DataStore> StorageBackend<T> {
         e_tag: &str,
         metadata: Option<&dto::Metadata>,
         internal_info: InternalInfo,
+        version_id: Option<&str>,
     ) -> Result<()> {
         // Validate the key before saving
         if !self.validate_s3_key(key) {
-            return Err(Error::from_string("Invalid S3 key format"));
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                "Invalid S3 key format",
+            ));
         }
 
         let internal_info_str = serde_json::to_string(&internal_info)?;
         let metadata_str = utils::metadata_to_string(metadata);
-        let path = bucket.to_string() + "/" + key;
+        let path = data_location(self.layout, bucket, key, version_id);
 
         let item = S3ItemDetail::builder()
             .bucket(bucket.to_string())
             .key(key.to_string())
+            .version_id(version_id.map(str::to_string))
             .e_tag(e_tag.to_string())
             .metadata(Some(metadata_str))
             .internal_info(Some(internal_info_str))
             .data_location(path)
             .build();
-        self.datastore.save_s3_item_detail(&item).await
+        self.datastore.save_s3_item_detail(&item).await?;
+        self.invalidate_cached_item(bucket, key);
+
+        // an object stored before the layout changed leaves its old data behind
+        if version_id.is_none() && !key.ends_with('/') {
+            let other_layout = match self.layout {
+                Layout::Flat => Layout::Sharded,
+                Layout::Sharded => Layout::Flat,
+            };
+            let previous = data_location(other_layout, bucket, key, None);
+            // a directory there holds the objects under `key/`, not the data of `key`
+            let is_file = fs::symlink_metadata(resolve_abs_path(&self.root, &previous)?)
+                .await
+                .is_ok_and(|metadata| metadata.is_file());
+            if is_file && previous != item.data_location {
+                self.remove_data_file(&previous).await?;
+            }
+        }
+        Ok(())
     }
 
     pub(crate) async fn get_s3_item_detail(
@@ -224,10 +752,158 @@ impl<T: DataStore> StorageBackend<T> {
         bucket: &str,
         key: &str,
     ) -> Result<Option<S3ItemDetail>> {
-        self.datastore.get_s3_item_detail(bucket, key).await
+        let Some(cache) = &self.item_cache else {
+            return self.datastore.get_s3_item_detail(bucket, key).await;
+        };
+        if let Some(item) = cache.get(bucket, key) {
+            debug!(bucket, key, "item cache hit");
+            return Ok(item);
+        }
+        let item = self.datastore.get_s3_item_detail(bucket, key).await?;
+        cache.insert(bucket, key, item.as_ref());
+        Ok(item)
+    }
+
+    /// Checks the `If-Match` and `If-None-Match` preconditions of a write against the
+    /// latest version of the key, before anything is written.
+    pub(crate) async fn check_write_conditions(
+        &self,
+        bucket: &str,
+        key: &str,
+        if_match: Option<&str>,
+        if_none_match: Option<&str>,
+    ) -> S3Result<()> {
+        if if_match.is_none() && if_none_match.is_none() {
+            return Ok(());
+        }
+        let current = self
+            .get_s3_item_detail(bucket, key)
+            .await?
+            .filter(|d| !d.delete_marker);
+        utils::check_write_conditions(
+            current.as_ref().map(|d| d.e_tag.as_str()),
+            if_match,
+            if_none_match,
+        )
     }
 
-    pub(crate) async fn get_s3_item_detail_with_filter(
+    /// Rewrites the internal info of a stored item, everything else is kept as is.
+    pub(crate) async fn update_internal_info(
+        &self,
+        mut item: S3ItemDetail,
+        internal_info: &InternalInfo,
+    ) -> Result<()> {
+        item.internal_info = serde_json::to_string(internal_info)?;
+        self.datastore.save_s3_item_detail(&item).await?;
+        self.invalidate_cached_item(&item.bucket, &item.key);
+        Ok(())
+    }
+
+    /// Saves a delete marker, hiding the object while keeping its prior versions.
+    pub(crate) async fn save_delete_marker(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<()> {
+        let item = S3ItemDetail::builder()
+            .bucket(bucket.to_string())
+            .key(key.to_string())
+            .version_id(Some(version_id.to_string()))
+            .e_tag(String::new())
+            .metadata(Some("{}".to_string()))
+            .internal_info(Some("{}".to_string()))
+            .data_location(String::new())
+            .delete_marker(true)
+            .build();
+        self.datastore.save_s3_item_detail(&item).await?;
+        self.invalidate_cached_item(bucket, key);
+        Ok(())
+    }
+
+    pub(crate) async fn delete_s3_item_detail_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+    ) -> Result<()> {
+        self.datastore
+            .delete_s3_item_detail_version(bucket, key, version_id)
+            .await?;
+        self.invalidate_cached_item(bucket, key);
+        Ok(())
+    }
+
+    /// Removes the data of a deleted object version.
+    /// Failures are only logged since the version is no longer tracked.
+    pub(crate) async fn remove_data_file(&self, data_location: &str) -> Result<()> {
+        let path = resolve_abs_path(&self.root, data_location)?;
+        let result = if path.is_dir() {
+            fs::remove_dir(&path).await
+        } else {
+            fs::remove_file(&path).await
+        };
+        if let Err(e) = result
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!(error = %e, path = %path.display(), "failed to remove object data");
+        }
+        Ok(())
+    }
+
+    /// Refuses the permanent deletion of a version under legal hold or still under retention.
+    pub(crate) async fn check_retention(
+        &self,
+        item: &S3ItemDetail,
+        bypass_governance: bool,
+        access_key: Option<&str>,
+    ) -> S3Result<()> {
+        let info: InternalInfo = serde_json::from_str(&item.internal_info).unwrap_or_default();
+        object_lock::check_legal_hold(&info)?;
+        let retention = object_lock::from_internal_info(&info);
+        let now = chrono::Utc::now().naive_utc();
+        if object_lock::check_delete(retention.as_ref(), now, false).is_ok() {
+            return Ok(());
+        }
+        let may_bypass =
+            bypass_governance && self.may_bypass_governance(&item.bucket, access_key).await?;
+        object_lock::check_delete(retention.as_ref(), now, may_bypass)
+    }
+
+    /// Only the bucket owner may bypass `GOVERNANCE` retention,
+    /// any authenticated caller when the bucket predates owner tracking.
+    pub(crate) async fn may_bypass_governance(
+        &self,
+        bucket: &str,
+        access_key: Option<&str>,
+    ) -> Result<bool> {
+        Ok(match self.datastore.get_bucket(bucket).await? {
+            Some(S3Bucket {
+                owner: Some(owner), ..
+            }) => access_key == Some(owner.as_str()),
+            _ => access_key.is_some(),
+        })
+    }
+
+    /// Looks up the requested version of an object, or its latest version when none is given.
+    pub(crate) async fn get_object_detail(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Option<S3ItemDetail>> {
+        match version_id {
+            Some(version_id) => {
+                self.datastore
+                    .get_s3_item_detail_version(bucket, key, version_id)
+                    .await
+            }
+            None => self.get_s3_item_detail(bucket, key).await,
+        }
+    }
+
+    /// Lists every version of the objects under `filter` in one go, without paging.
+    pub async fn get_s3_item_detail_with_filter(
         &self,
         bucket: &str,
         filter: &str,
@@ -237,26 +913,714 @@ impl<T: DataStore> StorageBackend<T> {
             .await
     }
 
-    pub(crate) async fn get_all_buckets(&self) -> Result<Vec<String>> {
-        self.datastore.get_all_buckets().await
+    pub(crate) async fn get_s3_item_versions_with_filter(
+        &self,
+        bucket: &str,
+        filter: &str,
+    ) -> Result<Vec<S3ItemDetail>> {
+        self.datastore
+            .get_s3_item_versions_with_filter(bucket, filter)
+            .await
     }
 
-    pub(crate) async fn save_multipart_upload(
+    /// Lists up to `limit` versions under `prefix` following the version
+    /// `version_id_marker` of `key_marker`, newest first within each key.
+    pub(crate) async fn list_versions_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<S3ItemDetail>> {
+        self.datastore
+            .list_versions_page(
+                bucket,
+                prefix,
+                key_marker.unwrap_or_default(),
+                version_id_marker.unwrap_or_default(),
+                limit,
+            )
+            .await
+    }
+
+    /// Lists the latest version of the objects under `prefix`, one page at a time.
+    ///
+    /// Unlike the S3 listings this supports walking the keys backwards, for UIs paging in reverse.
+    /// The continuation token of a page only continues a listing in the same order.
+    pub async fn list_objects_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        order: ListOrder,
+        continuation_token: Option<&str>,
+        max_keys: usize,
+    ) -> Result<ObjectPage> {
+        let start_after = continuation_token
+            .map(|token| {
+                decode_continuation_token(token).ok_or_else(|| {
+                    Error::with_message(ErrorKind::InvalidInput, "invalid continuation token")
+                })
+            })
+            .transpose()?;
+        self.list_latest_page(bucket, prefix, order, start_after.as_deref(), max_keys)
+            .await
+    }
+
+    /// Lists up to `max_keys` objects under `prefix` following the key `start_after`.
+    pub(crate) async fn list_latest_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        order: ListOrder,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<ObjectPage> {
+        let limit = i32::try_from(max_keys).unwrap_or(MAX_QUERY_SIZE);
+        // one more row tells whether there is a next page
+        let mut items = self
+            .datastore
+            .list_latest_items_page(
+                bucket,
+                prefix,
+                start_after.unwrap_or_default(),
+                order == ListOrder::Descending,
+                limit.saturating_add(1),
+            )
+            .await?;
+
+        let next_continuation_token = if items.len() > max_keys {
+            items.truncate(max_keys);
+            // an empty page continues where it started
+            let last_key = items
+                .last()
+                .map_or(start_after.unwrap_or_default(), |item| item.key.as_str());
+            Some(encode_continuation_token(last_key))
+        } else {
+            None
+        };
+        Ok(ObjectPage {
+            items,
+            next_continuation_token,
+        })
+    }
+
+    /// Lists up to `max_keys` objects and common prefixes under `prefix` following
+    /// `start_after`, the keys sharing a prefix up to `delimiter` grouped under it.
+    ///
+    /// A group counts once towards `max_keys`, and the next query starts past its keys so
+    /// large groups are not read through.
+    pub(crate) async fn list_grouped_page(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        delimiter: Option<&str>,
+        start_after: Option<&str>,
+        max_keys: usize,
+    ) -> Result<GroupedPage> {
+        let delimiter = delimiter.filter(|d| !d.is_empty());
+        let mut items = Vec::new();
+        let mut common_prefixes: Vec<String> = Vec::new();
+        let mut last_listed = start_after.map(str::to_owned);
+        let mut last_grouped = false;
+        let truncated = loop {
+            let after = last_listed
+                .as_deref()
+                .map(|last| resume_after(prefix, delimiter, last));
+            let wanted = max_keys - items.len() - common_prefixes.len();
+            let page = self
+                .list_latest_page(
+                    bucket,
+                    prefix,
+                    ListOrder::Ascending,
+                    after.as_deref(),
+                    wanted,
+                )
+                .await?;
+            let more = page.next_continuation_token.is_some();
+            if wanted == 0 {
+                break more;
+            }
+            for item in page.items {
+                match delimiter.and_then(|d| common_prefix(prefix, d, &item.key)) {
+                    Some(group) if common_prefixes.last().map(String::as_str) == Some(group) => {}
+                    Some(group) => {
+                        common_prefixes.push(group.to_owned());
+                        last_listed = Some(group.to_owned());
+                        last_grouped = true;
+                    }
+                    None => {
+                        last_listed = Some(item.key.clone());
+                        last_grouped = false;
+                        items.push(item);
+                    }
+                }
+            }
+            // the keys left after a full page may all belong to its last group
+            let full = items.len() + common_prefixes.len() == max_keys;
+            if !more || full && !last_grouped {
+                break more;
+            }
+        };
+
+        let next_continuation_token = truncated
+            .then(|| encode_continuation_token(last_listed.as_deref().unwrap_or_default()));
+        Ok(GroupedPage {
+            items,
+            common_prefixes,
+            next_continuation_token,
+        })
+    }
+
+    /// Reverse maps a data location (relative to the root) to the tracked item, if any.
+    /// Used by reconciliation tooling to tell whether an on-disk file is tracked.
+    pub async fn get_item_by_data_location(
+        &self,
+        data_location: &str,
+    ) -> Result<Option<S3ItemDetail>> {
+        self.datastore
+            .get_item_by_data_location(data_location)
+            .await
+    }
+
+    /// Reads the latest version of an object, or the `range` of it, `None` when there is
+    /// no such object.
+    ///
+    /// The library counterpart of `GetObject`, for embedding beggar without going through
+    /// HTTP. Objects encrypted with SSE-C cannot be read this way.
+    pub async fn read_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<dto::Range>,
+    ) -> Result<Option<impl Stream<Item = Result<Bytes>> + Send + use<T>>> {
+        let input = map_err!(
+            dto::GetObjectInput::builder()
+                .bucket(bucket.to_owned())
+                .key(key.to_owned())
+                .range(range)
+                .build()
+        )?;
+        match S3::get_object(self, S3Request::new(input)).await {
+            Ok(res) => Ok(res.output.body.map(|body| body.map_err(Error::new))),
+            Err(e) if *e.code() == S3ErrorCode::NoSuchKey => Ok(None),
+            Err(e) => Err(map_err(e)),
+        }
+    }
+
+    /// Stores `data` as the object `key`, returning its entity tag.
+    ///
+    /// The library counterpart of `PutObject`, a new version is created in versioned buckets.
+    pub async fn put_object_bytes(&self, bucket: &str, key: &str, data: Bytes) -> Result<String> {
+        let content_length = map_err!(i64::try_from(data.len()))?;
+        let body = StreamingBlob::wrap(futures::stream::iter([Ok::<_, io::Error>(data)]));
+        let input = map_err!(
+            dto::PutObjectInput::builder()
+                .bucket(bucket.to_owned())
+                .key(key.to_owned())
+                .content_length(Some(content_length))
+                .body(Some(body))
+                .build()
+        )?;
+        let res = map_err!(S3::put_object(self, S3Request::new(input)).await)?;
+        Ok(res.output.e_tag.unwrap_or_default())
+    }
+
+    /// Deletes the object `key`, which is not an error when there is none.
+    ///
+    /// The library counterpart of `DeleteObject`, versioned buckets get a delete marker.
+    pub async fn remove_object(&self, bucket: &str, key: &str) -> Result<()> {
+        let input = map_err!(
+            dto::DeleteObjectInput::builder()
+                .bucket(bucket.to_owned())
+                .key(key.to_owned())
+                .build()
+        )?;
+        map_err!(S3::delete_object(self, S3Request::new(input)).await)?;
+        Ok(())
+    }
+
+    /// Renames the object `key` to `dest_key` in `dest_bucket`, returning `false` when there
+    /// is no such object.
+    ///
+    /// Unlike a copy followed by a delete, the data file is renamed and its row updated in
+    /// place, so the cost does not grow with the size of the object. Both buckets must be
+    /// unversioned and on the same filesystem, and `dest_key` must be free.
+    pub async fn move_object(
         &self,
-        upload_id: &str,
         bucket: &str,
         key: &str,
-        metadata: &str,
-        access_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+    ) -> Result<bool> {
+        map_err!(self.check_writable())?;
+        if !self.validate_s3_key(dest_key) || key.ends_with('/') || dest_key.ends_with('/') {
+            return Err(Error::with_message(
+                ErrorKind::InvalidInput,
+                format!("directory objects cannot be moved and {dest_key} must be a valid key"),
+            ));
+        }
+        if !self.bucket_exists(dest_bucket).await? {
+            return Err(Error::with_message(
+                ErrorKind::NotFound,
+                format!("the bucket {dest_bucket} does not exist"),
+            ));
+        }
+        for bucket in [bucket, dest_bucket] {
+            if self.get_versioning_status(bucket).await?.is_some() {
+                return Err(Error::with_message(
+                    ErrorKind::InvalidInput,
+                    format!("objects of the versioned bucket {bucket} cannot be moved"),
+                ));
+            }
+        }
+        let Some(item) = self
+            .get_s3_item_detail(bucket, key)
+            .await?
+            .filter(|item| !item.delete_marker)
+        else {
+            return Ok(false);
+        };
+        if self
+            .get_s3_item_detail(dest_bucket, dest_key)
+            .await?
+            .is_some()
+        {
+            return Err(Error::with_message(
+                ErrorKind::Conflict,
+                format!("the object {dest_key} already exists in {dest_bucket}"),
+            ));
+        }
+        // the object leaves its key just like a deleted one
+        map_err!(self.check_retention(&item, false, None).await)?;
+        map_err!(self.check_key_collision(dest_bucket, dest_key).await)?;
+
+        let from = resolve_abs_path(&self.root, &item.data_location)?;
+        let dest_location = data_location(self.layout, dest_bucket, dest_key, None);
+        let to = resolve_abs_path(&self.root, &dest_location)?;
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(&from, &to).await?;
+        self.sync_data_file(&to).await?;
+        let moved = self
+            .datastore
+            .move_s3_item_detail(&item, dest_bucket, dest_key, &dest_location)
+            .await;
+        if !matches!(moved, Ok(true)) {
+            // the data goes back to where the row, if any, points
+            if let Err(e) = fs::rename(&to, &from).await {
+                warn!(error = %e, from = %to.display(), to = %from.display(), "failed to move object data back");
+            }
+        }
+        self.invalidate_cached_item(bucket, key);
+        self.invalidate_cached_item(dest_bucket, dest_key);
+        moved
+    }
+
+    pub(crate) async fn get_all_buckets(&self) -> Result<Vec<S3Bucket>> {
+        self.datastore.list_buckets().await
+    }
+
+    pub(crate) async fn bucket_exists(&self, bucket: &str) -> Result<bool> {
+        self.datastore.bucket_exists(bucket).await
+    }
+
+    /// Fails when another bucket would exceed the bucket limit.
+    pub(crate) async fn check_bucket_limit(&self) -> S3Result<()> {
+        if self.max_buckets == 0 {
+            return Ok(());
+        }
+        let count = self.datastore.count_buckets().await?;
+        if u64::try_from(count).unwrap_or_default() >= self.max_buckets {
+            return Err(s3_error!(
+                TooManyBuckets,
+                "no more than {} buckets may be created",
+                self.max_buckets
+            ));
+        }
+        Ok(())
+    }
+
+    /// Records a new bucket, returns `false` when it already exists.
+    pub(crate) async fn register_bucket(&self, bucket: &str, owner: Option<&str>) -> Result<bool> {
+        let bucket = S3Bucket::new(bucket.to_owned(), owner.map(str::to_owned));
+        self.datastore.create_bucket(&bucket).await
+    }
+
+    pub(crate) async fn unregister_bucket(&self, bucket: &str) -> Result<()> {
+        self.datastore.delete_bucket(bucket).await
+    }
+
+    pub(crate) async fn get_versioning_status(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<BucketVersioningStatus>> {
+        let status = self.datastore.get_bucket_versioning(bucket).await?;
+        Ok(status.map(BucketVersioningStatus::from))
+    }
+
+    pub(crate) async fn save_versioning_status(
+        &self,
+        bucket: &str,
+        status: &BucketVersioningStatus,
     ) -> Result<()> {
-        let upload = MultipartUpload::builder()
-            .upload_id(upload_id.to_string())
-            .bucket(bucket.to_string())
-            .key(key.to_string())
-            .metadata(metadata.to_string())
-            .access_key(access_key.to_string())
-            .build();
-        self.datastore.save_multipart_upload(&upload).await
+        self.datastore
+            .save_bucket_versioning(bucket, status.as_str())
+            .await
+    }
+
+    pub(crate) async fn get_object_ownership(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<ObjectOwnership>> {
+        let ownership = self.datastore.get_bucket_ownership(bucket).await?;
+        Ok(ownership.map(ObjectOwnership::from))
+    }
+
+    pub(crate) async fn save_object_ownership(
+        &self,
+        bucket: &str,
+        ownership: &ObjectOwnership,
+    ) -> Result<()> {
+        self.datastore
+            .save_bucket_ownership(bucket, ownership.as_str())
+            .await
+    }
+
+    /// The canned ACL of the bucket, `private` when none was set.
+    pub(crate) async fn get_canned_bucket_acl(&self, bucket: &str) -> Result<String> {
+        let acl = self.datastore.get_bucket_acl(bucket).await?;
+        Ok(acl.unwrap_or_else(|| acl::DEFAULT_ACL.to_owned()))
+    }
+
+    pub(crate) async fn save_canned_bucket_acl(&self, bucket: &str, acl: &str) -> Result<()> {
+        self.datastore.save_bucket_acl(bucket, acl).await
+    }
+
+    /// Content type given to objects uploaded to the bucket without one, if configured.
+    pub(crate) async fn get_default_content_type(&self, bucket: &str) -> Result<Option<String>> {
+        self.datastore.get_bucket_default_content_type(bucket).await
+    }
+
+    pub(crate) async fn save_default_content_type(
+        &self,
+        bucket: &str,
+        content_type: &str,
+    ) -> Result<()> {
+        self.datastore
+            .save_bucket_default_content_type(bucket, content_type)
+            .await
+    }
+
+    /// Default encryption configuration of the bucket, if one was set.
+    pub(crate) async fn get_default_encryption(
+        &self,
+        bucket: &str,
+    ) -> Result<Option<ServerSideEncryptionConfiguration>> {
+        let encryption = self.datastore.get_bucket_encryption(bucket).await?;
+        Ok(encryption.and_then(|json| server_side_encryption::bucket_config_from_json(&json)))
+    }
+
+    pub(crate) async fn save_default_encryption(
+        &self,
+        bucket: &str,
+        config: &ServerSideEncryptionConfiguration,
+    ) -> Result<()> {
+        let json = server_side_encryption::bucket_config_to_json(config);
+        self.datastore.save_bucket_encryption(bucket, &json).await
+    }
+
+    /// Expiration rules of the bucket, empty when no lifecycle configuration was set.
+    pub(crate) async fn get_lifecycle_rules(&self, bucket: &str) -> Result<Vec<ExpirationRule>> {
+        let lifecycle = self.datastore.get_bucket_lifecycle(bucket).await?;
+        Ok(lifecycle
+            .and_then(|json| lifecycle::rules_from_json(&json))
+            .unwrap_or_default())
+    }
+
+    /// Value of the `x-amz-expiration` header of the current version of an object, `None`
+    /// when no rule of the bucket expires it.
+    pub(crate) async fn expiration_header(
+        &self,
+        bucket: &str,
+        key: &str,
+        last_modified: NaiveDateTime,
+    ) -> Result<Option<String>> {
+        let rules = self.get_lifecycle_rules(bucket).await?;
+        Ok(lifecycle::expiration(&rules, key, last_modified)
+            .map(|(expiry_date, rule)| lifecycle::header_value(expiry_date, rule)))
+    }
+
+    pub(crate) async fn save_lifecycle_rules(
+        &self,
+        bucket: &str,
+        rules: &[ExpirationRule],
+    ) -> Result<()> {
+        let json = lifecycle::rules_to_json(rules);
+        self.datastore.save_bucket_lifecycle(bucket, &json).await
+    }
+
+    pub(crate) async fn delete_lifecycle_rules(&self, bucket: &str) -> Result<()> {
+        self.datastore.delete_bucket_lifecycle(bucket).await
+    }
+
+    /// Deletes the objects of the bucket that expired by its lifecycle rules, returning how
+    /// many were deleted.
+    ///
+    /// Objects are deleted like a `DeleteObject` without a version id, versioned buckets
+    /// get a delete marker. Objects under retention or legal hold are skipped.
+    ///
+    /// With `dry_run` the expired objects are only logged and counted, objects under
+    /// retention or legal hold included.
+    pub async fn expire_objects(&self, bucket: &str, dry_run: bool) -> Result<u64> {
+        if !self.bucket_exists(bucket).await? {
+            return Err(Error::with_message(ErrorKind::NotFound, "no such bucket"));
+        }
+        let rules = self.get_lifecycle_rules(bucket).await?;
+        let now = chrono::Utc::now().naive_utc();
+        let mut expired = 0;
+        for rule in rules.iter().filter(|rule| rule.enabled) {
+            let mut start_after = None;
+            loop {
+                let page = self
+                    .list_latest_page(
+                        bucket,
+                        &rule.prefix,
+                        ListOrder::Ascending,
+                        start_after.as_deref(),
+                        EXPIRE_PAGE_SIZE,
+                    )
+                    .await?;
+                let Some(last) = page.items.last() else {
+                    break;
+                };
+                start_after = Some(last.key.clone());
+                for item in &page.items {
+                    if rule.expiry_date(item.last_modified) > now {
+                        continue;
+                    }
+                    if dry_run {
+                        expired += 1;
+                        info!(bucket, key = %item.key, rule_id = ?rule.id, "object would expire");
+                        continue;
+                    }
+                    match self.remove_object(bucket, &item.key).await {
+                        Ok(()) => {
+                            expired += 1;
+                            info!(bucket, key = %item.key, rule_id = ?rule.id, "object expired");
+                        }
+                        Err(e) => warn!(bucket, key = %item.key, "failed to expire object: {e}"),
+                    }
+                }
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Registers the files under `dir` as objects of `bucket`, keyed by their path relative
+    /// to `dir`, returning how many were imported.
+    ///
+    /// Files are copied under the root, or moved with `move_files`. Objects get the default
+    /// content type of the bucket. Files whose key is not valid, is already stored or is in
+    /// the way of a stored object are skipped with a warning.
+    pub async fn import_dir(&self, bucket: &str, dir: &Path, move_files: bool) -> Result<u64> {
+        if !self.bucket_exists(bucket).await? {
+            return Err(Error::with_message(ErrorKind::NotFound, "no such bucket"));
+        }
+        let dir = dir.canonicalize()?;
+        let content_headers = ContentHeaders {
+            content_type: self.get_default_content_type(bucket).await?,
+            ..Default::default()
+        };
+        let mut imported = 0;
+        for path in list_files(&dir).await? {
+            let Some(key) = path.strip_prefix(&dir).ok().and_then(Path::to_str) else {
+                warn!(path = %path.display(), "file name is not UTF-8, skipped");
+                continue;
+            };
+            if !self.validate_s3_key(key) {
+                warn!(bucket, key, "invalid key, skipped");
+                continue;
+            }
+            if self
+                .get_s3_item_detail(bucket, key)
+                .await?
+                .is_some_and(|item| !item.delete_marker)
+            {
+                warn!(bucket, key, "object already exists, skipped");
+                continue;
+            }
+            let version_id = self.new_version_id(bucket).await?;
+            if version_id.is_none()
+                && let Err(e) = self.check_key_collision(bucket, key).await
+            {
+                warn!(
+                    bucket,
+                    key,
+                    reason = e.message().unwrap_or_default(),
+                    "skipped"
+                );
+                continue;
+            }
+
+            let object_path = self.get_object_version_path(bucket, key, version_id.as_deref())?;
+            if let Some(parent) = object_path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            let size = if move_files {
+                move_file(&path, &object_path).await?
+            } else {
+                fs::copy(&path, &object_path).await?
+            };
+            self.sync_data_file(&object_path).await?;
+            let md5_sum = utils::md5_sum(&object_path).await?;
+            let mut info = InternalInfo::new();
+            object_parts::modify_size(&mut info, size);
+            content_headers::modify_internal_info(&mut info, &content_headers);
+            self.save_s3_item_detail(bucket, key, &md5_sum, None, info, version_id.as_deref())
+                .await?;
+            imported += 1;
+            debug!(bucket, key, size, "file imported");
+        }
+        Ok(imported)
+    }
+
+    /// Writes the current version of every object of `bucket` to `dir`, at its key, returning
+    /// how many were exported.
+    ///
+    /// Directory markers become directories. Objects encrypted with a customer key, whose
+    /// key is not a plain relative path or whose data cannot be copied are skipped with a
+    /// warning.
+    pub async fn export_dir(&self, bucket: &str, dir: &Path) -> Result<u64> {
+        if !self.bucket_exists(bucket).await? {
+            return Err(Error::with_message(ErrorKind::NotFound, "no such bucket"));
+        }
+        fs::create_dir_all(dir).await?;
+        let mut exported = 0;
+        let mut start_after = None;
+        loop {
+            let page = self
+                .list_latest_page(
+                    bucket,
+                    "",
+                    ListOrder::Ascending,
+                    start_after.as_deref(),
+                    EXPORT_PAGE_SIZE,
+                )
+                .await?;
+            let Some(last) = page.items.last() else {
+                break;
+            };
+            start_after = Some(last.key.clone());
+            for item in &page.items {
+                match self.export_item(item, dir).await {
+                    Ok(true) => exported += 1,
+                    Ok(false) => {}
+                    Err(e) => warn!(bucket, key = %item.key, "failed to export object: {e}"),
+                }
+            }
+        }
+        Ok(exported)
+    }
+
+    async fn export_item(&self, item: &S3ItemDetail, dir: &Path) -> Result<bool> {
+        if !is_plain_path(&item.key) {
+            warn!(bucket = %item.bucket, key = %item.key, "key is not a plain path, skipped");
+            return Ok(false);
+        }
+        let info: InternalInfo = serde_json::from_str(&item.internal_info).unwrap_or_default();
+        if sse_c::from_internal_info(&info).is_some() {
+            warn!(bucket = %item.bucket, key = %item.key, "encrypted with a customer key, skipped");
+            return Ok(false);
+        }
+        let to = dir.join(&item.key);
+        if item.key.ends_with('/') {
+            fs::create_dir_all(&to).await?;
+            return Ok(true);
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let from = resolve_abs_path(&self.root, &item.data_location)?;
+        fs::copy(&from, &to).await?;
+        self.sync_data_file(&to).await?;
+        Ok(true)
+    }
+
+    /// Owner reported in ACLs: the creator of the bucket, or the requester for buckets
+    /// created before owners were recorded.
+    pub(crate) async fn acl_owner(
+        &self,
+        bucket: &str,
+        access_key: Option<&str>,
+    ) -> Result<dto::Owner> {
+        let bucket = self.datastore.get_bucket(bucket).await?;
+        let owner = bucket.and_then(|b| b.owner);
+        Ok(acl::owner(
+            owner.as_deref().or(access_key).unwrap_or_default(),
+        ))
+    }
+
+    /// ACLs are disabled on buckets whose object ownership is `BucketOwnerEnforced`.
+    pub(crate) async fn acls_disabled(&self, bucket: &str) -> Result<bool> {
+        let ownership = self.get_object_ownership(bucket).await?;
+        Ok(ownership.is_some_and(|o| o.as_str() == ObjectOwnership::BUCKET_OWNER_ENFORCED))
+    }
+
+    /// Whether the object, or the bucket's listing when `key` is `None`, may be read
+    /// without signing: its canned ACL is public and the bucket has ACLs enabled.
+    pub(crate) async fn is_public_read(
+        &self,
+        bucket: &str,
+        key: Option<&str>,
+        version_id: Option<&str>,
+    ) -> Result<bool> {
+        if self.acls_disabled(bucket).await? {
+            return Ok(false);
+        }
+        let acl = match key {
+            None => self.get_canned_bucket_acl(bucket).await?,
+            Some(key) => match self.get_object_detail(bucket, key, version_id).await? {
+                Some(detail) if !detail.delete_marker => {
+                    let info: InternalInfo =
+                        serde_json::from_str(&detail.internal_info).unwrap_or_default();
+                    acl::from_internal_info(&info)
+                }
+                _ => return Ok(false),
+            },
+        };
+        Ok(acl::is_public_read(&acl))
+    }
+
+    /// Rejects a canned ACL sent along a write to a bucket with ACLs disabled.
+    /// `bucket-owner-full-control` is still accepted, as S3 does.
+    pub(crate) async fn check_object_acl(
+        &self,
+        bucket: &str,
+        acl: Option<&dto::ObjectCannedACL>,
+    ) -> S3Result<()> {
+        let Some(acl) = acl else {
+            return Ok(());
+        };
+        if acl.as_str() != dto::ObjectCannedACL::BUCKET_OWNER_FULL_CONTROL
+            && self.acls_disabled(bucket).await?
+        {
+            return Err(s3_error!(AccessControlListNotSupported));
+        }
+        Ok(())
+    }
+
+    /// Returns a new version id when versioning is enabled on the bucket.
+    /// Objects written to unversioned or suspended buckets keep the `null` version.
+    pub(crate) async fn new_version_id(&self, bucket: &str) -> Result<Option<String>> {
+        let status = self.get_versioning_status(bucket).await?;
+        let enabled = status.is_some_and(|s| s.as_str() == BucketVersioningStatus::ENABLED);
+        Ok(enabled.then(|| Uuid::new_v4().to_string()))
+    }
+
+    pub(crate) async fn save_multipart_upload(&self, upload: &MultipartUpload) -> Result<()> {
+        self.datastore.save_multipart_upload(upload).await
     }
 
     pub(crate) async fn save_multipart_upload_part(
@@ -275,6 +1639,40 @@ impl<T: DataStore> StorageBackend<T> {
         self.datastore.save_multipart_upload_part(&part).await
     }
 
+    /// Records an uploaded part whose data was written to `file_path`.
+    pub(crate) async fn record_upload_part(
+        &self,
+        upload_id: &str,
+        part_number: i32,
+        md5: &str,
+        file_path: PathBuf,
+    ) -> Result<()> {
+        // A re-uploaded part replaces the previous one, remember where the old data lived
+        let previous_location = self
+            .get_parts_by_upload_id(upload_id)
+            .await?
+            .into_iter()
+            .find(|p| p.part_number == part_number)
+            .map(|p| p.data_location);
+
+        let data_location = file_path.into_os_string().into_string().unwrap();
+
+        //Save to db
+        self.save_multipart_upload_part(upload_id, part_number, md5, data_location.as_str())
+            .await?;
+
+        // Never leave the replaced part behind as an orphan,
+        // an assembly file still holds the other parts of the upload
+        if let Some(previous_location) = previous_location
+            && previous_location != data_location
+            && assembly_part_size(&previous_location).is_none()
+            && let Err(e) = fs::remove_file(&previous_location).await
+        {
+            warn!(error = %e, path = %previous_location, "failed to remove replaced part");
+        }
+        Ok(())
+    }
+
     pub(crate) async fn get_access_key_by_upload_id(
         &self,
         upload_id: &str,
@@ -305,11 +1703,18 @@ impl<T: DataStore> StorageBackend<T> {
     }
 }
 
+/// Syncs the entries of a directory, e.g. a file renamed into it, to disk.
+async fn sync_dir(path: &Path) -> io::Result<()> {
+    File::open(path).await?.sync_all().await
+}
+
 pub(crate) struct FileWriter<'a> {
     tmp_path: PathBuf,
     dest_path: &'a Path,
     writer: BufWriter<File>,
     clean_tmp: bool,
+    /// Syncs the file before it is moved into place and its directory after.
+    durable: bool,
 }
 
 impl<'a> FileWriter<'a> {
@@ -330,8 +1735,18 @@ impl<'a> FileWriter<'a> {
             fs::create_dir_all(&final_dir_path).await?;
         }
 
+        if self.durable {
+            self.writer.flush().await?;
+            self.writer.get_ref().sync_all().await?;
+        }
+
         if !self.dest_path().is_dir() {
             fs::rename(&self.tmp_path, self.dest_path()).await?;
+            if self.durable
+                && let Some(final_dir_path) = self.dest_path().parent()
+            {
+                sync_dir(final_dir_path).await?;
+            }
         }
 
         self.clean_tmp = false;
@@ -359,7 +1774,7 @@ mod tests {
     use uuid::Uuid;
 
     use super::*;
-    use crate::{MultipartUpload, MultipartUploadPart};
+    use crate::{DatabaseHealth, MultipartUpload, MultipartUploadPart};
 
     mock! {
         #[derive(Debug)]
@@ -368,12 +1783,79 @@ mod tests {
         impl DataStore for TestDataStore {
             async fn save_s3_item_detail(&self, item: &S3ItemDetail) -> Result<()>;
             async fn get_s3_item_detail(&self, bucket: &str, key: &str) -> Result<Option<S3ItemDetail>>;
+            async fn get_s3_item_detail_version(
+                &self,
+                bucket: &str,
+                key: &str,
+                version_id: &str,
+            ) -> Result<Option<S3ItemDetail>>;
             async fn get_s3_item_detail_with_filter(
                 &self,
                 bucket: &str,
                 filter: &str,
             ) -> Result<Vec<S3ItemDetail>>;
-            async fn get_all_buckets(&self) -> Result<Vec<String>>;
+            async fn get_s3_item_versions_with_filter(
+                &self,
+                bucket: &str,
+                filter: &str,
+            ) -> Result<Vec<S3ItemDetail>>;
+            async fn list_latest_items_page(
+                &self,
+                bucket: &str,
+                prefix: &str,
+                start_after: &str,
+                descending: bool,
+                limit: i32,
+            ) -> Result<Vec<S3ItemDetail>>;
+            async fn list_versions_page(
+                &self,
+                bucket: &str,
+                prefix: &str,
+                key_marker: &str,
+                version_id_marker: &str,
+                limit: i32,
+            ) -> Result<Vec<S3ItemDetail>>;
+            async fn list_item_versions_page(
+                &self,
+                after_bucket: &str,
+                after_key: &str,
+                after_version_id: &str,
+                limit: i32,
+            ) -> Result<Vec<S3ItemDetail>>;
+            async fn get_item_by_data_location(&self, data_location: &str)
+            -> Result<Option<S3ItemDetail>>;
+            async fn delete_s3_item_detail_version(
+                &self,
+                bucket: &str,
+                key: &str,
+                version_id: &str,
+            ) -> Result<()>;
+            async fn move_s3_item_detail(
+                &self,
+                item: &S3ItemDetail,
+                bucket: &str,
+                key: &str,
+                data_location: &str,
+            ) -> Result<bool>;
+            async fn create_bucket(&self, bucket: &S3Bucket) -> Result<bool>;
+            async fn delete_bucket(&self, bucket: &str) -> Result<()>;
+            async fn list_buckets(&self) -> Result<Vec<S3Bucket>>;
+            async fn count_buckets(&self) -> Result<i64>;
+            async fn bucket_exists(&self, bucket: &str) -> Result<bool>;
+            async fn get_bucket(&self, bucket: &str) -> Result<Option<S3Bucket>>;
+            async fn get_bucket_versioning(&self, bucket: &str) -> Result<Option<String>>;
+            async fn save_bucket_versioning(&self, bucket: &str, status: &str) -> Result<()>;
+            async fn get_bucket_ownership(&self, bucket: &str) -> Result<Option<String>>;
+            async fn save_bucket_ownership(&self, bucket: &str, ownership: &str) -> Result<()>;
+            async fn get_bucket_acl(&self, bucket: &str) -> Result<Option<String>>;
+            async fn save_bucket_acl(&self, bucket: &str, acl: &str) -> Result<()>;
+            async fn get_bucket_default_content_type(&self, bucket: &str) -> Result<Option<String>>;
+            async fn save_bucket_default_content_type(&self, bucket: &str, content_type: &str) -> Result<()>;
+            async fn get_bucket_encryption(&self, bucket: &str) -> Result<Option<String>>;
+            async fn save_bucket_encryption(&self, bucket: &str, encryption: &str) -> Result<()>;
+            async fn get_bucket_lifecycle(&self, bucket: &str) -> Result<Option<String>>;
+            async fn save_bucket_lifecycle(&self, bucket: &str, lifecycle: &str) -> Result<()>;
+            async fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<()>;
             async fn save_multipart_upload(&self, upload: &MultipartUpload) -> Result<()>;
             async fn save_multipart_upload_part(&self, part: &MultipartUploadPart) -> Result<()>;
             async fn get_access_key_by_upload_id(&self, upload_id: &str) -> Result<Option<String>>;
@@ -383,6 +1865,11 @@ mod tests {
                 upload_id: &str,
             ) -> Result<Option<MultipartUpload>>;
             async fn delete_multipart_upload_by_upload_id(&self, upload_id: &str) -> Result<()>;
+            async fn get_stale_multipart_uploads(
+                &self,
+                older_than: chrono::NaiveDateTime,
+            ) -> Result<Vec<MultipartUpload>>;
+            async fn health(&self) -> DatabaseHealth;
         }
     }
 
@@ -404,6 +1891,50 @@ mod tests {
         assert_eq!(result.unwrap(), None);
     }
 
+    #[tokio::test]
+    async fn test_get_item_by_data_location() {
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds
+            .expect_get_item_by_data_location()
+            .with(eq("test_bucket/test_key"))
+            .times(1)
+            .returning(|_| {
+                Ok(Some(
+                    S3ItemDetail::builder()
+                        .bucket("test_bucket".to_string())
+                        .key("test_key".to_string())
+                        .e_tag("test_etag".to_string())
+                        .data_location("test_bucket/test_key".to_string())
+                        .metadata(Some("{}".to_string()))
+                        .internal_info(Some("{}".to_string()))
+                        .build(),
+                ))
+            });
+        mock_ds
+            .expect_get_item_by_data_location()
+            .with(eq("test_bucket/untracked"))
+            .times(1)
+            .returning(|_| Ok(None));
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let root = tmp_dir.path().as_os_str();
+        let backend = StorageBackend::new(root, mock_ds).expect("backend created successfully");
+
+        let item = backend
+            .get_item_by_data_location("test_bucket/test_key")
+            .await
+            .unwrap()
+            .expect("item is tracked");
+        assert_eq!(item.bucket, "test_bucket");
+        assert_eq!(item.key, "test_key");
+
+        let item = backend
+            .get_item_by_data_location("test_bucket/untracked")
+            .await
+            .unwrap();
+        assert!(item.is_none());
+    }
+
     #[test]
     fn test_resolve_upload_part_path() {
         // initialize the temp directory
@@ -440,6 +1971,196 @@ mod tests {
         assert_eq!(path, expected_path);
     }
 
+    #[test]
+    fn test_get_object_path_keeps_dot_segments() {
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let root = tmp_dir.path().as_os_str();
+        let mock_ds = MockTestDataStore::new();
+        let backend = StorageBackend::new(root, mock_ds).expect("backend created successfully");
+
+        let bucket_path = tmp_dir.path().join("test_bucket");
+        let paths = ["a/./b", "a/../b", "a/b", "b", "a/%2E/b", "../../b", "/a/b"].map(|key| {
+            assert!(backend.validate_s3_key(key));
+            backend.get_object_path("test_bucket", key).unwrap()
+        });
+
+        assert_eq!(paths[0], bucket_path.join("a/%2E/b"));
+        assert_eq!(paths[1], bucket_path.join("a/%2E%2E/b"));
+        assert_eq!(paths[4], bucket_path.join("a/%252E/b"));
+        // keys never resolve outside of their bucket
+        assert_eq!(paths[5], bucket_path.join("%2E%2E/%2E%2E/b"));
+        assert_eq!(paths[6], bucket_path.join("%2F/a/b"));
+
+        let unique: std::collections::HashSet<_> = paths.iter().collect();
+        assert_eq!(unique.len(), paths.len());
+    }
+
+    #[tokio::test]
+    async fn test_sharded_layout() {
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let root = tmp_dir.path().as_os_str();
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds
+            .expect_save_s3_item_detail()
+            .times(2)
+            .returning(|_| Ok(()));
+        let backend = StorageBackend::new(root, mock_ds)
+            .expect("backend created successfully")
+            .with_layout(Layout::Sharded);
+
+        let path = backend
+            .get_object_path("test_bucket", "test_key.txt")
+            .unwrap();
+        assert_eq!(
+            path,
+            tmp_dir
+                .path()
+                .join(".hashed/test_bucket/e0/37/e03792c9070a38f334623692eaaeeb5c")
+        );
+        let path = backend
+            .get_object_version_path("test_bucket", "test_key.txt", Some("v1"))
+            .unwrap();
+        assert_eq!(path, tmp_dir.path().join(".versions/test_bucket/e0/37/v1"));
+
+        // a file along the key is not in the way
+        let bucket_path = tmp_dir.path().join("test_bucket");
+        fs::create_dir_all(&bucket_path).await.unwrap();
+        fs::write(bucket_path.join("a"), "a").await.unwrap();
+        backend
+            .check_key_collision("test_bucket", "a/b")
+            .await
+            .unwrap();
+
+        // the data stored before the layout changed is replaced
+        let flat_path = bucket_path.join("test_key.txt");
+        fs::write(&flat_path, "old").await.unwrap();
+        backend
+            .save_s3_item_detail(
+                "test_bucket",
+                "test_key.txt",
+                "d41d8cd98f00b204e9800998ecf8427e",
+                None,
+                InternalInfo::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!flat_path.exists());
+        assert!(bucket_path.join("a").exists());
+
+        // nor is the directory of a flat directory marker `dir/`
+        let marker_path = bucket_path.join("dir");
+        fs::create_dir_all(&marker_path).await.unwrap();
+        backend
+            .save_s3_item_detail(
+                "test_bucket",
+                "dir",
+                "d41d8cd98f00b204e9800998ecf8427e",
+                None,
+                InternalInfo::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(marker_path.is_dir());
+    }
+
+    #[tokio::test]
+    async fn test_import_and_export_dir() {
+        let saved = Arc::new(std::sync::Mutex::new(Vec::<S3ItemDetail>::new()));
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds.expect_bucket_exists().returning(|_| Ok(true));
+        mock_ds
+            .expect_get_bucket_default_content_type()
+            .returning(|_| Ok(Some("text/plain".to_owned())));
+        mock_ds
+            .expect_get_s3_item_detail()
+            .returning(|bucket, key| {
+                Ok((key == "exists").then(|| {
+                    S3ItemDetail::builder()
+                        .bucket(bucket.to_owned())
+                        .key(key.to_owned())
+                        .e_tag("test_etag".to_owned())
+                        .data_location(format!("{bucket}/{key}"))
+                        .metadata(Some("{}".to_owned()))
+                        .internal_info(Some("{}".to_owned()))
+                        .build()
+                }))
+            });
+        mock_ds
+            .expect_get_bucket_versioning()
+            .returning(|_| Ok(None));
+        let items = saved.clone();
+        mock_ds
+            .expect_save_s3_item_detail()
+            .times(2)
+            .returning(move |item| {
+                items.lock().unwrap().push(item.clone());
+                Ok(())
+            });
+        let items = saved.clone();
+        mock_ds
+            .expect_list_latest_items_page()
+            .returning(move |_, _, start_after, _, _| {
+                let items = items.lock().unwrap();
+                Ok(items
+                    .iter()
+                    .filter(|item| item.key.as_str() > start_after)
+                    .cloned()
+                    .collect())
+            });
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let root = tmp_dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        let backend = StorageBackend::new(&root, mock_ds).expect("backend created successfully");
+
+        let import_dir = tmp_dir.path().join("import");
+        fs::create_dir_all(import_dir.join("sub")).await.unwrap();
+        fs::write(import_dir.join("a.txt"), "a").await.unwrap();
+        fs::write(import_dir.join("sub/b.txt"), "bb").await.unwrap();
+        fs::write(import_dir.join("exists"), "c").await.unwrap();
+        let imported = backend
+            .import_dir("test_bucket", &import_dir, true)
+            .await
+            .unwrap();
+        assert_eq!(imported, 2);
+        {
+            let saved = saved.lock().unwrap();
+            assert_eq!(saved[0].key, "a.txt");
+            assert_eq!(saved[0].e_tag, hex(Md5::digest("a")));
+            assert_eq!(saved[1].key, "sub/b.txt");
+            assert_eq!(saved[1].data_location, "test_bucket/sub/b.txt");
+            let info: InternalInfo = serde_json::from_str(&saved[1].internal_info).unwrap();
+            assert_eq!(object_parts::size_from_internal_info(&info), Some(2));
+            assert_eq!(
+                content_headers::from_internal_info(&info).content_type,
+                Some("text/plain".to_owned())
+            );
+        }
+        // moved, the file of the existing object stays
+        assert!(!import_dir.join("a.txt").exists());
+        assert!(import_dir.join("exists").exists());
+        assert!(root.join("test_bucket/sub/b.txt").exists());
+
+        let export_dir = tmp_dir.path().join("export");
+        let exported = backend
+            .export_dir("test_bucket", &export_dir)
+            .await
+            .unwrap();
+        assert_eq!(exported, 2);
+        assert_eq!(
+            fs::read_to_string(export_dir.join("sub/b.txt"))
+                .await
+                .unwrap(),
+            "bb"
+        );
+
+        assert!(is_plain_path("a/b/"));
+        assert!(!is_plain_path("a/../b"));
+        assert!(!is_plain_path("/a"));
+    }
+
     #[test]
     fn test_get_bucket_path() {
         // initialize the temp directory
@@ -455,6 +2176,26 @@ mod tests {
         assert_eq!(path, expected_path);
     }
 
+    #[test]
+    fn test_virtual_hosts() {
+        use s3s::host::S3Host;
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend = StorageBackend::new(tmp_dir.path(), MockTestDataStore::new())
+            .expect("backend created successfully");
+        assert!(backend.virtual_hosts().unwrap().is_none());
+
+        let backend = backend.with_domains(vec!["s3.example.com".to_string()]);
+        let virtual_hosts = backend.virtual_hosts().unwrap().unwrap();
+        let host = virtual_hosts
+            .parse_host_header("mybucket.s3.example.com")
+            .unwrap();
+        assert_eq!(host.bucket(), Some("mybucket"));
+        // path-style requests keep working on the domain itself
+        let host = virtual_hosts.parse_host_header("s3.example.com").unwrap();
+        assert_eq!(host.bucket(), None);
+    }
+
     #[tokio::test]
     async fn test_delete_multipart_upload_by_upload_id() {
         let tmp_dir = tempdir().expect("tempdir created successfully");
@@ -550,7 +2291,7 @@ mod tests {
 
         // generate the md5sum from this link https://www.md5hashgenerator.com/
         let expected_md5_sum = "9473fdd0d880a43c21b7778d34872157";
-        let md5_sum = backend.get_md5_sum(bucket, key).await.unwrap();
+        let md5_sum = backend.get_md5_sum(bucket, key, None).await.unwrap();
 
         assert_eq!(md5_sum, expected_md5_sum);
     }
@@ -765,6 +2506,7 @@ mod tests {
         let mut mock_ds = MockTestDataStore::new();
         mock_ds
             .expect_save_multipart_upload()
+            .withf(|upload| upload.storage_class.as_deref() == Some("STANDARD_IA"))
             .times(1)
             .returning(|_| Ok(()));
 
@@ -778,17 +2520,10 @@ mod tests {
             .key("test_key".to_string())
             .metadata("test_metadata".to_string())
             .access_key("test_access_key".to_string())
+            .storage_class(Some("STANDARD_IA".to_string()))
             .build();
 
-        let result = backend
-            .save_multipart_upload(
-                &upload.upload_id,
-                &upload.bucket,
-                &upload.key,
-                &upload.metadata,
-                &upload.access_key,
-            )
-            .await;
+        let result = backend.save_multipart_upload(&upload).await;
 
         assert!(result.is_ok());
     }
@@ -877,4 +2612,498 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), vec![]);
     }
+
+    #[tokio::test]
+    async fn test_list_objects_page_descending() {
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds
+            .expect_list_latest_items_page()
+            .withf(|bucket, prefix, _, descending, limit| {
+                bucket == "test_bucket" && prefix.is_empty() && *descending && *limit == 3
+            })
+            .times(3)
+            .returning(|bucket, _, start_after, _, limit| {
+                let mut keys = vec!["a", "b", "c", "d", "e"];
+                keys.reverse();
+                Ok(keys
+                    .into_iter()
+                    .filter(|key| start_after.is_empty() || *key < start_after)
+                    .take(usize::try_from(limit).unwrap())
+                    .map(|key| {
+                        S3ItemDetail::builder()
+                            .bucket(bucket.to_string())
+                            .key(key.to_string())
+                            .e_tag(String::new())
+                            .metadata(Some("{}".to_string()))
+                            .internal_info(Some("{}".to_string()))
+                            .data_location(format!("{bucket}/{key}"))
+                            .build()
+                    })
+                    .collect())
+            });
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let root = tmp_dir.path().as_os_str();
+        let backend = StorageBackend::new(root, mock_ds).expect("backend created successfully");
+
+        let mut pages = Vec::new();
+        let mut token = None;
+        loop {
+            let page = backend
+                .list_objects_page(
+                    "test_bucket",
+                    "",
+                    ListOrder::Descending,
+                    token.as_deref(),
+                    2,
+                )
+                .await
+                .unwrap();
+            pages.push(
+                page.items
+                    .into_iter()
+                    .map(|item| item.key)
+                    .collect::<Vec<_>>(),
+            );
+            token = page.next_continuation_token;
+            if token.is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(pages, vec![vec!["e", "d"], vec!["c", "b"], vec!["a"]]);
+    }
+
+    #[tokio::test]
+    async fn test_list_objects_page_invalid_token() {
+        let mock_ds = MockTestDataStore::new();
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let root = tmp_dir.path().as_os_str();
+        let backend = StorageBackend::new(root, mock_ds).expect("backend created successfully");
+
+        let result = backend
+            .list_objects_page("test_bucket", "", ListOrder::Descending, Some("%%%"), 2)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_object_helpers_round_trip() {
+        let saved = Arc::new(std::sync::Mutex::new(None::<S3ItemDetail>));
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds.expect_bucket_exists().returning(|_| Ok(true));
+        mock_ds
+            .expect_get_bucket_versioning()
+            .returning(|_| Ok(None));
+        mock_ds
+            .expect_get_bucket_default_content_type()
+            .returning(|_| Ok(None));
+        mock_ds
+            .expect_get_bucket_lifecycle()
+            .returning(|_| Ok(None));
+        let save = Arc::clone(&saved);
+        mock_ds.expect_save_s3_item_detail().returning(move |item| {
+            *save.lock().unwrap() = Some(item.clone());
+            Ok(())
+        });
+        let load = Arc::clone(&saved);
+        mock_ds
+            .expect_get_s3_item_detail()
+            .returning(move |_, _| Ok(load.lock().unwrap().clone()));
+        let load = Arc::clone(&saved);
+        mock_ds
+            .expect_get_s3_item_detail_version()
+            .returning(move |_, _, _| Ok(load.lock().unwrap().clone()));
+        let delete = Arc::clone(&saved);
+        mock_ds
+            .expect_delete_s3_item_detail_version()
+            .times(1)
+            .returning(move |_, _, _| {
+                delete.lock().unwrap().take();
+                Ok(())
+            });
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend =
+            StorageBackend::new(tmp_dir.path(), mock_ds).expect("backend created successfully");
+        fs::create_dir(tmp_dir.path().join("bucket")).await.unwrap();
+
+        let e_tag = backend
+            .put_object_bytes("bucket", "key", Bytes::from_static(b"hello world"))
+            .await
+            .unwrap();
+        assert_eq!(e_tag, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+
+        let backend = &backend;
+        let read = |range| async move {
+            let body = backend.read_object("bucket", "key", range).await.unwrap();
+            let chunks: Option<Vec<Bytes>> = match body {
+                Some(body) => Some(body.try_collect().await.unwrap()),
+                None => None,
+            };
+            chunks.map(|chunks| chunks.concat())
+        };
+        assert_eq!(read(None).await.unwrap(), b"hello world");
+        let range = dto::Range::Int {
+            first: 6,
+            last: None,
+        };
+        assert_eq!(read(Some(range)).await.unwrap(), b"world");
+
+        backend.remove_object("bucket", "key").await.unwrap();
+        assert!(read(None).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_is_public_read() {
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds.expect_get_bucket_ownership().returning(|bucket| {
+            Ok((bucket == "enforced").then(|| ObjectOwnership::BUCKET_OWNER_ENFORCED.to_owned()))
+        });
+        mock_ds
+            .expect_get_bucket_acl()
+            .returning(|bucket| Ok((bucket != "private").then(|| "public-read".to_owned())));
+        let detail = |key: &str, internal_info: &str| {
+            S3ItemDetail::builder()
+                .bucket("bucket".to_owned())
+                .key(key.to_owned())
+                .e_tag("etag".to_owned())
+                .data_location(format!("bucket/{key}"))
+                .metadata(Some("{}".to_owned()))
+                .internal_info(Some(internal_info.to_owned()))
+                .build()
+        };
+        mock_ds
+            .expect_get_s3_item_detail()
+            .returning(move |_, key| {
+                Ok(match key {
+                    "public" => Some(detail(key, r#"{"acl":"public-read"}"#)),
+                    "private" => Some(detail(key, "{}")),
+                    _ => None,
+                })
+            });
+        mock_ds
+            .expect_get_s3_item_detail_version()
+            .returning(move |_, key, _| Ok(Some(detail(key, "{}"))));
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend =
+            StorageBackend::new(tmp_dir.path(), mock_ds).expect("backend created successfully");
+        let backend = &backend;
+        let is_public_read = |bucket, key, version_id| async move {
+            backend
+                .is_public_read(bucket, key, version_id)
+                .await
+                .unwrap()
+        };
+
+        assert!(is_public_read("bucket", Some("public"), None).await);
+        assert!(!is_public_read("bucket", Some("private"), None).await);
+        assert!(!is_public_read("bucket", Some("missing"), None).await);
+        // the ACL of the requested version counts
+        assert!(!is_public_read("bucket", Some("public"), Some("v1")).await);
+        // the bucket ACL only opens the listing
+        assert!(is_public_read("bucket", None, None).await);
+        assert!(!is_public_read("private", None, None).await);
+        // ACLs are ignored when disabled
+        assert!(!is_public_read("enforced", None, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_expire_objects() {
+        type Items = std::collections::BTreeMap<String, S3ItemDetail>;
+        let now = chrono::Utc::now().naive_utc();
+        let two_days_ago = now - chrono::TimeDelta::days(2);
+        let items = Arc::new(std::sync::Mutex::new(
+            [
+                ("logs/old", two_days_ago),
+                ("logs/new", now),
+                ("data/old", two_days_ago),
+            ]
+            .into_iter()
+            .map(|(key, last_modified)| {
+                let item = S3ItemDetail::builder()
+                    .bucket("bucket".to_string())
+                    .key(key.to_string())
+                    .e_tag(String::new())
+                    .last_modified(last_modified)
+                    .metadata(Some("{}".to_string()))
+                    .internal_info(Some("{}".to_string()))
+                    .data_location(format!("bucket/{key}"))
+                    .build();
+                (key.to_string(), item)
+            })
+            .collect::<Items>(),
+        ));
+
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds
+            .expect_bucket_exists()
+            .returning(|bucket| Ok(bucket == "bucket"));
+        mock_ds.expect_get_bucket_lifecycle().returning(|_| {
+            let rules = [ExpirationRule {
+                id: Some("logs".to_string()),
+                prefix: "logs/".to_string(),
+                days: 0,
+                enabled: true,
+            }];
+            Ok(Some(lifecycle::rules_to_json(&rules)))
+        });
+        mock_ds
+            .expect_get_bucket_versioning()
+            .returning(|_| Ok(None));
+        let list = Arc::clone(&items);
+        mock_ds.expect_list_latest_items_page().returning(
+            move |_, prefix, start_after, _, limit| {
+                Ok(list
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .filter(|item| item.key.starts_with(prefix) && *item.key > *start_after)
+                    .take(usize::try_from(limit).unwrap())
+                    .cloned()
+                    .collect())
+            },
+        );
+        let load = Arc::clone(&items);
+        mock_ds
+            .expect_get_s3_item_detail_version()
+            .returning(move |_, key, _| Ok(load.lock().unwrap().get(key).cloned()));
+        let delete = Arc::clone(&items);
+        mock_ds
+            .expect_delete_s3_item_detail_version()
+            .times(1)
+            .returning(move |_, key, _| {
+                delete.lock().unwrap().remove(key);
+                Ok(())
+            });
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend =
+            StorageBackend::new(tmp_dir.path(), mock_ds).expect("backend created successfully");
+        fs::create_dir_all(tmp_dir.path().join("bucket/logs"))
+            .await
+            .unwrap();
+        fs::write(tmp_dir.path().join("bucket/logs/old"), "old")
+            .await
+            .unwrap();
+
+        // a dry run leaves the expired object alone
+        assert_eq!(backend.expire_objects("bucket", true).await.unwrap(), 1);
+        assert_eq!(items.lock().unwrap().len(), 3);
+        assert!(tmp_dir.path().join("bucket/logs/old").exists());
+
+        assert_eq!(backend.expire_objects("bucket", false).await.unwrap(), 1);
+        let keys = items.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys, ["data/old", "logs/new"]);
+        assert!(!tmp_dir.path().join("bucket/logs/old").exists());
+
+        // nothing is left to expire
+        assert_eq!(backend.expire_objects("bucket", false).await.unwrap(), 0);
+
+        let err = backend.expire_objects("missing", false).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_move_object() {
+        type Items = std::collections::HashMap<(String, String), S3ItemDetail>;
+        let items = Arc::new(std::sync::Mutex::new(Items::new()));
+        let fail = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds
+            .expect_bucket_exists()
+            .returning(|bucket| Ok(bucket != "missing"));
+        mock_ds
+            .expect_get_bucket_versioning()
+            .returning(|_| Ok(None));
+        mock_ds
+            .expect_get_bucket_default_content_type()
+            .returning(|_| Ok(None));
+        let save = Arc::clone(&items);
+        mock_ds.expect_save_s3_item_detail().returning(move |item| {
+            let id = (item.bucket.clone(), item.key.clone());
+            save.lock().unwrap().insert(id, item.clone());
+            Ok(())
+        });
+        let load = Arc::clone(&items);
+        mock_ds
+            .expect_get_s3_item_detail()
+            .returning(move |bucket, key| {
+                let id = (bucket.to_owned(), key.to_owned());
+                Ok(load.lock().unwrap().get(&id).cloned())
+            });
+        let moved = Arc::clone(&items);
+        let should_fail = Arc::clone(&fail);
+        mock_ds
+            .expect_move_s3_item_detail()
+            .returning(move |item, bucket, key, data_location| {
+                if should_fail.swap(false, std::sync::atomic::Ordering::SeqCst) {
+                    return Err(Error::from_string("connection lost"));
+                }
+                let mut items = moved.lock().unwrap();
+                let mut item = items
+                    .remove(&(item.bucket.clone(), item.key.clone()))
+                    .unwrap();
+                item.bucket = bucket.to_owned();
+                item.key = key.to_owned();
+                item.data_location = data_location.to_owned();
+                items.insert((item.bucket.clone(), item.key.clone()), item);
+                Ok(true)
+            });
+
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend =
+            StorageBackend::new(tmp_dir.path(), mock_ds).expect("backend created successfully");
+        fs::create_dir(tmp_dir.path().join("bucket")).await.unwrap();
+        fs::create_dir(tmp_dir.path().join("other")).await.unwrap();
+        backend
+            .put_object_bytes("bucket", "key", Bytes::from_static(b"hello world"))
+            .await
+            .unwrap();
+
+        let err = backend
+            .move_object("bucket", "key", "missing", "c")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        // the data stays where the row points when the row cannot be moved
+        let result = backend.move_object("bucket", "key", "other", "a/b").await;
+        assert!(result.is_err());
+        assert!(tmp_dir.path().join("bucket/key").is_file());
+
+        assert!(
+            backend
+                .move_object("bucket", "key", "other", "a/b")
+                .await
+                .unwrap()
+        );
+        assert!(!tmp_dir.path().join("bucket/key").exists());
+        let data = fs::read(tmp_dir.path().join("other/a/b")).await.unwrap();
+        assert_eq!(data, b"hello world");
+        let item = items.lock().unwrap()[&("other".to_owned(), "a/b".to_owned())].clone();
+        assert_eq!(item.data_location, "other/a/b");
+
+        assert!(
+            !backend
+                .move_object("bucket", "key", "other", "c")
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clean_stale_multipart_uploads() {
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let part_path = tmp_dir.path().join(".upload_id-u1.part-1");
+        fs::write(&part_path, "part").await.unwrap();
+        let data_location = part_path.to_string_lossy().into_owned();
+
+        let mut mock_ds = MockTestDataStore::new();
+        mock_ds
+            .expect_get_stale_multipart_uploads()
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    MultipartUpload::builder()
+                        .upload_id("u1".to_string())
+                        .bucket("test_bucket".to_string())
+                        .key("test_key".to_string())
+                        .metadata("{}".to_string())
+                        .access_key("test_access".to_string())
+                        .build(),
+                ])
+            });
+        mock_ds
+            .expect_get_parts_by_upload_id()
+            .with(eq("u1"))
+            .times(1)
+            .returning(move |_| {
+                // a part whose file is already gone does not stop the cleanup
+                Ok(vec![
+                    MultipartUploadPart::builder()
+                        .upload_id("u1".to_string())
+                        .part_number(1)
+                        .md5("md5".to_string())
+                        .data_location(data_location.clone())
+                        .build(),
+                    MultipartUploadPart::builder()
+                        .upload_id("u1".to_string())
+                        .part_number(2)
+                        .md5("md5".to_string())
+                        .data_location(format!("{data_location}-missing"))
+                        .build(),
+                ])
+            });
+        mock_ds
+            .expect_delete_multipart_upload_by_upload_id()
+            .with(eq("u1"))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let older_than = chrono::Utc::now().naive_utc();
+        let removed = clean_stale_multipart_uploads(&mock_ds, older_than)
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(!part_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_file_write_tmp_paths_are_distinct() {
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend = StorageBackend::new(tmp_dir.path(), MockTestDataStore::new())
+            .expect("backend created successfully");
+        let dest = tmp_dir.path().join("dest");
+
+        let (a, b) = tokio::join!(
+            backend.prepare_file_write(&dest),
+            backend.prepare_file_write(&dest)
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_ne!(a.tmp_path(), b.tmp_path());
+        for writer in [&a, &b] {
+            let name = writer.tmp_path().file_name().unwrap().to_str().unwrap();
+            assert!(name.starts_with(&format!(".tmp.{}.", std::process::id())));
+            assert!(name.ends_with(".internal.part"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_durable_file_write() {
+        let tmp_dir = tempdir().expect("tempdir created successfully");
+        let backend = StorageBackend::new(tmp_dir.path(), MockTestDataStore::new())
+            .expect("backend created successfully")
+            .with_durable(true);
+        let dest = tmp_dir.path().join("bucket/dir/key");
+
+        // written without a flush, done() flushes before syncing
+        let mut writer = backend.prepare_file_write(&dest).await.unwrap();
+        writer.writer().write_all(b"durable").await.unwrap();
+        let tmp_path = writer.tmp_path().to_owned();
+        writer.done().await.unwrap();
+
+        assert!(!tmp_path.exists());
+        assert_eq!(fs::read(&dest).await.unwrap(), b"durable");
+
+        // parts written in place are synced as well
+        let assembly = tmp_dir.path().join("bucket/assembly");
+        let stream = futures::stream::iter([Ok::<_, StdError>(Bytes::from_static(b"part"))]);
+        assert_eq!(backend.write_at(&assembly, 4, stream).await.unwrap(), 4);
+        assert_eq!(fs::read(&assembly).await.unwrap(), b"\0\0\0\0part");
+        backend.sync_data_file(&dest).await.unwrap();
+        let err = backend
+            .sync_data_file(&tmp_dir.path().join("missing"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // nothing is synced unless asked for
+        let backend = backend.with_durable(false);
+        backend
+            .sync_data_file(&tmp_dir.path().join("missing"))
+            .await
+            .unwrap();
+    }
 }
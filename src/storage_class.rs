@@ -0,0 +1,38 @@
+use s3s::dto::StorageClass;
+
+use crate::storage_backend::InternalInfo;
+
+/// Storage class of objects stored without one.
+///
+/// Every class is kept on the same disk, the class given on upload is only recorded
+/// so it can be reported back.
+pub const DEFAULT_STORAGE_CLASS: &str = StorageClass::STANDARD;
+
+pub fn modify_internal_info(info: &mut InternalInfo, storage_class: &str) {
+    info.insert(
+        "storage_class".to_owned(),
+        serde_json::Value::String(storage_class.to_owned()),
+    );
+}
+
+/// The recorded storage class, [`DEFAULT_STORAGE_CLASS`] when none was given.
+pub fn from_internal_info(info: &InternalInfo) -> String {
+    info.get("storage_class")
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_STORAGE_CLASS)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_info_round_trip() {
+        let mut info = InternalInfo::new();
+        assert_eq!(from_internal_info(&info), "STANDARD");
+
+        modify_internal_info(&mut info, StorageClass::STANDARD_IA);
+        assert_eq!(from_internal_info(&info), "STANDARD_IA");
+    }
+}
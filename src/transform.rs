@@ -0,0 +1,29 @@
+use s3s::dto::{Metadata, StreamingBlob};
+
+/// The object whose body is about to be served, as seen by an [`ObjectTransform`].
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub bucket: String,
+    pub key: String,
+    /// `None` for objects stored without versioning.
+    pub version_id: Option<String>,
+    /// The stored `Content-Type`, `application/octet-stream` when there is none.
+    pub content_type: mime::Mime,
+    pub metadata: Metadata,
+}
+
+/// Post-processes the body of objects served by `GetObject`, e.g. to resize images or
+/// redact documents on the fly, like S3 Object Lambda without the round trip through
+/// `WriteGetObjectResponse`.
+///
+/// A transformed body has no known length and is sent chunked, without the checksums of
+/// the stored object. Its `ETag` stays the one of the stored object. Ranges and part
+/// numbers cannot be served from a transformed object, they fail with `NotImplemented`.
+/// `HeadObject` describes the stored object.
+pub trait ObjectTransform: Send + Sync + std::fmt::Debug + 'static {
+    /// Whether the body of `object` is transformed, others are served as stored.
+    fn applies_to(&self, object: &ObjectInfo) -> bool;
+
+    /// The body to serve instead of `body`, the decrypted data of the stored object.
+    fn transform(&self, object: &ObjectInfo, body: StreamingBlob) -> StreamingBlob;
+}
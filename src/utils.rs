@@ -1,7 +1,12 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
 use bytes::Bytes;
 use futures::{Stream, StreamExt, pin_mut};
+use hyper::HeaderMap;
+use md5::{Digest, Md5};
 use path_absolutize::Absolutize;
 use s3s::{
     S3Result, StdError,
@@ -10,7 +15,11 @@ use s3s::{
     s3_error,
 };
 use stdx::default::default;
-use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
+};
+use tracing::warn;
 use transform_stream::AsyncTryStream;
 
 use crate::error::*;
@@ -60,13 +69,78 @@ pub fn hex(input: impl AsRef<[u8]>) -> String {
     hex_simd::encode_to_string(input.as_ref(), hex_simd::AsciiCase::Lower)
 }
 
-pub fn to_timestamp(datetime: &chrono::NaiveDateTime) -> Option<Timestamp> {
-    let date_time_rfc3339 = datetime.and_utc().to_rfc3339();
+/// Percent-encodes a key the way listings with `encoding-type=url` return it: every byte
+/// but unreserved characters and `/`, spaces included as `%20`.
+pub fn url_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            // writing to a string cannot fail
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Decodes a percent-encoded query value, `+` standing for a space as in forms.
+/// Malformed escapes are kept as they are, `None` when the result is not UTF-8.
+pub fn url_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        if let Some(byte) = escaped {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+pub fn to_timestamp(datetime: &chrono::NaiveDateTime) -> Timestamp {
+    Timestamp::from(std::time::SystemTime::from(datetime.and_utc()))
+}
+
+pub fn from_timestamp(timestamp: &Timestamp) -> Option<chrono::NaiveDateTime> {
+    let date_time = format_timestamp(timestamp, TimestampFormat::DateTime)?;
+    Some(
+        chrono::DateTime::parse_from_rfc3339(&date_time)
+            .ok()?
+            .naive_utc(),
+    )
+}
+
+/// Formats a timestamp the way it is serialized in responses, [`TimestampFormat::HttpDate`]
+/// (RFC 1123) in headers like `Last-Modified` and [`TimestampFormat::DateTime`] (RFC 3339)
+/// in XML bodies.
+pub fn format_timestamp(timestamp: &Timestamp, format: TimestampFormat) -> Option<String> {
+    let mut buf = Vec::new();
+    timestamp.format(format, &mut buf).ok()?;
+    String::from_utf8(buf).ok()
+}
 
-    match Timestamp::parse(TimestampFormat::DateTime, date_time_rfc3339.as_str()) {
-        Ok(t) => Some(t),
-        Err(_) => None,
+/// Hex MD5 digest of the content of a file.
+pub async fn md5_sum(path: &Path) -> Result<String> {
+    let mut file = File::open(path).await?;
+    let mut buf = vec![0; 65536];
+    let mut md5_hash = Md5::new();
+    loop {
+        let nread = file.read(&mut buf).await?;
+        if nread == 0 {
+            break;
+        }
+        md5_hash.update(&buf[..nread]);
     }
+    Ok(hex(md5_hash.finalize()))
 }
 
 pub fn resolve_abs_path(root_path: &PathBuf, path: impl AsRef<Path>) -> Result<PathBuf> {
@@ -107,11 +181,86 @@ pub fn metadata_to_string(metadata: Option<&dto::Metadata>) -> String {
     }
 }
 
+/// Bytes of user-defined metadata S3 accepts on an object.
+pub const MAX_METADATA_SIZE: usize = 2048;
+
+/// Rejects user-defined metadata larger than S3 accepts, measured as the UTF-8 bytes
+/// of every key and value.
+pub fn check_metadata_size(metadata: Option<&dto::Metadata>) -> S3Result<()> {
+    let size = metadata.map_or(0, |metadata| {
+        metadata
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    });
+    if size > MAX_METADATA_SIZE {
+        return Err(s3_error!(
+            MetadataTooLarge,
+            "the metadata is {size} bytes, at most {MAX_METADATA_SIZE} are allowed"
+        ));
+    }
+    Ok(())
+}
+
 /// convert metadata in string to Metadata
 pub fn metadata_from_string(metadata: &str) -> dto::Metadata {
     serde_json::from_str(metadata).unwrap_or_default()
 }
 
+/// Bytes of `x-amz-meta-*` headers returned with an object, well below the 8 KiB header
+/// limits common to clients and proxies.
+pub const METADATA_HEADER_BUDGET: usize = 4096;
+
+/// Metadata returned as `x-amz-meta-*` headers and the number of entries left out
+/// to stay within [`METADATA_HEADER_BUDGET`].
+///
+/// Metadata stored before its size was limited may not fit, entries are then kept in
+/// key order until the budget is spent.
+pub fn metadata_headers(metadata: &str) -> (Option<dto::Metadata>, Option<i32>) {
+    let Ok(metadata) = serde_json::from_str::<dto::Metadata>(metadata) else {
+        return (None, None);
+    };
+    let header_len =
+        |(key, value): (&String, &String)| "x-amz-meta-: \r\n".len() + key.len() + value.len();
+    if metadata.iter().map(header_len).sum::<usize>() <= METADATA_HEADER_BUDGET {
+        return (Some(metadata), None);
+    }
+
+    let mut entries = metadata.iter().collect::<Vec<_>>();
+    entries.sort_unstable();
+    let mut budget = METADATA_HEADER_BUDGET;
+    let mut kept = dto::Metadata::new();
+    for (key, value) in entries {
+        let len = header_len((key, value));
+        if len <= budget {
+            budget -= len;
+            kept.insert(key.clone(), value.clone());
+        }
+    }
+    let missing = metadata.len() - kept.len();
+    warn!(
+        missing,
+        "stored metadata exceeds the header budget, entries are left out"
+    );
+    (Some(kept), i32::try_from(missing).ok())
+}
+
+/// Host the request addressed `bucket` by when it is virtual-hosted-style,
+/// `bucket.domain` for one of `domains`, with its port if any.
+pub fn virtual_host<'a>(
+    headers: &'a HeaderMap,
+    bucket: &str,
+    domains: &[String],
+) -> Option<&'a str> {
+    let host = headers.get(hyper::header::HOST)?.to_str().ok()?;
+    let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    let domain = name.strip_prefix(bucket)?.strip_prefix('.')?;
+    domains
+        .iter()
+        .any(|d| d.eq_ignore_ascii_case(domain))
+        .then_some(host)
+}
+
 /// retrieve the access key from Credentials
 pub fn access_key_from_creds(cred: Option<&Credentials>) -> Option<&str> {
     cred.map(|c| c.access_key.as_str())
@@ -142,3 +291,32 @@ pub fn validate_checksums(
     }
     Ok(())
 }
+
+/// Whether an `If-Match` / `If-None-Match` value, `*` or a list of entity tags, matches `e_tag`.
+fn e_tag_matches(condition: &str, e_tag: &str) -> bool {
+    condition.split(',').map(str::trim).any(|c| {
+        c == "*" || c.trim_start_matches("W/").trim_matches('"') == e_tag.trim_matches('"')
+    })
+}
+
+/// Checks the `If-Match` and `If-None-Match` conditions of a write against the entity tag
+/// of the current object, `None` when there is none.
+pub fn check_write_conditions(
+    current_e_tag: Option<&str>,
+    if_match: Option<&str>,
+    if_none_match: Option<&str>,
+) -> S3Result<()> {
+    if let Some(condition) = if_match {
+        match current_e_tag {
+            Some(e_tag) if e_tag_matches(condition, e_tag) => {}
+            Some(_) => return Err(s3_error!(PreconditionFailed)),
+            None => return Err(s3_error!(NoSuchKey)),
+        }
+    }
+    if let Some(condition) = if_none_match
+        && current_e_tag.is_some_and(|e_tag| e_tag_matches(condition, e_tag))
+    {
+        return Err(s3_error!(PreconditionFailed));
+    }
+    Ok(())
+}